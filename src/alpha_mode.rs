@@ -0,0 +1,72 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::shader::DecodingScheme;
+
+/// How the alpha channel of a `DecodingScheme` is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum AlphaMode {
+    #[default]
+    Ignore,
+    Checkerboard,
+    PremultipliedCheckerboard,
+    AlphaOnly,
+}
+
+impl AlphaMode {
+    pub const ALL: &'static [Self] = &[
+        Self::Ignore,
+        Self::Checkerboard,
+        Self::PremultipliedCheckerboard,
+        Self::AlphaOnly,
+    ];
+
+    /// Schemes without any alpha bits are always shown as they are.
+    pub fn for_scheme(self, decoding_scheme: &DecodingScheme) -> Self {
+        if decoding_scheme.has_alpha() {
+            self
+        } else {
+            AlphaMode::Ignore
+        }
+    }
+
+    /// Value of the `alpha_mode` uniform, matching the `ALPHA_MODE_*`
+    /// constants in `shader.wgsl`.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            AlphaMode::Ignore => 0,
+            AlphaMode::Checkerboard => 1,
+            AlphaMode::PremultipliedCheckerboard => 2,
+            AlphaMode::AlphaOnly => 3,
+        }
+    }
+}
+
+impl Display for AlphaMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AlphaMode::Ignore => "ignore",
+            AlphaMode::Checkerboard => "checkerboard",
+            AlphaMode::PremultipliedCheckerboard => "premultiplied",
+            AlphaMode::AlphaOnly => "alpha-only",
+        })
+    }
+}
+
+impl FromStr for AlphaMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AlphaMode::ALL
+            .iter()
+            .find(|mode| mode.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names = AlphaMode::ALL
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("unknown alpha mode {s:?}, expected one of: {names}")
+            })
+    }
+}
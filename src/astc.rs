@@ -0,0 +1,811 @@
+//! Decoding of ASTC blocks with 4x4 footprints and LDR colors, following
+//! the block layout in the Khronos Data Format Specification. Illegal
+//! blocks, and pixels whose endpoints need the HDR profile, decode to the
+//! error color.
+
+use super::preview::Pixel;
+
+/// Magenta, what the specification has illegal blocks decode to.
+const ERROR_COLOR: Pixel = Pixel {
+    red: 255,
+    green: 0,
+    blue: 255,
+    alpha: 255,
+};
+
+/// Pixels across and down a block.
+const FOOTPRINT: usize = 4;
+
+/// How the values of a range are packed: as plain bits, or as bits plus a
+/// trit or quint packed with those of the values either side.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Packing {
+    Bits,
+    Trits,
+    Quints,
+}
+
+/// Ranges of integer sequences, smallest first, as their packing and
+/// plain bits per value. Weights use the first 12.
+const RANGES: [(Packing, u32); 21] = [
+    (Packing::Bits, 1),
+    (Packing::Trits, 0),
+    (Packing::Bits, 2),
+    (Packing::Quints, 0),
+    (Packing::Trits, 1),
+    (Packing::Bits, 3),
+    (Packing::Quints, 1),
+    (Packing::Trits, 2),
+    (Packing::Bits, 4),
+    (Packing::Quints, 2),
+    (Packing::Trits, 3),
+    (Packing::Bits, 5),
+    (Packing::Quints, 3),
+    (Packing::Trits, 4),
+    (Packing::Bits, 6),
+    (Packing::Quints, 4),
+    (Packing::Trits, 5),
+    (Packing::Bits, 7),
+    (Packing::Quints, 5),
+    (Packing::Trits, 6),
+    (Packing::Bits, 8),
+];
+
+/// The smallest range endpoints can be packed in, 0 to 5.
+const MIN_COLOR_RANGE: usize = 4;
+
+/// Most endpoint values a block can have, four partitions of RGB or one
+/// of RGBA being the most.
+const MAX_COLOR_VALUES: usize = 18;
+
+/// Most weights a block can have.
+const MAX_WEIGHTS: usize = 64;
+
+/// Reads fields from the bits of a block, lowest bit first.
+struct BitReader {
+    bits: u128,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        let value = self.bits & ((1 << count) - 1);
+        self.bits >>= count;
+        value as u32
+    }
+}
+
+/// `count` bits of `block` from `low` up.
+fn bits(block: u128, low: u32, count: u32) -> u32 {
+    ((block >> low) & ((1 << count) - 1)) as u32
+}
+
+/// The grid of weights a block has, and the range they're packed in.
+struct BlockMode {
+    width: usize,
+    height: usize,
+    /// Whether one channel has weights of its own
+    dual_plane: bool,
+    weight_range: usize,
+}
+
+impl BlockMode {
+    /// Reads the block mode from the lowest 11 bits of a block, if it's one
+    /// a 4x4 block can have.
+    fn decode(mode: u32) -> Option<BlockMode> {
+        let bit = |i: u32| (mode >> i) & 1;
+        let a = (mode >> 5) & 3;
+
+        let (width, height, precision, dual_plane, range) = if mode & 3 != 0 {
+            let b = (mode >> 7) & 3;
+            let (width, height) = match (mode >> 2) & 3 {
+                0 => (b + 4, a + 2),
+                1 => (b + 8, a + 2),
+                2 => (a + 2, b + 8),
+                _ if bit(8) == 0 => (a + 2, (b & 1) + 6),
+                _ => ((b & 1) + 2, a + 2),
+            };
+            (width, height, bit(9), bit(10), bit(4) | (mode & 3) << 1)
+        } else {
+            let range = bit(4) | ((mode >> 2) & 3) << 1;
+            if range < 2 {
+                return None;
+            }
+            let b = (mode >> 9) & 3;
+            let (width, height, precision, dual_plane) = match (mode >> 7) & 3 {
+                0 => (12, a + 2, bit(9), bit(10)),
+                1 => (a + 2, 12, bit(9), bit(10)),
+                2 => (a + 6, b + 6, 0, 0),
+                _ => match a {
+                    0 => (6, 10, bit(9), bit(10)),
+                    1 => (10, 6, bit(9), bit(10)),
+                    _ => return None,
+                },
+            };
+            (width, height, precision, dual_plane, range)
+        };
+
+        let (width, height) = (width as usize, height as usize);
+        if width > FOOTPRINT || height > FOOTPRINT {
+            return None;
+        }
+        Some(BlockMode {
+            width,
+            height,
+            dual_plane: dual_plane == 1,
+            weight_range: (range - 2 + precision * 6) as usize,
+        })
+    }
+}
+
+/// Bits `count` values in `range` take up.
+fn packed_bits(range: usize, count: usize) -> u32 {
+    let (packing, bits) = RANGES[range];
+    let count = count as u32;
+    count * bits
+        + match packing {
+            Packing::Bits => 0,
+            Packing::Trits => (8 * count).div_ceil(5),
+            Packing::Quints => (7 * count).div_ceil(3),
+        }
+}
+
+/// Unpacks `values.len()` values in `range` from the lowest bits of
+/// `packed`, whose bits past the sequence must be 0.
+fn unpack(packed: u128, range: usize, values: &mut [u32]) {
+    let (packing, bits) = RANGES[range];
+    let mut reader = BitReader { bits: packed };
+
+    match packing {
+        Packing::Bits => {
+            for value in values {
+                *value = reader.read(bits);
+            }
+        }
+        // Five values share 8 bits of trits, spread out between them
+        Packing::Trits => {
+            for group in values.chunks_mut(5) {
+                let mut low = [0; 5];
+                let mut packed_trits = 0;
+                for (i, (shift, count)) in [(0, 2), (2, 2), (4, 1), (5, 2), (7, 1)]
+                    .into_iter()
+                    .enumerate()
+                {
+                    low[i] = reader.read(bits);
+                    packed_trits |= reader.read(count) << shift;
+                }
+                let trits = decode_trits(packed_trits);
+                for (i, value) in group.iter_mut().enumerate() {
+                    *value = trits[i] << bits | low[i];
+                }
+            }
+        }
+        // Three values share 7 bits of quints
+        Packing::Quints => {
+            for group in values.chunks_mut(3) {
+                let mut low = [0; 3];
+                let mut packed_quints = 0;
+                for (i, (shift, count)) in [(0, 3), (3, 2), (5, 2)].into_iter().enumerate() {
+                    low[i] = reader.read(bits);
+                    packed_quints |= reader.read(count) << shift;
+                }
+                let quints = decode_quints(packed_quints);
+                for (i, value) in group.iter_mut().enumerate() {
+                    *value = quints[i] << bits | low[i];
+                }
+            }
+        }
+    }
+}
+
+fn decode_trits(packed: u32) -> [u32; 5] {
+    let bit = |value: u32, i: u32| (value >> i) & 1;
+
+    let (c, t3, t4) = if (packed >> 2) & 7 == 7 {
+        ((packed >> 5 & 7) << 2 | (packed & 3), 2, 2)
+    } else if (packed >> 5) & 3 == 3 {
+        (packed & 0x1F, bit(packed, 7), 2)
+    } else {
+        (packed & 0x1F, (packed >> 5) & 3, bit(packed, 7))
+    };
+
+    let (t0, t1, t2) = if c & 3 == 3 {
+        (bit(c, 3) << 1 | (bit(c, 2) & !bit(c, 3) & 1), bit(c, 4), 2)
+    } else if (c >> 2) & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        (
+            bit(c, 1) << 1 | (bit(c, 0) & !bit(c, 1) & 1),
+            (c >> 2) & 3,
+            bit(c, 4),
+        )
+    };
+    [t0, t1, t2, t3, t4]
+}
+
+fn decode_quints(packed: u32) -> [u32; 3] {
+    let bit = |i: u32| (packed >> i) & 1;
+
+    if (packed >> 1) & 3 == 3 && (packed >> 5) & 3 == 0 {
+        let not0 = !bit(0) & 1;
+        return [4, 4, bit(0) << 2 | (bit(4) & not0) << 1 | (bit(3) & not0)];
+    }
+    let (c, q2) = if (packed >> 1) & 3 == 3 {
+        ((packed >> 3 & 3) << 3 | (!packed >> 5 & 3) << 1 | bit(0), 4)
+    } else {
+        (packed & 0x1F, (packed >> 5) & 3)
+    };
+    let (q0, q1) = if c & 7 == 5 {
+        ((c >> 3) & 3, 4)
+    } else {
+        (c & 7, (c >> 3) & 3)
+    };
+    [q0, q1, q2]
+}
+
+/// Widens a `bits` bit value to `to` bits by repeating its bits.
+fn replicate(value: u32, bits: u32, to: u32) -> u32 {
+    let mut result = 0;
+    let mut filled = 0;
+    while filled < to {
+        result = result << bits | value;
+        filled += bits;
+    }
+    result >> (filled - to)
+}
+
+/// Scales an endpoint value in `range` to 0 to 255.
+fn unquantize_color(value: u32, range: usize) -> i32 {
+    let (packing, bits) = RANGES[range];
+    if packing == Packing::Bits {
+        return replicate(value, bits, 8) as i32;
+    }
+
+    // The trit or quint is scaled by `c`, and the bits above the lowest
+    // shuffled into `b`
+    let low = value & ((1 << bits) - 1);
+    let d = value >> bits;
+    let a = if low & 1 == 1 { 0x1FF } else { 0 };
+    let (x, y, z) = (low >> 1 & 1, low >> 1 & 3, low >> 1 & 7);
+    let (b, c) = match (packing, bits) {
+        (Packing::Trits, 1) => (0, 204),
+        (Packing::Quints, 1) => (0, 113),
+        (Packing::Trits, 2) => (x << 8 | x << 4 | x << 2 | x << 1, 93),
+        (Packing::Quints, 2) => (x << 8 | x << 3 | x << 2, 54),
+        (Packing::Trits, 3) => (y << 7 | y << 2 | y, 44),
+        (Packing::Quints, 3) => (y << 7 | y << 1 | y >> 1, 26),
+        (Packing::Trits, 4) => (z << 6 | z, 22),
+        (Packing::Quints, 4) => (z << 6 | z >> 1, 13),
+        (Packing::Trits, 5) => ((low >> 1) << 5 | (low >> 3), 11),
+        (Packing::Quints, 5) => ((low >> 1) << 5 | (low >> 4), 6),
+        (Packing::Trits, _) => ((low >> 1) << 4 | (low >> 5), 5),
+        _ => unreachable!("no endpoint range is packed that way"),
+    };
+    let t = (d * c + b) ^ a;
+    ((a & 0x80) | t >> 2) as i32
+}
+
+/// Scales a weight in `range` to 0 to 64.
+fn unquantize_weight(value: u32, range: usize) -> u32 {
+    let (packing, bits) = RANGES[range];
+    let weight = match (packing, bits) {
+        (Packing::Bits, _) => replicate(value, bits, 6),
+        (Packing::Trits, 0) => [0, 32, 63][value as usize],
+        (Packing::Quints, 0) => [0, 16, 32, 47, 63][value as usize],
+        _ => {
+            let low = value & ((1 << bits) - 1);
+            let d = value >> bits;
+            let a = if low & 1 == 1 { 0x7F } else { 0 };
+            let (x, y) = (low >> 1 & 1, low >> 1 & 3);
+            let (b, c) = match (packing, bits) {
+                (Packing::Trits, 1) => (0, 50),
+                (Packing::Quints, 1) => (0, 28),
+                (Packing::Trits, 2) => (x << 6 | x << 2 | x, 23),
+                (Packing::Quints, 2) => (x << 6 | x << 1, 13),
+                (Packing::Trits, _) => (y << 5 | y, 11),
+                _ => unreachable!("no weight range is packed that way"),
+            };
+            let t = (d * c + b) ^ a;
+            (a & 0x20) | t >> 2
+        }
+    };
+    if weight > 32 {
+        weight + 1
+    } else {
+        weight
+    }
+}
+
+/// Moves the top bit of `b` into `a`, leaving `b` a signed 6 bit offset.
+fn bit_transfer_signed(a: i32, b: i32) -> (i32, i32) {
+    let a = (a >> 1) | (b & 0x80);
+    let b = (b >> 1) & 0x3F;
+    (a, if b & 0x20 != 0 { b - 0x40 } else { b })
+}
+
+/// Averages red and green with blue, undoing what the encoder did to
+/// spend fewer bits on them.
+fn blue_contract([red, green, blue, alpha]: [i32; 4]) -> [i32; 4] {
+    [(red + blue) >> 1, (green + blue) >> 1, blue, alpha]
+}
+
+/// The two endpoints of color endpoint mode `mode` from its values, or
+/// none for the HDR modes.
+fn endpoints(mode: u32, v: &[i32]) -> Option<[[i32; 4]; 2]> {
+    let endpoints = match mode {
+        // Luminance
+        0 => [[v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]],
+        // Luminance, base and offset
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            [[l0, l0, l0, 255], [l1, l1, l1, 255]]
+        }
+        // Luminance and alpha
+        4 => [[v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]],
+        // Luminance and alpha, base and offset
+        5 => {
+            let (l0, l1) = bit_transfer_signed(v[0], v[1]);
+            let (a0, a1) = bit_transfer_signed(v[2], v[3]);
+            let l1 = l0 + l1;
+            [[l0, l0, l0, a0], [l1, l1, l1, a0 + a1]]
+        }
+        // RGB, and a scale for the first endpoint
+        6 | 10 => {
+            let (a0, a1) = if mode == 10 { (v[4], v[5]) } else { (255, 255) };
+            let scale = |channel: i32| (channel * v[3]) >> 8;
+            [
+                [scale(v[0]), scale(v[1]), scale(v[2]), a0],
+                [v[0], v[1], v[2], a1],
+            ]
+        }
+        // RGB or RGBA, with blue contraction when the second is darker
+        8 | 12 => {
+            let (a0, a1) = if mode == 12 { (v[6], v[7]) } else { (255, 255) };
+            let e0 = [v[0], v[2], v[4], a0];
+            let e1 = [v[1], v[3], v[5], a1];
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                [e0, e1]
+            } else {
+                [blue_contract(e1), blue_contract(e0)]
+            }
+        }
+        // RGB or RGBA, base and offset
+        9 | 13 => {
+            let (r0, r1) = bit_transfer_signed(v[0], v[1]);
+            let (g0, g1) = bit_transfer_signed(v[2], v[3]);
+            let (b0, b1) = bit_transfer_signed(v[4], v[5]);
+            let (a0, a1) = if mode == 13 {
+                bit_transfer_signed(v[6], v[7])
+            } else {
+                (255, 0)
+            };
+            let base = [r0, g0, b0, a0];
+            let offset = [r0 + r1, g0 + g1, b0 + b1, a0 + a1];
+            if r1 + g1 + b1 >= 0 {
+                [base, offset]
+            } else {
+                [blue_contract(offset), blue_contract(base)]
+            }
+        }
+        _ => return None,
+    };
+    Some(endpoints.map(|endpoint| endpoint.map(|channel| channel.clamp(0, 255))))
+}
+
+/// Which of `partitions` partitions the pixel at `x`, `y` is in, from the
+/// hash the specification uses to lay partitions out.
+fn partition_of(seed: u32, partitions: u32, x: u32, y: u32) -> usize {
+    // Blocks of fewer than 31 pixels use every other position
+    let (x, y) = (x << 1, y << 1);
+    let seed = seed + (partitions - 1) * 1024;
+
+    let mut rnum = seed;
+    rnum ^= rnum >> 15;
+    rnum = rnum.wrapping_sub(rnum << 17);
+    rnum = rnum.wrapping_add(rnum << 7);
+    rnum = rnum.wrapping_add(rnum << 4);
+    rnum ^= rnum >> 5;
+    rnum = rnum.wrapping_add(rnum << 16);
+    rnum ^= rnum >> 7;
+    rnum ^= rnum >> 3;
+    rnum ^= rnum << 6;
+    rnum ^= rnum >> 17;
+
+    let mut seeds: [u32; 8] = std::array::from_fn(|i| {
+        let seed = (rnum >> (i * 4)) & 0xF;
+        seed * seed
+    });
+    let (sh1, sh2) = match (seed & 1 == 1, seed & 2 == 2, partitions == 3) {
+        (true, two, three) => (if two { 4 } else { 5 }, if three { 6 } else { 5 }),
+        (false, two, three) => (if three { 6 } else { 5 }, if two { 4 } else { 5 }),
+    };
+    for (i, seed) in seeds.iter_mut().enumerate() {
+        *seed >>= if i % 2 == 0 { sh1 } else { sh2 };
+    }
+
+    let mut scores = [
+        seeds[0] * x + seeds[1] * y + (rnum >> 14),
+        seeds[2] * x + seeds[3] * y + (rnum >> 10),
+        seeds[4] * x + seeds[5] * y + (rnum >> 6),
+        seeds[6] * x + seeds[7] * y + (rnum >> 2),
+    ]
+    .map(|score| score & 0x3F);
+    for score in &mut scores[partitions as usize..] {
+        *score = 0;
+    }
+
+    // The first partition with the highest score
+    (0..4).rev().max_by_key(|&i| scores[i]).unwrap_or(0)
+}
+
+/// Decodes a 16 byte ASTC block with a 4x4 footprint into its pixels, row
+/// by row. Illegal blocks, and pixels whose endpoints need the HDR
+/// profile, are magenta.
+pub fn decode_block(block: &[u8; 16]) -> [Pixel; 16] {
+    decode(u128::from_le_bytes(*block)).unwrap_or([ERROR_COLOR; 16])
+}
+
+fn decode(block: u128) -> Option<[Pixel; 16]> {
+    if block & 0x1FF == 0x1FC {
+        return decode_void_extent(block);
+    }
+
+    let mode = BlockMode::decode(bits(block, 0, 11))?;
+    let partitions = bits(block, 11, 2) + 1;
+    let planes = if mode.dual_plane { 2 } else { 1 };
+    let weight_count = mode.width * mode.height * planes;
+    let weight_bits = packed_bits(mode.weight_range, weight_count);
+    if weight_count > MAX_WEIGHTS
+        || !(24..=96).contains(&weight_bits)
+        || (mode.dual_plane && partitions == 4)
+    {
+        return None;
+    }
+
+    // Fields that don't fit below the partitions are stored just below the
+    // weights, which fill the block from the top down
+    let mut below_weights = 128 - weight_bits;
+    let mut modes = [0; 4];
+    let color_start = if partitions == 1 {
+        modes[0] = bits(block, 13, 4);
+        17
+    } else {
+        let low = bits(block, 23, 6);
+        if low & 3 == 0 {
+            modes = [low >> 2; 4];
+        } else {
+            // A class shared by all partitions, which each can go one above
+            let high_bits = 3 * partitions - 4;
+            below_weights = below_weights.checked_sub(high_bits)?;
+            let encoded = low | bits(block, below_weights, high_bits) << 6;
+            let class = (encoded & 3) - 1;
+            for (i, mode) in modes[..partitions as usize].iter_mut().enumerate() {
+                let i = i as u32;
+                let raised = (encoded >> (2 + i)) & 1;
+                let mode_in_class = (encoded >> (2 + partitions + i * 2)) & 3;
+                *mode = (class + raised) << 2 | mode_in_class;
+            }
+        }
+        29
+    };
+    let plane2_channel = if mode.dual_plane {
+        below_weights = below_weights.checked_sub(2)?;
+        Some(bits(block, below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    // Endpoints get the largest range that fits in the bits left
+    let modes = &modes[..partitions as usize];
+    let color_count: usize = modes.iter().map(|&mode| (mode as usize >> 2) * 2 + 2).sum();
+    let color_bits = below_weights.checked_sub(color_start)?;
+    if color_count > MAX_COLOR_VALUES {
+        return None;
+    }
+    let color_range = (0..RANGES.len())
+        .rev()
+        .find(|&range| packed_bits(range, color_count) <= color_bits)
+        .filter(|&range| range >= MIN_COLOR_RANGE)?;
+
+    let mut colors = [0; MAX_COLOR_VALUES];
+    let color_data = block >> color_start;
+    let color_len = packed_bits(color_range, color_count);
+    unpack(
+        color_data & ((1 << color_len) - 1),
+        color_range,
+        &mut colors[..color_count],
+    );
+    let colors = colors.map(|value| unquantize_color(value, color_range));
+
+    // Partitions with HDR endpoints are left as none, for their pixels to
+    // be the error color
+    let mut color_endpoints = [None; 4];
+    let mut next = 0;
+    for (endpoints_of, &mode) in color_endpoints.iter_mut().zip(modes) {
+        let count = (mode as usize >> 2) * 2 + 2;
+        *endpoints_of = endpoints(mode, &colors[next..next + count]);
+        next += count;
+    }
+
+    // Weights are stored bit reversed from the top of the block down
+    let mut weights = [0; MAX_WEIGHTS];
+    let weight_data = block.reverse_bits() & ((1 << weight_bits) - 1);
+    unpack(weight_data, mode.weight_range, &mut weights[..weight_count]);
+    let weights = weights.map(|weight| unquantize_weight(weight, mode.weight_range));
+    let weight_at =
+        |x: usize, y: usize, plane: usize| -> u32 { infill(&weights, &mode, planes, x, y, plane) };
+
+    let partition_seed = bits(block, 13, 10);
+    Some(std::array::from_fn(|pixel| {
+        let (x, y) = (pixel % FOOTPRINT, pixel / FOOTPRINT);
+        let partition = match partitions {
+            1 => 0,
+            _ => partition_of(partition_seed, partitions, x as u32, y as u32),
+        };
+        let Some([e0, e1]) = color_endpoints[partition] else {
+            return ERROR_COLOR;
+        };
+        let weight = weight_at(x, y, 0);
+        let plane2_weight = plane2_channel.map(|_| weight_at(x, y, 1));
+
+        let channel = |i: usize| -> u8 {
+            let weight = match plane2_weight {
+                Some(plane2_weight) if plane2_channel == Some(i) => plane2_weight,
+                _ => weight,
+            };
+            interpolate(e0[i], e1[i], weight)
+        };
+        Pixel {
+            red: channel(0),
+            green: channel(1),
+            blue: channel(2),
+            alpha: channel(3),
+        }
+    }))
+}
+
+/// The weight of the pixel at `x`, `y` in one plane, bilinearly
+/// interpolated from the grid of weights, which can be smaller than the
+/// block.
+fn infill(
+    weights: &[u32],
+    mode: &BlockMode,
+    planes: usize,
+    x: usize,
+    y: usize,
+    plane: usize,
+) -> u32 {
+    let scale = (1024 + FOOTPRINT / 2) / (FOOTPRINT - 1);
+    let gs = (scale * x * (mode.width - 1) + 32) >> 6;
+    let gt = (scale * y * (mode.height - 1) + 32) >> 6;
+    let (js, fs) = (gs >> 4, (gs & 0xF) as u32);
+    let (jt, ft) = (gt >> 4, (gt & 0xF) as u32);
+
+    let w11 = (fs * ft + 8) >> 4;
+    let w10 = ft - w11;
+    let w01 = fs - w11;
+    let w00 = 16 + w11 - fs - ft;
+
+    // Points past the edge of the grid only ever get no weight
+    let at = |s: usize, t: usize| -> u32 {
+        let index = (t * mode.width + s) * planes + plane;
+        weights.get(index).copied().unwrap_or(0)
+    };
+    (at(js, jt) * w00 + at(js + 1, jt) * w01 + at(js, jt + 1) * w10 + at(js + 1, jt + 1) * w11 + 8)
+        >> 4
+}
+
+/// Blends two 8 bit endpoints as 16 bit values, keeping the top 8 bits.
+fn interpolate(e0: i32, e1: i32, weight: u32) -> u8 {
+    let (c0, c1) = ((e0 as u32) * 257, (e1 as u32) * 257);
+    let value = (c0 * (64 - weight) + c1 * weight + 32) >> 6;
+    (value >> 8) as u8
+}
+
+/// A block of a single color, stored as 16 bit values.
+fn decode_void_extent(block: u128) -> Option<[Pixel; 16]> {
+    // HDR colors are half floats, which the LDR profile can't decode
+    if bits(block, 9, 1) == 1 {
+        return None;
+    }
+    // The texture coordinates the color covers, which must all be set or
+    // make a rectangle
+    let coordinate = |i: u32| bits(block, 12 + i * 13, 13);
+    let all_set = (0..4).all(|i| coordinate(i) == 0x1FFF);
+    if !all_set && (coordinate(0) >= coordinate(1) || coordinate(2) >= coordinate(3)) {
+        return None;
+    }
+
+    let channel = |i: u32| (bits(block, 64 + i * 16, 16) >> 8) as u8;
+    Some(
+        [Pixel {
+            red: channel(0),
+            green: channel(1),
+            blue: channel(2),
+            alpha: channel(3),
+        }; 16],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(pixel: Pixel) -> [u8; 4] {
+        [pixel.red, pixel.green, pixel.blue, pixel.alpha]
+    }
+
+    #[test]
+    fn void_extent() {
+        // 0x1000, 0x8000, 0xF000 and 0xFFFF over the whole texture
+        let block = [
+            0xFC, 0xFD, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x10, 0x00, 0x80, 0x00, 0xF0,
+            0xFF, 0xFF,
+        ];
+        assert_eq!(decode_block(&block).map(rgba), [[16, 128, 240, 255]; 16]);
+
+        // Half floats need the HDR profile
+        let mut hdr = block;
+        hdr[1] |= 0x02;
+        assert_eq!(decode_block(&hdr), [ERROR_COLOR; 16]);
+    }
+
+    #[test]
+    fn trits_and_quints() {
+        // Every packing of five trits and three quints comes back out
+        for trits in 0..243 {
+            let expected: [u32; 5] = std::array::from_fn(|i| trits / 3u32.pow(i as u32) % 3);
+            assert!((0..256).any(|packed| decode_trits(packed) == expected));
+        }
+        for quints in 0..125 {
+            let expected: [u32; 3] = std::array::from_fn(|i| quints / 5u32.pow(i as u32) % 5);
+            assert!((0..128).any(|packed| decode_quints(packed) == expected));
+        }
+    }
+
+    /// Blocks of each kind with the pixels Mesa's ASTC decoder (llvmpipe,
+    /// Mesa 22.3.6) reads out of them, as 0xRRGGBBAA row by row.
+    const REFERENCE_BLOCKS: [([u8; 16], [u32; 16]); 11] = [
+        // Void extent
+        (
+            [
+                0xfc, 0x11, 0xd9, 0xca, 0x77, 0xda, 0x50, 0x84, 0x7f, 0xba, 0xd0, 0xaa, 0x7d, 0x7f,
+                0x2e, 0xe7,
+            ],
+            [
+                0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7,
+                0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7, 0xbaaa7fe7,
+                0xbaaa7fe7, 0xbaaa7fe7,
+            ],
+        ),
+        // One partition, weights as bits
+        (
+            [
+                0x53, 0x82, 0x1c, 0x8b, 0xd6, 0x64, 0x32, 0xa9, 0x1a, 0x43, 0x09, 0x82, 0xbf, 0xf7,
+                0x7a, 0x96,
+            ],
+            [
+                0x5b5b5b8f, 0xa5a5a56d, 0xc0c0c061, 0xf0f0f04c, 0xf0f0f04c, 0xf0f0f04c, 0x4d4d4d95,
+                0x54545492, 0x91919176, 0xacacac6a, 0x91919176, 0x98989873, 0xb3b3b367, 0x6161618c,
+                0x9e9e9e70, 0x5b5b5b8f,
+            ],
+        ),
+        // One partition, weights with trits
+        (
+            [
+                0x51, 0xa2, 0x1d, 0x9d, 0x01, 0xef, 0xaf, 0x0b, 0xdd, 0x90, 0x4d, 0x62, 0xe1, 0xd2,
+                0xa8, 0x40,
+            ],
+            [
+                0xa890b0fa, 0xa68baafb, 0xad9cbef8, 0xa890b0fa, 0xa992b2fa, 0xa890b0fa, 0xac9abcf8,
+                0xac9abcf8, 0xa78cacfb, 0xab98baf9, 0xa78cacfb, 0xa890b0fa, 0xab98baf9, 0xab98baf9,
+                0xad9dbff7, 0xa68baafb,
+            ],
+        ),
+        // One partition, weights with quints
+        (
+            [
+                0x41, 0xa2, 0xcf, 0x4a, 0x6b, 0xd7, 0xef, 0x4f, 0x4f, 0x21, 0x32, 0x1f, 0x5b, 0x08,
+                0xd2, 0xae,
+            ],
+            [
+                0xefedfdf8, 0xeeeafdf6, 0xebe0fef0, 0xe9dafeeb, 0xf1f3fcfc, 0xe7d6fee9, 0xf1f3fcfc,
+                0xe9dafeeb, 0xf0f0fdfa, 0xf0f0fdfa, 0xebe0fef0, 0xeeeafdf6, 0xece3fef2, 0xece3fef2,
+                0xeaddfeee, 0xf1f3fcfc,
+            ],
+        ),
+        // A 2x3 grid of weights spread over the block
+        (
+            [
+                0x32, 0xa0, 0xe3, 0xe8, 0x01, 0x60, 0x8c, 0x91, 0xa7, 0xe1, 0x80, 0xcb, 0xd1, 0xf1,
+                0x57, 0x76,
+            ],
+            [
+                0xbc6fc761, 0xc16fce63, 0xcd71e369, 0xcd71e369, 0xc270d164, 0xc370d364, 0xca71df68,
+                0xcd71e369, 0xc770da66, 0xc770da66, 0xc970dc67, 0xcb71df68, 0xcd71e369, 0xcd71e369,
+                0xc970dc67, 0xc570d565,
+            ],
+        ),
+        // Two planes of weights
+        (
+            [
+                0x9e, 0x07, 0xec, 0x42, 0x21, 0xb3, 0xc7, 0xfa, 0x05, 0x09, 0x31, 0x27, 0x3a, 0x03,
+                0xaf, 0x48,
+            ],
+            [
+                0x8a8a7aff, 0x87878cff, 0x818195ff, 0x767696ff, 0x8c8c7bff, 0x86868eff, 0x808092ff,
+                0x79798eff, 0x8f8f7dff, 0x83838fff, 0x7d7d92ff, 0x7c7c83ff, 0x92927fff, 0x838390ff,
+                0x7d7d8fff, 0x7f7f7aff,
+            ],
+        ),
+        // Two partitions
+        (
+            [
+                0x11, 0x6a, 0x52, 0x89, 0x23, 0xd6, 0xf2, 0x1f, 0x27, 0xdf, 0x46, 0x78, 0x68, 0x08,
+                0xe5, 0x6e,
+            ],
+            [
+                0x979797cb, 0x9d9d9dcb, 0x9d9d9dcb, 0x909090cb, 0x9c9c9ccb, 0x999999cb, 0x9a9a9acb,
+                0x969696cb, 0xa1a1a1ca, 0x949494cb, 0x969696cb, 0x9e9e9ecb, 0xa6a6a6ca, 0x909090cb,
+                0x929292cb, 0xa4a4a4ca,
+            ],
+        ),
+        // Three partitions
+        (
+            [
+                0x03, 0xb2, 0x7d, 0x3b, 0xa1, 0x42, 0x59, 0xba, 0x69, 0x93, 0x0b, 0x41, 0xeb, 0x10,
+                0xcd, 0xd3,
+            ],
+            [
+                0x6a1a6aff, 0x98c1cbff, 0x87b5c0ff, 0x587069ff, 0xddf2f4ff, 0x9bc3ccff, 0x9bc3ccff,
+                0x5b7770ff, 0xe2f5f6ff, 0x9dc4cdff, 0xb3d4dbff, 0x5f8079ff, 0xe6f8f9ff, 0x9fc6cfff,
+                0xc7e2e6ff, 0x638780ff,
+            ],
+        ),
+        // Four partitions
+        (
+            [
+                0x3f, 0x5b, 0x05, 0x2c, 0x89, 0xe3, 0x40, 0xaa, 0x4b, 0x49, 0x40, 0x8f, 0x21, 0x43,
+                0x7c, 0x13,
+            ],
+            [
+                0x63254cff, 0x7a2e5eff, 0x983a75ff, 0xaf4387ff, 0x7c2f60ff, 0x7a2e5eff, 0x6d2954ff,
+                0x6d2954ff, 0x170077ff, 0x15006eff, 0x120061ff, 0x110059ff, 0x3f1c54ff, 0x321642ff,
+                0x200e2bff, 0x2c000cff,
+            ],
+        ),
+        // A partition with HDR endpoints
+        (
+            [
+                0xad, 0x6f, 0xcd, 0x1e, 0x71, 0x51, 0xe5, 0xbc, 0xba, 0x12, 0xd5, 0x1d, 0xf4, 0xd5,
+                0x5b, 0xaa,
+            ],
+            [
+                0xff00ffff, 0x551b96ff, 0x541b96ff, 0xff00ffff, 0xff00ffff, 0x612083ff, 0xff00ffff,
+                0x581cb4ff, 0x5c1e9cff, 0x6a237bff, 0xff00ffff, 0xff00ffff, 0x732593ff, 0x77277eff,
+                0xff00ffff, 0xff00ffff,
+            ],
+        ),
+        // Illegal
+        (
+            [
+                0x73, 0xdd, 0x8f, 0xdb, 0xec, 0xc7, 0x77, 0x73, 0x82, 0xda, 0x96, 0x30, 0x2f, 0xcd,
+                0x83, 0x79,
+            ],
+            [
+                0xff00ffff, 0xff00ffff, 0xff00ffff, 0xff00ffff, 0xff00ffff, 0xff00ffff, 0xff00ffff,
+                0xff00ffff, 0xff00ffff, 0xff00ffff, 0xff00ffff, 0xff00ffff, 0xff00ffff, 0xff00ffff,
+                0xff00ffff, 0xff00ffff,
+            ],
+        ),
+    ];
+
+    #[test]
+    fn matches_reference_decoder() {
+        for (block, expected) in REFERENCE_BLOCKS {
+            let pixels = decode_block(&block)
+                .map(|pixel| u32::from_be_bytes([pixel.red, pixel.green, pixel.blue, pixel.alpha]));
+            assert_eq!(pixels, expected, "block {block:02x?}");
+        }
+    }
+}
@@ -0,0 +1,608 @@
+//! Decoding of BC7 blocks, following the block layout in the BC7 format
+//! specification.
+
+use super::preview::Pixel;
+
+/// The fields of each of the eight BC7 modes.
+struct Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    /// A p-bit for each endpoint
+    endpoint_p_bits: bool,
+    /// A p-bit shared by both endpoints of a subset
+    shared_p_bits: bool,
+    index_bits: u32,
+    /// Bits of the separate alpha indices of modes 4 and 5
+    secondary_index_bits: u32,
+}
+
+/// A mode from its subsets and partition bits, then pairs of its rotation
+/// and index selection bits, color and alpha bits, endpoint and shared
+/// p-bits, and index and secondary index bits.
+const fn mode(
+    subsets: usize,
+    partition_bits: u32,
+    (rotation_bits, index_selection_bits): (u32, u32),
+    (color_bits, alpha_bits): (u32, u32),
+    p_bits: (bool, bool),
+    index_bits: (u32, u32),
+) -> Mode {
+    Mode {
+        subsets,
+        partition_bits,
+        rotation_bits,
+        index_selection_bits,
+        color_bits,
+        alpha_bits,
+        endpoint_p_bits: p_bits.0,
+        shared_p_bits: p_bits.1,
+        index_bits: index_bits.0,
+        secondary_index_bits: index_bits.1,
+    }
+}
+
+#[rustfmt::skip]
+const MODES: [Mode; 8] = [
+    mode(3, 4, (0, 0), (4, 0), (true, false), (3, 0)),
+    mode(2, 6, (0, 0), (6, 0), (false, true), (3, 0)),
+    mode(3, 6, (0, 0), (5, 0), (false, false), (2, 0)),
+    mode(2, 6, (0, 0), (7, 0), (true, false), (2, 0)),
+    mode(1, 0, (2, 1), (5, 6), (false, false), (2, 3)),
+    mode(1, 0, (2, 0), (7, 8), (false, false), (2, 2)),
+    mode(1, 0, (0, 0), (7, 7), (true, false), (4, 0)),
+    mode(2, 6, (0, 0), (5, 5), (true, false), (2, 0)),
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+#[rustfmt::skip]
+const PARTITIONS_2: [[u8; 16]; 64] = [
+    [0,0,1,1,0,0,1,1,0,0,1,1,0,0,1,1], [0,0,0,1,0,0,0,1,0,0,0,1,0,0,0,1],
+    [0,1,1,1,0,1,1,1,0,1,1,1,0,1,1,1], [0,0,0,1,0,0,1,1,0,0,1,1,0,1,1,1],
+    [0,0,0,0,0,0,0,1,0,0,0,1,0,0,1,1], [0,0,1,1,0,1,1,1,0,1,1,1,1,1,1,1],
+    [0,0,0,1,0,0,1,1,0,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,1,0,0,1,1,0,1,1,1],
+    [0,0,0,0,0,0,0,0,0,0,0,1,0,0,1,1], [0,0,1,1,0,1,1,1,1,1,1,1,1,1,1,1],
+    [0,0,0,0,0,0,0,1,0,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,0,0,0,0,1,0,1,1,1],
+    [0,0,0,1,0,1,1,1,1,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,0,1,1,1,1,1,1,1,1],
+    [0,0,0,0,1,1,1,1,1,1,1,1,1,1,1,1], [0,0,0,0,0,0,0,0,0,0,0,0,1,1,1,1],
+    [0,0,0,0,1,0,0,0,1,1,1,0,1,1,1,1], [0,1,1,1,0,0,0,1,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,1,0,0,0,1,1,1,0], [0,1,1,1,0,0,1,1,0,0,0,1,0,0,0,0],
+    [0,0,1,1,0,0,0,1,0,0,0,0,0,0,0,0], [0,0,0,0,1,0,0,0,1,1,0,0,1,1,1,0],
+    [0,0,0,0,0,0,0,0,1,0,0,0,1,1,0,0], [0,1,1,1,0,0,1,1,0,0,1,1,0,0,0,1],
+    [0,0,1,1,0,0,0,1,0,0,0,1,0,0,0,0], [0,0,0,0,1,0,0,0,1,0,0,0,1,1,0,0],
+    [0,1,1,0,0,1,1,0,0,1,1,0,0,1,1,0], [0,0,1,1,0,1,1,0,0,1,1,0,1,1,0,0],
+    [0,0,0,1,0,1,1,1,1,1,1,0,1,0,0,0], [0,0,0,0,1,1,1,1,1,1,1,1,0,0,0,0],
+    [0,1,1,1,0,0,0,1,1,0,0,0,1,1,1,0], [0,0,1,1,1,0,0,1,1,0,0,1,1,1,0,0],
+    [0,1,0,1,0,1,0,1,0,1,0,1,0,1,0,1], [0,0,0,0,1,1,1,1,0,0,0,0,1,1,1,1],
+    [0,1,0,1,1,0,1,0,0,1,0,1,1,0,1,0], [0,0,1,1,0,0,1,1,1,1,0,0,1,1,0,0],
+    [0,0,1,1,1,1,0,0,0,0,1,1,1,1,0,0], [0,1,0,1,0,1,0,1,1,0,1,0,1,0,1,0],
+    [0,1,1,0,1,0,0,1,0,1,1,0,1,0,0,1], [0,1,0,1,1,0,1,0,1,0,1,0,0,1,0,1],
+    [0,1,1,1,0,0,1,1,1,1,0,0,1,1,1,0], [0,0,0,1,0,0,1,1,1,1,0,0,1,0,0,0],
+    [0,0,1,1,0,0,1,0,0,1,0,0,1,1,0,0], [0,0,1,1,1,0,1,1,1,1,0,1,1,1,0,0],
+    [0,1,1,0,1,0,0,1,1,0,0,1,0,1,1,0], [0,0,1,1,1,1,0,0,1,1,0,0,0,0,1,1],
+    [0,1,1,0,0,1,1,0,1,0,0,1,1,0,0,1], [0,0,0,0,0,1,1,0,0,1,1,0,0,0,0,0],
+    [0,1,0,0,1,1,1,0,0,1,0,0,0,0,0,0], [0,0,1,0,0,1,1,1,0,0,1,0,0,0,0,0],
+    [0,0,0,0,0,0,1,0,0,1,1,1,0,0,1,0], [0,0,0,0,0,1,0,0,1,1,1,0,0,1,0,0],
+    [0,1,1,0,1,1,0,0,1,0,0,1,0,0,1,1], [0,0,1,1,0,1,1,0,1,1,0,0,1,0,0,1],
+    [0,1,1,0,0,0,1,1,1,0,0,1,1,1,0,0], [0,0,1,1,1,0,0,1,1,1,0,0,0,1,1,0],
+    [0,1,1,0,1,1,0,0,1,1,0,0,1,0,0,1], [0,1,1,0,0,0,1,1,0,0,1,1,1,0,0,1],
+    [0,1,1,1,1,1,1,0,1,0,0,0,0,0,0,1], [0,0,0,1,1,0,0,0,1,1,1,0,0,1,1,1],
+    [0,0,0,0,1,1,1,1,0,0,1,1,0,0,1,1], [0,0,1,1,0,0,1,1,1,1,1,1,0,0,0,0],
+    [0,0,1,0,0,0,1,0,1,1,1,0,1,1,1,0], [0,1,0,0,0,1,0,0,0,1,1,1,0,1,1,1],
+];
+
+#[rustfmt::skip]
+const PARTITIONS_3: [[u8; 16]; 64] = [
+    [0,0,1,1,0,0,1,1,0,2,2,1,2,2,2,2], [0,0,0,1,0,0,1,1,2,2,1,1,2,2,2,1],
+    [0,0,0,0,2,0,0,1,2,2,1,1,2,2,1,1], [0,2,2,2,0,0,2,2,0,0,1,1,0,1,1,1],
+    [0,0,0,0,0,0,0,0,1,1,2,2,1,1,2,2], [0,0,1,1,0,0,1,1,0,0,2,2,0,0,2,2],
+    [0,0,2,2,0,0,2,2,1,1,1,1,1,1,1,1], [0,0,1,1,0,0,1,1,2,2,1,1,2,2,1,1],
+    [0,0,0,0,0,0,0,0,1,1,1,1,2,2,2,2], [0,0,0,0,1,1,1,1,1,1,1,1,2,2,2,2],
+    [0,0,0,0,1,1,1,1,2,2,2,2,2,2,2,2], [0,0,1,2,0,0,1,2,0,0,1,2,0,0,1,2],
+    [0,1,1,2,0,1,1,2,0,1,1,2,0,1,1,2], [0,1,2,2,0,1,2,2,0,1,2,2,0,1,2,2],
+    [0,0,1,1,0,1,1,2,1,1,2,2,1,2,2,2], [0,0,1,1,2,0,0,1,2,2,0,0,2,2,2,0],
+    [0,0,0,1,0,0,1,1,0,1,1,2,1,1,2,2], [0,1,1,1,0,0,1,1,2,0,0,1,2,2,0,0],
+    [0,0,0,0,1,1,2,2,1,1,2,2,1,1,2,2], [0,0,2,2,0,0,2,2,0,0,2,2,1,1,1,1],
+    [0,1,1,1,0,1,1,1,0,2,2,2,0,2,2,2], [0,0,0,1,0,0,0,1,2,2,2,1,2,2,2,1],
+    [0,0,0,0,0,0,1,1,0,1,2,2,0,1,2,2], [0,0,0,0,1,1,0,0,2,2,1,0,2,2,1,0],
+    [0,1,2,2,0,1,2,2,0,0,1,1,0,0,0,0], [0,0,1,2,0,0,1,2,1,1,2,2,2,2,2,2],
+    [0,1,1,0,1,2,2,1,1,2,2,1,0,1,1,0], [0,0,0,0,0,1,1,0,1,2,2,1,1,2,2,1],
+    [0,0,2,2,1,1,0,2,1,1,0,2,0,0,2,2], [0,1,1,0,0,1,1,0,2,0,0,2,2,2,2,2],
+    [0,0,1,1,0,1,2,2,0,1,2,2,0,0,1,1], [0,0,0,0,2,0,0,0,2,2,1,1,2,2,2,1],
+    [0,0,0,0,0,0,0,2,1,1,2,2,1,2,2,2], [0,2,2,2,0,0,2,2,0,0,1,2,0,0,1,1],
+    [0,0,1,1,0,0,1,2,0,0,2,2,0,2,2,2], [0,1,2,0,0,1,2,0,0,1,2,0,0,1,2,0],
+    [0,0,0,0,1,1,1,1,2,2,2,2,0,0,0,0], [0,1,2,0,1,2,0,1,2,0,1,2,0,1,2,0],
+    [0,1,2,0,2,0,1,2,1,2,0,1,0,1,2,0], [0,0,1,1,2,2,0,0,1,1,2,2,0,0,1,1],
+    [0,0,1,1,1,1,2,2,2,2,0,0,0,0,1,1], [0,1,0,1,0,1,0,1,2,2,2,2,2,2,2,2],
+    [0,0,0,0,0,0,0,0,2,1,2,1,2,1,2,1], [0,0,2,2,1,1,2,2,0,0,2,2,1,1,2,2],
+    [0,0,2,2,0,0,1,1,0,0,2,2,0,0,1,1], [0,2,2,0,1,2,2,1,0,2,2,0,1,2,2,1],
+    [0,1,0,1,2,2,2,2,2,2,2,2,0,1,0,1], [0,0,0,0,2,1,2,1,2,1,2,1,2,1,2,1],
+    [0,1,0,1,0,1,0,1,0,1,0,1,2,2,2,2], [0,2,2,2,0,1,1,1,0,2,2,2,0,1,1,1],
+    [0,0,0,2,1,1,1,2,0,0,0,2,1,1,1,2], [0,0,0,0,2,1,1,2,2,1,1,2,2,1,1,2],
+    [0,2,2,2,0,1,1,1,0,1,1,1,0,2,2,2], [0,0,0,2,1,1,1,2,1,1,1,2,0,0,0,2],
+    [0,1,1,0,0,1,1,0,0,1,1,0,2,2,2,2], [0,0,0,0,0,0,0,0,2,1,1,2,2,1,1,2],
+    [0,1,1,0,0,1,1,0,2,2,2,2,2,2,2,2], [0,0,2,2,0,0,1,1,0,0,1,1,0,0,2,2],
+    [0,0,2,2,1,1,2,2,1,1,2,2,0,0,2,2], [0,0,0,0,0,0,0,0,0,0,0,0,2,1,1,2],
+    [0,0,0,2,0,0,0,1,0,0,0,2,0,0,0,1], [0,2,2,2,1,2,2,2,0,2,2,2,1,2,2,2],
+    [0,1,0,1,2,2,2,2,2,2,2,2,2,2,2,2], [0,1,1,1,2,0,1,1,2,2,0,1,2,2,2,0],
+];
+
+/// Pixel of the second subset of each two subset partition whose index is
+/// a bit shorter. The first subset's is always pixel 0.
+#[rustfmt::skip]
+const ANCHORS_2: [usize; 64] = [
+    15,15,15,15,15,15,15,15, 15,15,15,15,15,15,15,15,
+    15, 2, 8, 2, 2, 8, 8,15,  2, 8, 2, 2, 8, 8, 2, 2,
+    15,15, 6, 8, 2, 8,15,15,  2, 8, 2, 2, 2,15,15, 6,
+     6, 2, 6, 8,15,15, 2, 2, 15,15,15,15,15, 2, 2,15,
+];
+
+/// Anchor pixels of the second and third subsets of three subset partitions.
+#[rustfmt::skip]
+const ANCHORS_3: [[usize; 2]; 64] = [
+    [3,15], [3,8], [15,8], [15,3], [8,15], [3,15], [15,3], [15,8],
+    [8,15], [8,15], [6,15], [6,15], [6,15], [5,15], [3,15], [3,8],
+    [3,15], [3,8], [8,15], [15,3], [3,15], [3,8], [6,15], [10,8],
+    [5,3], [8,15], [8,6], [6,10], [8,15], [5,15], [15,10], [15,8],
+    [8,15], [15,3], [3,15], [5,10], [6,10], [10,8], [8,9], [15,10],
+    [15,6], [3,15], [15,8], [5,15], [15,3], [15,6], [15,6], [15,8],
+    [3,15], [15,3], [5,15], [5,15], [5,15], [8,15], [5,15], [10,15],
+    [5,15], [10,15], [8,15], [13,15], [15,3], [12,15], [3,15], [3,8],
+];
+
+/// Reads fields from the bits of a block, lowest bit first.
+struct BitReader {
+    bits: u128,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        let value = self.bits & ((1 << count) - 1);
+        self.bits >>= count;
+        value as u32
+    }
+}
+
+/// Decodes a 16 byte BC7 block into its pixels, row by row. Blocks with no
+/// valid mode are transparent black.
+pub fn decode_block(block: &[u8; 16]) -> [Pixel; 16] {
+    let mut reader = BitReader {
+        bits: u128::from_le_bytes(*block),
+    };
+
+    let Some(mode_number) = (0..8).find(|_| reader.read(1) == 1) else {
+        return [Pixel::default(); 16];
+    };
+    let mode = &MODES[mode_number];
+
+    let partition = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_selection = reader.read(mode.index_selection_bits);
+
+    // Endpoints are stored one channel at a time, then their p-bits
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[channel] = reader.read(mode.color_bits);
+        }
+    }
+    for endpoint in &mut endpoints[..endpoint_count] {
+        endpoint[3] = reader.read(mode.alpha_bits);
+    }
+
+    let (mut color_bits, mut alpha_bits) = (mode.color_bits, mode.alpha_bits);
+    if mode.endpoint_p_bits || mode.shared_p_bits {
+        let p_bit_count = if mode.endpoint_p_bits {
+            endpoint_count
+        } else {
+            mode.subsets
+        };
+        let mut p_bits = [0; 6];
+        for p_bit in &mut p_bits[..p_bit_count] {
+            *p_bit = reader.read(1);
+        }
+        for (i, endpoint) in endpoints[..endpoint_count].iter_mut().enumerate() {
+            let p_bit = if mode.endpoint_p_bits {
+                p_bits[i]
+            } else {
+                p_bits[i / 2]
+            };
+            for value in endpoint.iter_mut() {
+                *value = (*value << 1) | p_bit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    for endpoint in &mut endpoints[..endpoint_count] {
+        for value in &mut endpoint[..3] {
+            *value = expand(*value, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 {
+            expand(endpoint[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    let subset_of = |pixel: usize| -> usize {
+        match mode.subsets {
+            2 => usize::from(PARTITIONS_2[partition][pixel]),
+            3 => usize::from(PARTITIONS_3[partition][pixel]),
+            _ => 0,
+        }
+    };
+    let is_anchor = |pixel: usize| -> bool {
+        pixel == 0
+            || match mode.subsets {
+                2 => ANCHORS_2[partition] == pixel,
+                3 => ANCHORS_3[partition].contains(&pixel),
+                _ => false,
+            }
+    };
+
+    let mut read_indices = |bits: u32| -> [u32; 16] {
+        std::array::from_fn(|pixel| match bits {
+            0 => 0,
+            _ if is_anchor(pixel) => reader.read(bits - 1),
+            _ => reader.read(bits),
+        })
+    };
+    let indices = read_indices(mode.index_bits);
+    let secondary_indices = read_indices(mode.secondary_index_bits);
+
+    // Mode 4 can swap which set of indices is used for color and alpha
+    let (color_indices, color_index_bits, alpha_indices, alpha_index_bits) =
+        match (mode.secondary_index_bits, index_selection) {
+            (0, _) => (indices, mode.index_bits, indices, mode.index_bits),
+            (_, 0) => (
+                indices,
+                mode.index_bits,
+                secondary_indices,
+                mode.secondary_index_bits,
+            ),
+            _ => (
+                secondary_indices,
+                mode.secondary_index_bits,
+                indices,
+                mode.index_bits,
+            ),
+        };
+
+    std::array::from_fn(|pixel| {
+        let subset = subset_of(pixel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        let color_weight = weight(color_index_bits, color_indices[pixel]);
+        let alpha_weight = weight(alpha_index_bits, alpha_indices[pixel]);
+
+        let mut rgba: [u8; 4] = std::array::from_fn(|channel| {
+            let weight = if channel == 3 {
+                alpha_weight
+            } else {
+                color_weight
+            };
+            interpolate(e0[channel], e1[channel], weight)
+        });
+        if rotation > 0 {
+            rgba.swap(rotation as usize - 1, 3);
+        }
+
+        Pixel {
+            red: rgba[0],
+            green: rgba[1],
+            blue: rgba[2],
+            alpha: rgba[3],
+        }
+    })
+}
+
+fn expand(value: u32, bits: u32) -> u32 {
+    let value = value << (8 - bits);
+    (value | value >> bits) & 0xFF
+}
+
+fn weight(bits: u32, index: u32) -> u32 {
+    let index = index as usize;
+    match bits {
+        2 => WEIGHTS_2[index],
+        3 => WEIGHTS_3[index],
+        4 => WEIGHTS_4[index],
+        _ => 0,
+    }
+}
+
+fn interpolate(e0: u32, e1: u32, weight: u32) -> u8 {
+    (((64 - weight) * e0 + weight * e1 + 32) >> 6) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs `(bits, value)` fields into a block, lowest bit first.
+    fn pack(fields: &[(u32, u128)]) -> [u8; 16] {
+        let mut block = 0u128;
+        let mut position = 0;
+        for &(bits, value) in fields {
+            block |= value << position;
+            position += bits;
+        }
+        assert_eq!(position, 128);
+        block.to_le_bytes()
+    }
+
+    #[test]
+    fn anchors_are_in_their_subsets() {
+        for partition in 0..64 {
+            assert_eq!(PARTITIONS_2[partition][ANCHORS_2[partition]], 1);
+            let [second, third] = ANCHORS_3[partition];
+            assert_eq!(PARTITIONS_3[partition][second], 1);
+            assert_eq!(PARTITIONS_3[partition][third], 2);
+        }
+    }
+
+    #[test]
+    fn mode_6() {
+        // Every endpoint white, with both p-bits set
+        let white = pack(&[(7, 1 << 6), (56, (1 << 56) - 1), (2, 3), (63, 0)]);
+        assert!(decode_block(&white).iter().all(|pixel| *pixel
+            == Pixel {
+                red: 255,
+                green: 255,
+                blue: 255,
+                alpha: 255
+            }));
+
+        // Black to white, each pixel using the next index. The p-bits only
+        // set the second endpoint's lowest bit.
+        let indices = (1..16).fold(0u128, |indices, i| indices | (i as u128) << (i * 4 - 1));
+        let gradient = pack(&[
+            (7, 1 << 6),
+            (42, 0b1111111_0000000_1111111_0000000_1111111_0000000),
+            (14, (1 << 14) - 1),
+            (2, 0b10),
+            (63, indices),
+        ]);
+        let pixels = decode_block(&gradient);
+        assert_eq!(pixels[0].red, 0);
+        assert_eq!(pixels[1].red, 16);
+        assert_eq!(pixels[15].red, 255);
+        assert_eq!(pixels[0].alpha, 254);
+    }
+
+    #[test]
+    fn mode_5_rotation() {
+        // White with transparent alpha, then red and alpha swapped
+        let block = pack(&[(6, 1 << 5), (2, 1), (42, (1 << 42) - 1), (16, 0), (62, 0)]);
+        assert_eq!(
+            decode_block(&block)[0],
+            Pixel {
+                red: 0,
+                green: 255,
+                blue: 255,
+                alpha: 255
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_blocks_are_transparent() {
+        assert_eq!(decode_block(&[0; 16]), [Pixel::default(); 16]);
+    }
+
+    /// Blocks of every mode with the pixels Mesa's BPTC decoder (llvmpipe,
+    /// Mesa 22.3.6) reads out of them, as 0xRRGGBBAA row by row.
+    const REFERENCE_BLOCKS: [([u8; 16], [u32; 16]); 16] = [
+        // Mode 0
+        (
+            [
+                0x79, 0x2e, 0xba, 0x94, 0x4d, 0x33, 0xe3, 0xb9, 0x68, 0xc1, 0xb7, 0xc2, 0x43, 0x88,
+                0x3e, 0xa2,
+            ],
+            [
+                0x46acebff, 0xa6a14fff, 0x50a856ff, 0x5a9cbdff, 0x7b6bceff, 0x34ab58ff, 0x18ad5aff,
+                0x6689a4ff, 0x5c91dfff, 0x18ad5aff, 0xa6a14fff, 0xad1808ff, 0x3bb9f1ff, 0x50a856ff,
+                0x8aa351ff, 0x71778aff,
+            ],
+        ),
+        // Mode 0
+        (
+            [
+                0xd1, 0xbc, 0x7f, 0x5a, 0x6a, 0x86, 0xba, 0x9d, 0xf6, 0x37, 0x4f, 0x8b, 0xb4, 0x54,
+                0x84, 0x13,
+            ],
+            [
+                0x7c2fdfff, 0x8e35e1ff, 0x9f3ae2ff, 0x7c2fdfff, 0x8e35e1ff, 0x8e35e1ff, 0x9f3ae2ff,
+                0x7c2fdfff, 0xdc3253ff, 0xf3379aff, 0xd63142ff, 0xdc3253ff, 0x98a0d9ff, 0x7f88e3ff,
+                0x6774ecff, 0x394affff,
+            ],
+        ),
+        // Mode 1
+        (
+            [
+                0xba, 0xc6, 0xff, 0xdd, 0x34, 0xb0, 0xc0, 0xba, 0x77, 0xec, 0xb5, 0xd4, 0xdf, 0xa7,
+                0x25, 0x88,
+            ],
+            [
+                0x3ab6dbff, 0xa56b74ff, 0x8a4139ff, 0xbf3d9aff, 0xdf1f8aff, 0xddc1edff, 0xa56b74ff,
+                0xff027aff, 0xa56b74ff, 0x5a98cbff, 0x7b7bbbff, 0x8a4139ff, 0x8a4139ff, 0x1ad3ebff,
+                0x3ab6dbff, 0x975657ff,
+            ],
+        ),
+        // Mode 1
+        (
+            [
+                0x36, 0xde, 0x69, 0xfa, 0x0e, 0xc5, 0x59, 0xa0, 0x6a, 0x77, 0x1f, 0xb9, 0xbe, 0x23,
+                0xc3, 0x53,
+            ],
+            [
+                0x8a4494ff, 0x7f3d89ff, 0x84418eff, 0x9a4fa5ff, 0x954ba0ff, 0x9a4fa5ff, 0x9f52abff,
+                0x9a4fa5ff, 0xa96fcdff, 0xb66bbfff, 0xee5d84ff, 0x9b72dbff, 0xee5d84ff, 0xc468b0ff,
+                0xb66bbfff, 0xa96fcdff,
+            ],
+        ),
+        // Mode 2
+        (
+            [
+                0x64, 0x54, 0x58, 0xcb, 0x33, 0x53, 0x6d, 0x6a, 0x51, 0x91, 0x36, 0xe7, 0xde, 0x68,
+                0x3a, 0x34,
+            ],
+            [
+                0x3a5a42ff, 0x5a927bff, 0x5a4a9cff, 0xf76b9cff, 0x208431ff, 0x5a6d8cff, 0x5a927bff,
+                0xf76b9cff, 0x3a5a42ff, 0x5a4a9cff, 0x5a927bff, 0xf76b9cff, 0x208431ff, 0x5a6d8cff,
+                0x5a927bff, 0xf76b9cff,
+            ],
+        ),
+        // Mode 2
+        (
+            [
+                0x0c, 0xbf, 0x39, 0xc3, 0x04, 0xf8, 0xdd, 0x42, 0xd8, 0x81, 0x51, 0xc5, 0xf5, 0x91,
+                0xcd, 0xb4,
+            ],
+            [
+                0xff8473ff, 0x08c6bdff, 0x08c6bdff, 0x246d49ff, 0xff8473ff, 0x75d726ff, 0x314210ff,
+                0x08c6bdff, 0x75d726ff, 0xbbac4dff, 0x23627cff, 0x246d49ff, 0x75d726ff, 0x75d726ff,
+                0x2e6b49ff, 0x2e6b49ff,
+            ],
+        ),
+        // Mode 3
+        (
+            [
+                0x68, 0x9d, 0x1c, 0x54, 0xd9, 0xa7, 0x9b, 0xc7, 0x3b, 0x3c, 0xfe, 0x76, 0x5d, 0x22,
+                0x33, 0x5e,
+            ],
+            [
+                0x3e6727ff, 0x1cba3cff, 0x2d9232ff, 0x4f3f1dff, 0x3e6727ff, 0x4f3f1dff, 0x3e6727ff,
+                0x2d9232ff, 0x92f2f2ff, 0x4f3f1dff, 0x1cba3cff, 0x4f3f1dff, 0x7bf1e5ff, 0x64f0daff,
+                0x3e6727ff, 0x3e6727ff,
+            ],
+        ),
+        // Mode 3
+        (
+            [
+                0x78, 0x98, 0xd6, 0xa0, 0x24, 0x43, 0x63, 0x9f, 0x56, 0x55, 0xf0, 0xb5, 0xff, 0xb6,
+                0x77, 0xdc,
+            ],
+            [
+                0x7a228eff, 0xd73555ff, 0xd73555ff, 0x7a228eff, 0xd73555ff, 0xa92b71ff, 0x7a228eff,
+                0x93a7d7ff, 0xd73555ff, 0xa92b71ff, 0x93a7d7ff, 0x41ede1ff, 0xa92b71ff, 0x93a7d7ff,
+                0x78bedaff, 0x5cd6deff,
+            ],
+        ),
+        // Mode 4
+        (
+            [
+                0x30, 0xaf, 0xb2, 0xc4, 0xdc, 0x21, 0x54, 0xec, 0x34, 0x94, 0xaf, 0x10, 0x19, 0xf0,
+                0xd7, 0x2c,
+            ],
+            [
+                0x145b688b, 0x0e526e9d, 0x16526e9d, 0x1c63637b, 0x19526e9d, 0x165b688b, 0x0b4a73ad,
+                0x1c5b688b, 0x1c526e9d, 0x0b526e9d, 0x085b688b, 0x1463637b, 0x0e526e9d, 0x19526e9d,
+                0x1463637b, 0x194a73ad,
+            ],
+        ),
+        // Mode 4
+        (
+            [
+                0x10, 0xe6, 0x26, 0x70, 0xb4, 0x3c, 0x59, 0x3a, 0x14, 0x32, 0xcd, 0x48, 0x3d, 0xb1,
+                0x76, 0x9a,
+            ],
+            [
+                0x314a39a8, 0xbd00d6b9, 0x8f18a296, 0x314a3982, 0x5f326d82, 0xbd00d6a8, 0x5f326d4d,
+                0x314a39b9, 0x8f18a2b9, 0x8f18a25f, 0x314a39a8, 0x314a3996, 0x5f326d4d, 0x8f18a282,
+                0x5f326d5f, 0x8f18a282,
+            ],
+        ),
+        // Mode 5
+        (
+            [
+                0x60, 0x7b, 0x86, 0xe1, 0x6f, 0xa9, 0xf8, 0x6a, 0x33, 0xd7, 0x12, 0x4d, 0x4d, 0x47,
+                0x22, 0x90,
+            ],
+            [
+                0xbe0c2cf7, 0xdaaf2b61, 0xbe5c2bae, 0xc7af2b61, 0xdaff2a18, 0xc7af2b61, 0xbeaf2b61,
+                0xc75c2bae, 0xd15c2bae, 0xbeaf2b61, 0xd10c2cf7, 0xbeaf2b61, 0xbeaf2b61, 0xbe5c2bae,
+                0xc7af2b61, 0xd1af2b61,
+            ],
+        ),
+        // Mode 5
+        (
+            [
+                0xa0, 0xbb, 0x40, 0x86, 0x19, 0x7e, 0x37, 0xe4, 0x32, 0xc8, 0x63, 0x2d, 0x83, 0xd9,
+                0x39, 0x51,
+            ],
+            [
+                0x7645c332, 0x280dd677, 0x500dcc54, 0x7681c332, 0x7645c332, 0x5081cc54, 0x2845d677,
+                0x02b9df99, 0x5045cc54, 0x7681c332, 0x02b9df99, 0x280dd677, 0x2845d677, 0x500dcc54,
+                0x5045cc54, 0x2845d677,
+            ],
+        ),
+        // Mode 6
+        (
+            [
+                0x40, 0xc0, 0xb3, 0xe4, 0xcc, 0x0b, 0x94, 0xe6, 0xee, 0xad, 0x8b, 0x60, 0xef, 0xbd,
+                0xb8, 0xf3,
+            ],
+            [
+                0x4b7183af, 0x949713c9, 0x889126c4, 0x6a8152ba, 0x748643bd, 0x547674b2, 0x014bf395,
+                0x416c92ab, 0x9e9c04cc, 0x949713c9, 0x889126c4, 0x748643bd, 0x547674b2, 0x748643bd,
+                0x215bc2a0, 0x9e9c04cc,
+            ],
+        ),
+        // Mode 6
+        (
+            [
+                0xc0, 0x12, 0x1e, 0x3a, 0x0e, 0x84, 0x20, 0xf1, 0xd4, 0x35, 0xe8, 0xa2, 0x9d, 0xec,
+                0x16, 0xf2,
+            ],
+            [
+                0x62a60c3c, 0xd9c139c7, 0x81ad1860, 0x6da91048, 0xa3b52488, 0xe6c43ed6, 0x62a60c3c,
+                0xbaba2da3, 0xd9c139c7, 0xadb72894, 0xcebe35bb, 0xe6c43ed6, 0x8eb01d6f, 0x55a3072d,
+                0x62a60c3c, 0xf0c642e2,
+            ],
+        ),
+        // Mode 7
+        (
+            [
+                0x80, 0x2c, 0x3c, 0x7c, 0x95, 0xcc, 0xbb, 0x2a, 0x29, 0x16, 0x20, 0x9e, 0x1a, 0xcf,
+                0xf1, 0x98,
+            ],
+            [
+                0x82285128, 0x5def8e7d, 0xe3e34910, 0x82285128, 0x5def8e7d, 0x3ccf2c04, 0x82285128,
+                0x5def8e7d, 0xb7e76034, 0x82285128, 0x3ccf2c04, 0x5def8e7d, 0x82285128, 0x89eb7759,
+                0xb7e76034, 0x53983810,
+            ],
+        ),
+        // Mode 7
+        (
+            [
+                0x80, 0xcf, 0xfe, 0x9a, 0xa1, 0x07, 0x98, 0x1b, 0x8f, 0x2e, 0x8b, 0xb2, 0x50, 0x00,
+                0x1f, 0x47,
+            ],
+            [
+                0xdb417159, 0xf3693897, 0xf3693897, 0xdb417159, 0xdb417159, 0xdb417159, 0xdb417159,
+                0xf3693897, 0xff7d1cb6, 0xff7d1cb6, 0xdb417159, 0xf3693897, 0x6161a2cb, 0xd3007941,
+                0x8641959e, 0xd3007941,
+            ],
+        ),
+    ];
+
+    #[test]
+    fn matches_reference_decoder() {
+        for (block, expected) in REFERENCE_BLOCKS {
+            let pixels = decode_block(&block)
+                .map(|pixel| u32::from_be_bytes([pixel.red, pixel.green, pixel.blue, pixel.alpha]));
+            assert_eq!(pixels, expected, "block {block:02x?}");
+        }
+    }
+}
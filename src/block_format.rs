@@ -0,0 +1,338 @@
+use std::fmt::Display;
+
+use super::astc;
+use super::bc7;
+use super::byte_order::ByteOrder;
+use super::etc;
+use super::preview::{DataWindow, Pixel};
+
+/// Texture formats compressed in 4x4 blocks of pixels. Blocks are stored
+/// left to right, then top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BlockFormat {
+    /// BC1, or DXT1: RGB565 endpoints with 1 bit alpha
+    Bc1,
+    /// BC2, or DXT3: BC1 color with 4 bit alpha
+    Bc2,
+    /// BC3, or DXT5: BC1 color with interpolated alpha
+    Bc3,
+    /// BC4: one interpolated channel, shown as gray
+    Bc4,
+    /// BC5: two interpolated channels, red and green
+    Bc5,
+    /// BC7: eight modes of RGB or RGBA with partitions
+    Bc7,
+    /// ETC1 RGB
+    Etc1,
+    /// ETC2 RGB, ETC1 with the T, H and planar modes
+    Etc2Rgb,
+    /// ASTC with a 4x4 footprint and LDR colors
+    Astc,
+}
+
+/// Width and height of a block in pixels.
+pub const BLOCK_SIZE: u32 = 4;
+
+impl BlockFormat {
+    pub fn block_bytes(&self) -> usize {
+        match self {
+            BlockFormat::Bc1 | BlockFormat::Bc4 | BlockFormat::Etc1 | BlockFormat::Etc2Rgb => 8,
+            BlockFormat::Bc2
+            | BlockFormat::Bc3
+            | BlockFormat::Bc5
+            | BlockFormat::Bc7
+            | BlockFormat::Astc => 16,
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        matches!(
+            self,
+            BlockFormat::Bc1
+                | BlockFormat::Bc2
+                | BlockFormat::Bc3
+                | BlockFormat::Bc7
+                | BlockFormat::Astc
+        )
+    }
+
+    /// Bits a line of the image takes up, a quarter of a row of blocks.
+    /// Rows are a whole number of blocks wide, however wide the image is.
+    pub fn bits_per_line(&self, target_width: u32) -> u64 {
+        let row_bytes = u64::from(target_width.div_ceil(BLOCK_SIZE)) * self.block_bytes() as u64;
+        row_bytes * 8 / u64::from(BLOCK_SIZE)
+    }
+
+    /// Bytes of blocks covering `lines` lines.
+    pub fn data_len(&self, target_width: u32, lines: u32) -> usize {
+        let blocks =
+            target_width.div_ceil(BLOCK_SIZE) as usize * lines.div_ceil(BLOCK_SIZE) as usize;
+        blocks * self.block_bytes()
+    }
+
+    /// Decodes one block into its pixels, row by row. Blocks cut short by
+    /// the end of the data are padded with zeroes.
+    pub fn decode_block(&self, block: &[u8]) -> [Pixel; 16] {
+        let mut padded = [0; 16];
+        let len = block.len().min(self.block_bytes());
+        padded[..len].copy_from_slice(&block[..len]);
+
+        match self {
+            BlockFormat::Bc1 => decode_color_block(&padded[..8], true),
+            BlockFormat::Bc2 => {
+                let mut pixels = decode_color_block(&padded[8..], false);
+                let alpha = u64::from_le_bytes(padded[..8].try_into().unwrap());
+                for (i, pixel) in pixels.iter_mut().enumerate() {
+                    pixel.alpha = ((alpha >> (i * 4)) & 0xF) as u8 * 17;
+                }
+                pixels
+            }
+            BlockFormat::Bc3 => {
+                let mut pixels = decode_color_block(&padded[8..], false);
+                let alpha = decode_channel_block(&padded[..8]);
+                for (pixel, alpha) in pixels.iter_mut().zip(alpha) {
+                    pixel.alpha = alpha;
+                }
+                pixels
+            }
+            BlockFormat::Bc4 => decode_channel_block(&padded[..8]).map(|value| Pixel {
+                red: value,
+                green: value,
+                blue: value,
+                alpha: u8::MAX,
+            }),
+            BlockFormat::Bc5 => {
+                let red = decode_channel_block(&padded[..8]);
+                let green = decode_channel_block(&padded[8..]);
+                std::array::from_fn(|i| Pixel {
+                    red: red[i],
+                    green: green[i],
+                    blue: 0,
+                    alpha: u8::MAX,
+                })
+            }
+            BlockFormat::Bc7 => bc7::decode_block(&padded),
+            BlockFormat::Etc1 => etc::decode_block(&padded[..8], false),
+            BlockFormat::Etc2Rgb => etc::decode_block(&padded[..8], true),
+            BlockFormat::Astc => astc::decode_block(&padded),
+        }
+    }
+
+    /// Decodes `lines` lines of `target_width` pixels from blocks starting
+    /// at the start of `data`, row by row.
+    pub fn decode_image(&self, data: &[u8], target_width: u32, lines: u32) -> Vec<Pixel> {
+        let width = target_width as usize;
+        let blocks_across = target_width.div_ceil(BLOCK_SIZE) as usize;
+        let block_size = BLOCK_SIZE as usize;
+        let mut pixels = vec![Pixel::default(); width * lines as usize];
+
+        for block_row in 0..lines.div_ceil(BLOCK_SIZE) as usize {
+            for block_column in 0..blocks_across {
+                let start = (block_row * blocks_across + block_column) * self.block_bytes();
+                let block = data.get(start..).unwrap_or_default();
+                let decoded = self.decode_block(block);
+
+                for (i, pixel) in decoded.into_iter().enumerate() {
+                    let x = block_column * block_size + i % block_size;
+                    let y = block_row * block_size + i / block_size;
+                    if x < width && y < lines as usize {
+                        pixels[y * width + x] = pixel;
+                    }
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Decodes at least `lines` lines of blocks starting at `start_bit`
+    /// into the window the shader reads, one 0xRRGGBBAA word per pixel.
+    pub fn read(
+        &self,
+        data: &[u8],
+        byte_order: ByteOrder,
+        start_bit: u64,
+        target_width: u32,
+        lines: u32,
+    ) -> DataWindow {
+        let lines = lines.next_multiple_of(BLOCK_SIZE);
+        let len = self.data_len(target_width, lines);
+        let (bytes, lead_bits) = byte_order.read(data, start_bit / 8, len);
+        let blocks = bytes.get(lead_bits as usize / 8..).unwrap_or_default();
+
+        DataWindow {
+            words: self
+                .decode_image(blocks, target_width, lines)
+                .into_iter()
+                .map(|pixel| u32::from_be_bytes([pixel.red, pixel.green, pixel.blue, pixel.alpha]))
+                .collect(),
+            ..DataWindow::default()
+        }
+    }
+}
+
+impl Display for BlockFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BlockFormat::Bc1 => "bc1",
+            BlockFormat::Bc2 => "bc2",
+            BlockFormat::Bc3 => "bc3",
+            BlockFormat::Bc4 => "bc4",
+            BlockFormat::Bc5 => "bc5",
+            BlockFormat::Bc7 => "bc7",
+            BlockFormat::Etc1 => "etc1",
+            BlockFormat::Etc2Rgb => "etc2",
+            BlockFormat::Astc => "astc",
+        })
+    }
+}
+
+/// Expands a `bits` bit value to 8 bits by repeating its bits.
+pub fn expand_bits(value: u32, bits: u32) -> u8 {
+    let value = value << (8 - bits);
+    (value | value >> bits) as u8
+}
+
+fn rgb565(color: u16) -> [u32; 3] {
+    let color = u32::from(color);
+    [
+        u32::from(expand_bits(color >> 11, 5)),
+        u32::from(expand_bits((color >> 5) & 0x3F, 6)),
+        u32::from(expand_bits(color & 0x1F, 5)),
+    ]
+}
+
+/// Decodes the 8 byte color part of BC1, BC2 and BC3 blocks. BC1 blocks
+/// whose first color isn't greater than the second have a transparent
+/// black entry instead of a fourth color.
+fn decode_color_block(block: &[u8], bc1: bool) -> [Pixel; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let (c0, c1) = (rgb565(color0), rgb565(color1));
+    let mix = |weight0: u32, weight1: u32| -> Pixel {
+        let total = weight0 + weight1;
+        let channel = |i: usize| ((c0[i] * weight0 + c1[i] * weight1 + total / 2) / total) as u8;
+        Pixel {
+            red: channel(0),
+            green: channel(1),
+            blue: channel(2),
+            alpha: u8::MAX,
+        }
+    };
+
+    let palette = if color0 > color1 || !bc1 {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(1, 1), Pixel::default()]
+    };
+
+    std::array::from_fn(|i| palette[(indices >> (i * 2)) as usize & 3])
+}
+
+/// Decodes an 8 byte block of one interpolated channel, as used for BC3
+/// alpha, BC4 and BC5. When the first endpoint isn't greater than the second
+/// there are fewer steps between them, plus 0 and 255.
+fn decode_channel_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (u32::from(block[0]), u32::from(block[1]));
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+
+    let mut values = [a0, a1, 0, 0, 0, 0, 0, 255];
+    if a0 > a1 {
+        for (i, value) in values.iter_mut().enumerate().skip(2) {
+            let i = i as u32;
+            *value = ((8 - i) * a0 + (i - 1) * a1 + 3) / 7;
+        }
+    } else {
+        for (i, value) in values.iter_mut().enumerate().take(6).skip(2) {
+            let i = i as u32;
+            *value = ((6 - i) * a0 + (i - 1) * a1 + 2) / 5;
+        }
+    }
+
+    std::array::from_fn(|i| values[(indices >> (i * 3)) as usize & 7] as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(pixel: Pixel) -> [u8; 4] {
+        [pixel.red, pixel.green, pixel.blue, pixel.alpha]
+    }
+
+    #[test]
+    fn bc1() {
+        // Red and blue, with every index used along the first row
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0b1110_0100, 0, 0, 0];
+        let pixels = BlockFormat::Bc1.decode_block(&block).map(rgba);
+        assert_eq!(
+            pixels[..4],
+            [
+                [255, 0, 0, 255],
+                [0, 0, 255, 255],
+                [170, 0, 85, 255],
+                [85, 0, 170, 255]
+            ]
+        );
+        assert_eq!(pixels[4], [255, 0, 0, 255]);
+
+        // Swapping the endpoints gives three colors and transparent black
+        let block = [0x1F, 0x00, 0x00, 0xF8, 0b1110_0100, 0, 0, 0];
+        assert_eq!(
+            BlockFormat::Bc1.decode_block(&block).map(rgba)[..4],
+            [
+                [0, 0, 255, 255],
+                [255, 0, 0, 255],
+                [128, 0, 128, 255],
+                [0, 0, 0, 0]
+            ]
+        );
+    }
+
+    #[test]
+    fn bc2_and_bc3_alpha() {
+        let white = [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0];
+
+        let bc2 = [[0x0F, 0xF0, 0, 0, 0, 0, 0, 0], white].concat();
+        let alpha = BlockFormat::Bc2.decode_block(&bc2).map(|p| p.alpha);
+        assert_eq!(alpha[..4], [255, 0, 0, 255]);
+
+        // Indices 0, 1, 2 and 7 of 255 down to 0 in sevenths
+        let bc3 = [[255, 0, 0b1000_1000, 0b0000_1110, 0, 0, 0, 0], white].concat();
+        let alpha = BlockFormat::Bc3.decode_block(&bc3).map(|p| p.alpha);
+        assert_eq!(alpha[..4], [255, 0, 219, 36]);
+    }
+
+    #[test]
+    fn bc4_and_bc5() {
+        // Fewer steps between endpoints that go up, plus 0 and 255
+        let block = [0, 100, 0b1011_1010, 0b0000_1101, 0, 0, 0, 0];
+        let gray = BlockFormat::Bc4.decode_block(&block).map(|p| p.red);
+        assert_eq!(gray[..4], [20, 255, 0, 0]);
+
+        let block = [[200, 200, 0, 0, 0, 0, 0, 0], [50, 50, 0, 0, 0, 0, 0, 0]].concat();
+        assert_eq!(
+            rgba(BlockFormat::Bc5.decode_block(&block)[15]),
+            [200, 50, 0, 255]
+        );
+    }
+
+    #[test]
+    fn images_are_made_of_blocks() {
+        // Two BC4 blocks side by side, 0 and 255, cropped to 6 pixels wide
+        let data = [0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 0, 0, 0, 0, 0, 0];
+        let pixels = BlockFormat::Bc4.decode_image(&data, 6, 2);
+        let gray = pixels.iter().map(|p| p.red).collect::<Vec<_>>();
+        assert_eq!(gray, [0, 0, 0, 0, 255, 255, 0, 0, 0, 0, 255, 255]);
+
+        let window = BlockFormat::Bc4.read(&data, ByteOrder::default(), 0, 6, 1);
+        assert_eq!(window.words.len(), 6 * 4);
+        assert_eq!(window.words[4], 0xFFFFFFFF);
+
+        assert_eq!(BlockFormat::Bc4.bits_per_line(6), 32);
+        assert_eq!(BlockFormat::Bc4.data_len(6, 5), 32);
+    }
+}
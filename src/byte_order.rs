@@ -0,0 +1,208 @@
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+/// Size of the units whose bytes are reordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum UnitSize {
+    #[default]
+    Bits8,
+    Bits16,
+    Bits32,
+    Bits64,
+}
+
+impl UnitSize {
+    pub const ALL: &'static [Self] = &[Self::Bits8, Self::Bits16, Self::Bits32, Self::Bits64];
+
+    pub fn bytes(&self) -> usize {
+        match self {
+            UnitSize::Bits8 => 1,
+            UnitSize::Bits16 => 2,
+            UnitSize::Bits32 => 4,
+            UnitSize::Bits64 => 8,
+        }
+    }
+}
+
+impl Display for UnitSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-bit", self.bytes() * 8)
+    }
+}
+
+impl FromStr for UnitSize {
+    type Err = String;
+
+    /// Accepts the displayed name or just the number of bits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UnitSize::ALL
+            .iter()
+            .find(|size| {
+                size.to_string().eq_ignore_ascii_case(s) || (size.bytes() * 8).to_string() == s
+            })
+            .copied()
+            .ok_or_else(|| format!("unknown unit size {s:?}, expected one of: 8, 16, 32, 64"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+impl Endianness {
+    pub const ALL: &'static [Self] = &[Self::Big, Self::Little];
+}
+
+impl Display for Endianness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Endianness::Big => "big",
+            Endianness::Little => "little",
+        })
+    }
+}
+
+impl FromStr for Endianness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Endianness::ALL
+            .iter()
+            .find(|endianness| endianness.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown endianness {s:?}, expected one of: big, little"))
+    }
+}
+
+/// How the file's bytes are grouped into units before decoding. Decoding
+/// schemes number bits as if the data was big endian, so little endian units
+/// have their bytes reversed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ByteOrder {
+    pub unit_size: UnitSize,
+    pub endianness: Endianness,
+}
+
+impl ByteOrder {
+    /// Whether any bytes actually move.
+    pub fn is_swapped(&self) -> bool {
+        self.endianness == Endianness::Little && self.unit_size.bytes() > 1
+    }
+
+    /// Reads at least `len` bytes of `data` from `start_byte` in big endian
+    /// order. Units are aligned to the start of the data, so reading may have
+    /// to begin before `start_byte`; how many bits before is returned along
+    /// with the bytes. A unit cut short by the end of the data is padded with
+    /// zeroes before it is reversed.
+    pub fn read<'a>(&self, data: &'a [u8], start_byte: u64, len: usize) -> (Cow<'a, [u8]>, u32) {
+        let slice = |start: u64, len: usize| {
+            let beginning = usize::try_from(start)
+                .ok()
+                .and_then(|start| data.get(start..))
+                .unwrap_or_default();
+            beginning.get(..len).unwrap_or(beginning)
+        };
+
+        if !self.is_swapped() {
+            return (Cow::Borrowed(slice(start_byte, len)), 0);
+        }
+
+        let unit_bytes = self.unit_size.bytes();
+        let lead_bytes = (start_byte % unit_bytes as u64) as usize;
+        let units = slice(
+            start_byte - lead_bytes as u64,
+            (lead_bytes + len).next_multiple_of(unit_bytes),
+        );
+
+        let mut swapped = Vec::with_capacity(units.len().next_multiple_of(unit_bytes));
+        for unit in units.chunks(unit_bytes) {
+            let padding = unit_bytes - unit.len();
+            swapped.extend(std::iter::repeat_n(0, padding));
+            swapped.extend(unit.iter().rev());
+        }
+
+        (Cow::Owned(swapped), lead_bytes as u32 * 8)
+    }
+
+    /// Where bit `bit` of the data, counted in this order, is in the file.
+    /// Bits keep their place within their byte; only bytes move.
+    pub fn file_bit(&self, bit: u64) -> u64 {
+        if !self.is_swapped() {
+            return bit;
+        }
+
+        let unit_bytes = self.unit_size.bytes() as u64;
+        let byte = bit / 8;
+        let file_byte = byte - byte % unit_bytes + (unit_bytes - 1 - byte % unit_bytes);
+        file_byte * 8 + bit % 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn byte_order(unit_size: UnitSize, endianness: Endianness) -> ByteOrder {
+        ByteOrder {
+            unit_size,
+            endianness,
+        }
+    }
+
+    #[test]
+    fn big_endian_is_unchanged() {
+        let data = [1, 2, 3, 4, 5];
+        for unit_size in UnitSize::ALL {
+            let (bytes, lead_bits) = byte_order(*unit_size, Endianness::Big).read(&data, 1, 3);
+            assert_eq!(*bytes, [2, 3, 4]);
+            assert_eq!(lead_bits, 0);
+        }
+
+        let (bytes, _) = byte_order(UnitSize::Bits8, Endianness::Little).read(&data, 0, 9);
+        assert_eq!(*bytes, data);
+    }
+
+    #[test]
+    fn little_endian_units() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        let le16 = byte_order(UnitSize::Bits16, Endianness::Little);
+        assert_eq!(le16.read(&data, 0, 4), (vec![2, 1, 4, 3].into(), 0));
+        // Starting mid unit reads the whole unit
+        assert_eq!(le16.read(&data, 3, 2), (vec![4, 3, 6, 5].into(), 8));
+
+        let le32 = byte_order(UnitSize::Bits32, Endianness::Little);
+        assert_eq!(le32.read(&data, 6, 1), (vec![8, 7, 6, 5].into(), 16));
+
+        let le64 = byte_order(UnitSize::Bits64, Endianness::Little);
+        assert_eq!(
+            le64.read(&data, 0, 8),
+            (vec![8, 7, 6, 5, 4, 3, 2, 1].into(), 0)
+        );
+    }
+
+    #[test]
+    fn file_bits_of_reordered_data() {
+        let le32 = byte_order(UnitSize::Bits32, Endianness::Little);
+        // The first byte read is the last of its unit
+        assert_eq!(le32.file_bit(0), 24);
+        assert_eq!(le32.file_bit(13), 21);
+        assert_eq!(le32.file_bit(4 * 8 + 7), 7 * 8 + 7);
+        assert_eq!(
+            byte_order(UnitSize::Bits32, Endianness::Big).file_bit(13),
+            13
+        );
+    }
+
+    #[test]
+    fn short_last_unit_is_padded() {
+        let le32 = byte_order(UnitSize::Bits32, Endianness::Little);
+        assert_eq!(
+            le32.read(&[1, 2, 3, 4, 5, 6], 0, 100),
+            (vec![4, 3, 2, 1, 0, 0, 6, 5].into(), 0)
+        );
+        assert_eq!(le32.read(&[1, 2], 8, 4), (vec![].into(), 0));
+    }
+}
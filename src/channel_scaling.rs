@@ -0,0 +1,86 @@
+use std::{fmt::Display, str::FromStr};
+
+/// How channels narrower than 8 bits are scaled up to 8 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ChannelScaling {
+    /// Repeat the channel's bits into the low bits, so 5 bit 31 becomes 255
+    #[default]
+    Replicate,
+    /// Rescale the channel's range to 0..=255 with rounding
+    Linear,
+    /// Keep the bits where the scheme puts them and leave the rest 0
+    ZeroFill,
+    /// Show the channel's value as is, without scaling
+    Raw,
+}
+
+impl ChannelScaling {
+    pub const ALL: &'static [Self] = &[Self::Replicate, Self::Linear, Self::ZeroFill, Self::Raw];
+
+    /// Value of the `channel_scaling` uniform, matching the
+    /// `CHANNEL_SCALING_*` constants in `shader.wgsl`.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            ChannelScaling::Replicate => 0,
+            ChannelScaling::Linear => 1,
+            ChannelScaling::ZeroFill => 2,
+            ChannelScaling::Raw => 3,
+        }
+    }
+
+    /// Scales the `depth` bit `value` of a channel to 8 bits.
+    pub fn scale(&self, value: u32, depth: u32) -> u8 {
+        if depth == 0 {
+            return 0;
+        }
+
+        let scaled = match self {
+            ChannelScaling::Linear => {
+                let max_value = (1 << depth) - 1;
+                (value * 255 + max_value / 2) / max_value
+            }
+            ChannelScaling::ZeroFill => value << (8 - depth),
+            ChannelScaling::Raw => value,
+            ChannelScaling::Replicate => {
+                let mut scaled = 0;
+                let mut filled = 0;
+                while filled < 8 {
+                    scaled = (scaled << depth) | value;
+                    filled += depth;
+                }
+                scaled >> (filled - 8)
+            }
+        };
+        scaled as u8
+    }
+}
+
+impl Display for ChannelScaling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ChannelScaling::Replicate => "replicate",
+            ChannelScaling::Linear => "linear",
+            ChannelScaling::ZeroFill => "zero-fill",
+            ChannelScaling::Raw => "raw",
+        })
+    }
+}
+
+impl FromStr for ChannelScaling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChannelScaling::ALL
+            .iter()
+            .find(|scaling| scaling.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names = ChannelScaling::ALL
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("unknown channel scaling {s:?}, expected one of: {names}")
+            })
+    }
+}
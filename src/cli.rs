@@ -0,0 +1,384 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+
+use super::alpha_mode::AlphaMode;
+use super::byte_order::{ByteOrder, Endianness, UnitSize};
+use super::channel_scaling::ChannelScaling;
+use super::data_source::DataSource;
+use super::decoder::{apply_alpha_mode, Decoder};
+use super::export::write_png;
+use super::palette::{BuiltinPalette, PaletteSource};
+use super::pixel_mode::PixelMode;
+use super::pixel_order::PixelOrder;
+use super::planar::{parse_plane_order, PlanarError, PlanarLayout, PlaneInterleave};
+use super::row_stride::{RowStride, StrideUnit};
+use super::sample::{ToneMapping, ToneSettings};
+use super::tiling::{TileOrder, Tiling, TilingError};
+use super::yuv::{Frame420, YuvMatrix, YuvRange, YuvSettings};
+
+#[derive(Parser)]
+#[command(
+    name = "binlens",
+    about = "View binary data as images",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub open: OpenArgs,
+}
+
+/// What the window starts out showing.
+#[derive(Args, Default)]
+pub struct OpenArgs {
+    /// File to open, or - for standard input
+    pub path: Option<PathBuf>,
+
+    /// Process to view the memory of, picking a region in the window
+    #[arg(long, conflicts_with = "path")]
+    pub pid: Option<u32>,
+
+    /// Pixel mode to decode with
+    #[arg(short, long)]
+    pub mode: Option<PixelMode>,
+
+    /// Image width in pixels
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Bit of the file to start at
+    #[arg(short, long)]
+    pub start_bit: Option<u64>,
+
+    /// Reload the file whenever it changes
+    #[arg(long)]
+    pub watch: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Render part of a file to a PNG without opening a window
+    Render(RenderArgs),
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// File to read, or - for standard input
+    pub path: PathBuf,
+
+    /// PNG file to write
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// Pixel mode to decode with
+    #[arg(short, long, default_value = "rgb")]
+    pub mode: PixelMode,
+
+    /// Image width in pixels
+    #[arg(short, long, default_value_t = 300, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: u32,
+
+    /// Bit of the file to start decoding at
+    #[arg(short, long, default_value_t = 0)]
+    pub start_bit: u64,
+
+    /// Number of lines to render, the rest of the file if not given
+    #[arg(short, long)]
+    pub lines: Option<u32>,
+
+    /// How channels narrower than 8 bits are scaled
+    #[arg(long, default_value_t = ChannelScaling::default())]
+    pub scaling: ChannelScaling,
+
+    /// Order of pixels narrower than a byte within each byte
+    #[arg(long, default_value_t = PixelOrder::default())]
+    pub pixel_order: PixelOrder,
+
+    /// Size of the units the data is stored in, in bits
+    #[arg(long, default_value_t = UnitSize::default())]
+    pub unit_size: UnitSize,
+
+    /// Byte order within each unit
+    #[arg(long, default_value_t = Endianness::default())]
+    pub endianness: Endianness,
+
+    /// Number of planes each pixel is split across
+    #[arg(long, default_value_t = 1)]
+    pub planes: u32,
+
+    /// How the planes are interleaved
+    #[arg(long, default_value_t = PlaneInterleave::default())]
+    pub plane_interleave: PlaneInterleave,
+
+    /// Bytes from one plane to the next for image interleaved planes, or 0 to
+    /// split the file evenly
+    #[arg(long, default_value_t = 0)]
+    pub plane_size: u64,
+
+    /// Plane each field of the pixel is read from, most significant first,
+    /// like "3 2 1 0"
+    #[arg(long, default_value = "")]
+    pub plane_order: String,
+
+    /// Order the pixels are stored in
+    #[arg(long, default_value_t = TileOrder::default())]
+    pub tile_order: TileOrder,
+
+    /// Width in pixels of the tiles for tiles and morton orders
+    #[arg(long, default_value_t = 8)]
+    pub tile_width: u32,
+
+    /// Height in pixels of the tiles for tiles and morton orders
+    #[arg(long, default_value_t = 8)]
+    pub tile_height: u32,
+
+    /// Distance from the start of one row to the next in --stride-unit, for
+    /// rows padded past the width, or 0 for rows that follow straight on
+    #[arg(long, default_value_t = 0)]
+    pub row_stride: u32,
+
+    /// Unit of the row stride
+    #[arg(long, default_value_t = StrideUnit::default())]
+    pub stride_unit: StrideUnit,
+
+    /// Color matrix for YUV modes
+    #[arg(long, default_value_t = YuvMatrix::default())]
+    pub yuv_matrix: YuvMatrix,
+
+    /// Sample range for YUV modes
+    #[arg(long, default_value_t = YuvRange::default())]
+    pub yuv_range: YuvRange,
+
+    /// Lines in each frame for 4:2:0 YUV modes, or 0 for a single frame that
+    /// fills the file
+    #[arg(long, default_value_t = 0)]
+    pub frame_height: u32,
+
+    /// How channels wider than 8 bits, signed or float are brought into
+    /// range
+    #[arg(long, default_value_t = ToneMapping::default())]
+    pub tone_mapping: ToneMapping,
+
+    /// Stops to brighten color channels by before tone mapping
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub exposure: f32,
+
+    /// Value shown as black when normalizing, the smallest in the rendered
+    /// lines if not given
+    #[arg(long, allow_negative_numbers = true)]
+    pub min: Option<f32>,
+
+    /// Value shown as white when normalizing, the largest in the rendered
+    /// lines if not given
+    #[arg(long, allow_negative_numbers = true)]
+    pub max: Option<f32>,
+
+    /// Flatten alpha the way the preview shows it, instead of writing it to
+    /// the PNG
+    #[arg(short, long)]
+    pub alpha: Option<AlphaMode>,
+
+    /// Palette for indexed modes, either a built in palette's name or a
+    /// palette file
+    #[arg(short, long, default_value = "grayscale")]
+    pub palette: String,
+
+    /// Read the palette from this bit of the file instead
+    #[arg(long, conflicts_with = "palette")]
+    pub palette_start_bit: Option<u64>,
+
+    /// Pixel mode of a palette read from the file
+    #[arg(long, default_value = "rgb", requires = "palette_start_bit")]
+    pub palette_mode: PixelMode,
+}
+
+impl RenderArgs {
+    fn byte_order(&self) -> ByteOrder {
+        ByteOrder {
+            unit_size: self.unit_size,
+            endianness: self.endianness,
+        }
+    }
+
+    fn tiling(&self) -> Result<Tiling, TilingError> {
+        Tiling::new(self.tile_order, self.tile_width, self.tile_height)
+    }
+
+    fn row_stride(
+        &self,
+        planar_layout: &PlanarLayout,
+        tiling: &Tiling,
+    ) -> Result<RowStride, Box<dyn Error>> {
+        let bits = self
+            .stride_unit
+            .bits(self.row_stride)
+            .ok_or("the row stride is too large")?;
+        let row_stride = RowStride::new(bits);
+        row_stride.validate(
+            self.mode.decoding_scheme(),
+            planar_layout,
+            tiling,
+            self.width,
+        )?;
+        Ok(row_stride)
+    }
+
+    fn yuv(&self) -> YuvSettings {
+        YuvSettings {
+            matrix: self.yuv_matrix,
+            range: self.yuv_range,
+            frame_height: self.frame_height,
+        }
+    }
+
+    fn planar_layout(&self) -> Result<PlanarLayout, PlanarError> {
+        let layout = PlanarLayout::new(
+            self.planes,
+            &parse_plane_order(&self.plane_order)?,
+            self.plane_interleave,
+            self.plane_size,
+        )?;
+        layout.check(self.mode.decoding_scheme())?;
+        Ok(layout)
+    }
+
+    /// The tone settings, with a normalizing range not given filled in from
+    /// the values of the rendered lines.
+    fn tone(&self, decoder: &Decoder, data: &[u8], lines: u32) -> ToneSettings {
+        let mut tone = decoder.tone;
+        if tone.tone_mapping != ToneMapping::Normalize {
+            return tone;
+        }
+
+        let (min, max) = match (self.min, self.max) {
+            (Some(min), Some(max)) => (min, max),
+            (min, max) => {
+                let (low, high) = decoder
+                    .file_value_range(data, self.start_bit, self.width, lines)
+                    .unwrap_or((tone.min, tone.max));
+                (min.unwrap_or(low), max.unwrap_or(high))
+            }
+        };
+        tone.min = min;
+        tone.max = max;
+        tone
+    }
+
+    fn palette_source(&self) -> PaletteSource {
+        if let Some(start_bit) = self.palette_start_bit {
+            return PaletteSource::Data {
+                start_bit,
+                pixel_mode: self.palette_mode.clone(),
+            };
+        }
+
+        match self.palette.parse::<BuiltinPalette>() {
+            Ok(builtin) => PaletteSource::Builtin(builtin),
+            Err(_) => PaletteSource::File(self.palette.clone().into()),
+        }
+    }
+}
+
+/// Most pixels `render` decodes. The decoded image and the bytes it is
+/// written out as take 4 bytes a pixel each, so this keeps them to 1 GiB.
+const MAX_RENDER_PIXELS: u64 = 1 << 28;
+
+/// Widest and tallest a PNG image can be.
+const MAX_PNG_SIZE: u32 = i32::MAX as u32;
+
+pub fn render(args: &RenderArgs) -> Result<(), Box<dyn Error>> {
+    let data = DataSource::from_path(args.path.clone()).load(|_, _| true)?;
+    let decoding_scheme = args.mode.decoding_scheme();
+    let planar_layout = args.planar_layout()?;
+    let tiling = args.tiling()?;
+    let row_stride = args.row_stride(&planar_layout, &tiling)?;
+
+    let frame = decoding_scheme
+        .yuv
+        .filter(|format| format.is_420())
+        .map(|format| {
+            Frame420::locate(
+                format,
+                data.len(),
+                args.start_bit,
+                args.width,
+                args.frame_height,
+            )
+        });
+
+    let lines = match (args.lines, frame) {
+        (Some(lines), _) => lines,
+        // 4:2:0 frames end where their chroma starts
+        (None, Some(frame)) => u32::try_from(frame.lines.saturating_sub(frame.first_line))?,
+        (None, None) => {
+            let bits_per_line = match decoding_scheme.block {
+                Some(block) => block.bits_per_line(args.width),
+                None if row_stride.is_padded() => u64::from(row_stride.bits()),
+                None => planar_layout.bits_per_line(decoding_scheme.bits_per_pixel, args.width),
+            };
+            let remaining_bits = planar_layout
+                .line_data_bits(data.len())
+                .saturating_sub(args.start_bit);
+            u32::try_from(remaining_bits.div_ceil(bits_per_line))?
+        }
+    };
+
+    if lines == 0 {
+        return Err("there are no lines to render".into());
+    }
+    if args.width > MAX_PNG_SIZE || lines > MAX_PNG_SIZE {
+        return Err(
+            format!("PNG images can't be more than {MAX_PNG_SIZE} pixels across or down").into(),
+        );
+    }
+    let pixel_count = u64::from(args.width) * u64::from(lines);
+    if pixel_count > MAX_RENDER_PIXELS {
+        return Err(format!(
+            "{} by {lines} is {pixel_count} pixels, more than the {MAX_RENDER_PIXELS} that can be rendered at once; pick fewer lines with --lines",
+            args.width
+        )
+        .into());
+    }
+
+    let palette = args.palette_source().resolve(
+        &data,
+        decoding_scheme.index_depth(),
+        args.scaling,
+        args.byte_order(),
+    )?;
+    let decoder = Decoder {
+        decoding_scheme,
+        channel_scaling: args.scaling,
+        pixel_order: args.pixel_order,
+        byte_order: args.byte_order(),
+        planar_layout,
+        tiling,
+        row_stride,
+        palette: &palette,
+        yuv: args.yuv(),
+        tone: ToneSettings {
+            tone_mapping: args.tone_mapping,
+            exposure: args.exposure,
+            ..ToneSettings::default()
+        },
+    };
+    let decoder = Decoder {
+        tone: args.tone(&decoder, &data, lines),
+        ..decoder
+    };
+    let mut pixels = decoder.decode_file_lines(&data, args.start_bit, args.width, lines);
+
+    if let Some(alpha_mode) = args.alpha {
+        let alpha_mode = alpha_mode.for_scheme(decoding_scheme);
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let x = i as u32 % args.width;
+            let y = i as u32 / args.width;
+            *pixel = apply_alpha_mode(*pixel, alpha_mode, x, y);
+        }
+    }
+
+    write_png(&args.output, args.width, lines, &pixels)
+}
@@ -0,0 +1,545 @@
+use std::{
+    borrow::Cow,
+    env,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use super::sample::SampleType;
+use super::shader::{DecodingScheme, MAX_CHANNEL_BITS, MAX_INDEX_BITS};
+
+/// A pixel format typed in by the user, kept together with the scheme it
+/// parses to.
+///
+/// Two syntaxes are accepted:
+///
+/// - A list of channels and their widths in bits, in the order they appear in
+///   the pixel, each most significant bit first. `R`, `G`, `B` and `A` are
+///   color channels, `L` is gray, `I` is a palette index and `X` is padding,
+///   e.g. `R5G6B5`, `A1R5G5B5`, `B8G8R8X8` or `I4X4`.
+/// - An explicit bit map: the bits per pixel, a colon, then each channel's
+///   bit numbers most significant first, e.g. `16: R=8-12 G=13-15,0-2 B=3-7`.
+///   Bit 0 is the first bit of the pixel.
+///
+/// Channels are unsigned integers unless the channel list or the bits per
+/// pixel end in a DXGI style type suffix: `_UNORM`, `_SNORM` for two's
+/// complement or `_FLOAT`, e.g. `R16G16B16A16_FLOAT` or `16_SNORM: L=0-15`.
+/// Float channels are 16 or 32 bits, or the 11 and 10 bits of `R11G11B10`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CustomFormat {
+    format: String,
+    decoding_scheme: Arc<DecodingScheme>,
+}
+
+impl CustomFormat {
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    pub fn decoding_scheme(&self) -> &DecodingScheme {
+        &self.decoding_scheme
+    }
+}
+
+impl FromStr for CustomFormat {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format = s.trim();
+        let decoding_scheme = if format.contains(':') {
+            parse_bit_map(format)?
+        } else {
+            parse_channel_list(format)?
+        };
+
+        Ok(Self {
+            format: format.to_owned(),
+            decoding_scheme: Arc::new(decoding_scheme),
+        })
+    }
+}
+
+impl Display for CustomFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.format)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    Empty,
+    UnexpectedCharacter(char),
+    MissingWidth(char),
+    MissingBits(String),
+    InvalidBitsPerPixel(String),
+    InvalidBit(String),
+    BitOutOfRange { bit: u32, bits_per_pixel: u32 },
+    ChannelTooWide { channel: char, bits: u32, max: u32 },
+    UnsupportedDepth { sample_type: SampleType, bits: u32 },
+    UnknownSampleType(String),
+    DuplicateChannel(char),
+    GrayWithColor,
+    IndexWithColor,
+    NoColorChannels,
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Empty => write!(f, "Format is empty"),
+            FormatError::UnexpectedCharacter(c) => write!(f, "Unexpected character {c:?}"),
+            FormatError::MissingWidth(channel) => {
+                write!(f, "Channel {channel} needs a width in bits")
+            }
+            FormatError::MissingBits(channel) => {
+                write!(f, "{channel:?} needs a list of bits, like R=0-7")
+            }
+            FormatError::InvalidBitsPerPixel(s) => write!(f, "Invalid bits per pixel {s:?}"),
+            FormatError::InvalidBit(s) => write!(f, "Invalid bit {s:?}"),
+            FormatError::BitOutOfRange {
+                bit,
+                bits_per_pixel,
+            } => write!(f, "Bit {bit} is outside of a {bits_per_pixel} bit pixel"),
+            FormatError::ChannelTooWide { channel, bits, max } => {
+                write!(f, "Channel {channel} is {bits} bits, the most is {max}")
+            }
+            FormatError::UnsupportedDepth { sample_type, bits } => {
+                write!(f, "{sample_type} channels can't be {bits} bits")
+            }
+            FormatError::UnknownSampleType(suffix) => {
+                write!(f, "Unknown type {suffix:?}, expected UNORM, SNORM or FLOAT")
+            }
+            FormatError::DuplicateChannel(channel) => {
+                write!(f, "Channel {channel} is given more than once")
+            }
+            FormatError::GrayWithColor => write!(f, "L can't be mixed with R, G or B"),
+            FormatError::IndexWithColor => write!(f, "I can't be mixed with other channels"),
+            FormatError::NoColorChannels => write!(f, "Format has no color channels"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Channel bits in the order they are read, most significant first.
+#[derive(Default)]
+struct ChannelBits {
+    red: Option<Vec<u32>>,
+    green: Option<Vec<u32>>,
+    blue: Option<Vec<u32>>,
+    alpha: Option<Vec<u32>>,
+    gray: Option<Vec<u32>>,
+    index: Option<Vec<u32>>,
+}
+
+impl ChannelBits {
+    fn set(&mut self, channel: char, bits: Vec<u32>) -> Result<(), FormatError> {
+        let slot = match channel {
+            'R' => &mut self.red,
+            'G' => &mut self.green,
+            'B' => &mut self.blue,
+            'A' => &mut self.alpha,
+            'L' => &mut self.gray,
+            'I' => &mut self.index,
+            _ => return Err(FormatError::UnexpectedCharacter(channel)),
+        };
+
+        if slot.is_some() {
+            return Err(FormatError::DuplicateChannel(channel));
+        }
+        let max = if channel == 'I' {
+            MAX_INDEX_BITS
+        } else {
+            MAX_CHANNEL_BITS
+        };
+        if bits.len() > max {
+            return Err(FormatError::ChannelTooWide {
+                channel,
+                bits: bits.len() as u32,
+                max: max as u32,
+            });
+        }
+
+        *slot = Some(bits);
+        Ok(())
+    }
+
+    fn into_decoding_scheme(
+        self,
+        bits_per_pixel: u32,
+        sample_type: SampleType,
+    ) -> Result<DecodingScheme, FormatError> {
+        let channels = [&self.red, &self.green, &self.blue, &self.alpha, &self.gray];
+        for bits in channels
+            .into_iter()
+            .flatten()
+            .filter(|bits| !bits.is_empty())
+        {
+            let bits = bits.len() as u32;
+            if !sample_type.supports_depth(bits) {
+                return Err(FormatError::UnsupportedDepth { sample_type, bits });
+            }
+        }
+
+        if let Some(index) = self.index {
+            let has_other_channels = [&self.red, &self.green, &self.blue, &self.alpha, &self.gray]
+                .iter()
+                .any(|channel| channel.is_some());
+            if has_other_channels {
+                return Err(FormatError::IndexWithColor);
+            }
+
+            return Ok(DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: channel_bits(Some(index)),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel,
+            });
+        }
+
+        let (red, green, blue) = match self.gray {
+            Some(gray) => {
+                if self.red.is_some() || self.green.is_some() || self.blue.is_some() {
+                    return Err(FormatError::GrayWithColor);
+                }
+                (Some(gray.clone()), Some(gray.clone()), Some(gray))
+            }
+            None => (self.red, self.green, self.blue),
+        };
+
+        if red.is_none() && green.is_none() && blue.is_none() {
+            return Err(FormatError::NoColorChannels);
+        }
+
+        Ok(DecodingScheme {
+            red: channel_bits(red),
+            green: channel_bits(green),
+            blue: channel_bits(blue),
+            alpha: channel_bits(self.alpha),
+            index: Cow::Borrowed(&[]),
+            sample_type,
+            inverted: false,
+            yuv: None,
+            block: None,
+            bits_per_pixel,
+        })
+    }
+}
+
+fn channel_bits(bits: Option<Vec<u32>>) -> super::shader::ChannelBits {
+    Cow::Owned(bits.unwrap_or_default())
+}
+
+/// Splits a DXGI style type suffix like `_FLOAT` off the end of a format.
+fn split_sample_type(format: &str) -> Result<(&str, SampleType), FormatError> {
+    match format.rsplit_once('_') {
+        Some((rest, suffix)) => suffix
+            .parse()
+            .map(|sample_type| (rest, sample_type))
+            .map_err(|_| FormatError::UnknownSampleType(suffix.to_owned())),
+        None => Ok((format, SampleType::Unorm)),
+    }
+}
+
+fn parse_channel_list(format: &str) -> Result<DecodingScheme, FormatError> {
+    let (format, sample_type) = split_sample_type(format)?;
+    let mut channels = ChannelBits::default();
+    let mut bits_per_pixel = 0u32;
+
+    let mut chars = format
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .peekable();
+
+    if chars.peek().is_none() {
+        return Err(FormatError::Empty);
+    }
+
+    while let Some(channel) = chars.next() {
+        if !matches!(channel, 'R' | 'G' | 'B' | 'A' | 'L' | 'I' | 'X') {
+            return Err(FormatError::UnexpectedCharacter(channel));
+        }
+
+        let mut width = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            width.push(digit);
+        }
+        let width: u32 = width
+            .parse()
+            .map_err(|_| FormatError::MissingWidth(channel))?;
+
+        let start = bits_per_pixel;
+        bits_per_pixel = bits_per_pixel
+            .checked_add(width)
+            .ok_or(FormatError::InvalidBitsPerPixel(format.to_owned()))?;
+
+        if channel != 'X' {
+            channels.set(channel, (start..bits_per_pixel).collect())?;
+        }
+    }
+
+    if bits_per_pixel == 0 {
+        return Err(FormatError::InvalidBitsPerPixel(format.to_owned()));
+    }
+
+    channels.into_decoding_scheme(bits_per_pixel, sample_type)
+}
+
+fn parse_bit_map(format: &str) -> Result<DecodingScheme, FormatError> {
+    let (bits_per_pixel, channel_list) = format.split_once(':').unwrap_or((format, ""));
+
+    let (bits_per_pixel, sample_type) = split_sample_type(bits_per_pixel.trim())?;
+    let bits_per_pixel: u32 = match bits_per_pixel.parse() {
+        Ok(bits) if bits > 0 => bits,
+        _ => return Err(FormatError::InvalidBitsPerPixel(bits_per_pixel.to_owned())),
+    };
+
+    let mut channels = ChannelBits::default();
+    for channel_spec in channel_list.split_whitespace() {
+        let (channel, bit_list) = channel_spec
+            .split_once('=')
+            .ok_or_else(|| FormatError::MissingBits(channel_spec.to_owned()))?;
+
+        let mut channel_chars = channel.chars().map(|c| c.to_ascii_uppercase());
+        let channel = match (channel_chars.next(), channel_chars.next()) {
+            (Some(channel), None) => channel,
+            (Some(_), Some(c)) => return Err(FormatError::UnexpectedCharacter(c)),
+            (None, _) => return Err(FormatError::UnexpectedCharacter('=')),
+        };
+
+        let mut bits = Vec::new();
+        for range in bit_list.split(',').filter(|range| !range.is_empty()) {
+            let parse_bit = |bit: &str| -> Result<u32, FormatError> {
+                let bit = bit
+                    .parse()
+                    .map_err(|_| FormatError::InvalidBit(bit.to_owned()))?;
+                if bit >= bits_per_pixel {
+                    return Err(FormatError::BitOutOfRange {
+                        bit,
+                        bits_per_pixel,
+                    });
+                }
+                Ok(bit)
+            };
+
+            match range.split_once('-') {
+                Some((first, last)) => {
+                    let first = parse_bit(first)?;
+                    let last = parse_bit(last)?;
+                    if first <= last {
+                        bits.extend(first..=last);
+                    } else {
+                        bits.extend((last..=first).rev());
+                    }
+                }
+                None => bits.push(parse_bit(range)?),
+            }
+        }
+
+        channels.set(channel, bits)?;
+    }
+
+    channels.into_decoding_scheme(bits_per_pixel, sample_type)
+}
+
+/// Where custom formats are remembered between runs.
+fn custom_formats_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(config_dir.join("binlens").join("custom_formats.txt"))
+}
+
+/// Loads the saved custom formats, one per line. Lines that no longer parse
+/// are skipped.
+pub fn load_custom_formats() -> Vec<CustomFormat> {
+    let Some(path) = custom_formats_path() else {
+        return vec![];
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match line.parse() {
+                Ok(format) => Some(format),
+                Err(why) => {
+                    eprintln!("Skipping custom format {line:?} in {path:#?} : {why}");
+                    None
+                }
+            })
+            .collect(),
+        Err(why) if why.kind() == io::ErrorKind::NotFound => vec![],
+        Err(why) => {
+            eprintln!("Could not read custom formats {path:#?} : {why}");
+            vec![]
+        }
+    }
+}
+
+pub fn save_custom_formats(formats: &[CustomFormat]) -> io::Result<()> {
+    let path = custom_formats_path().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "no config directory to save custom formats in",
+        )
+    })?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let contents = formats
+        .iter()
+        .map(|format| format!("{}\n", format.format()))
+        .collect::<String>();
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_mode::PixelMode;
+
+    fn scheme(format: &str) -> DecodingScheme {
+        format
+            .parse::<CustomFormat>()
+            .unwrap()
+            .decoding_scheme()
+            .clone()
+    }
+
+    #[test]
+    fn channel_list() {
+        let rgb565 = scheme("R5G6B5");
+        assert_eq!(rgb565.bits_per_pixel, 16);
+        assert_eq!(*rgb565.red, [0, 1, 2, 3, 4]);
+        assert_eq!(*rgb565.green, [5, 6, 7, 8, 9, 10]);
+        assert!(rgb565.alpha.is_empty());
+
+        let bgrx = scheme("b8 g8 r8 x8");
+        assert_eq!(bgrx.bits_per_pixel, 32);
+        assert_eq!(bgrx.red[0], 16);
+        assert_eq!(bgrx.blue[0], 0);
+        assert!(!bgrx.has_alpha());
+
+        let argb1555 = scheme("A1R5G5B5");
+        assert_eq!(*argb1555.alpha, [0]);
+        assert_eq!(argb1555.blue[0], 11);
+    }
+
+    #[test]
+    fn gray() {
+        let gray = scheme("L4X4");
+        assert_eq!(gray.bits_per_pixel, 8);
+        assert_eq!(gray.red, gray.green);
+        assert_eq!(gray.red, gray.blue);
+        assert_eq!(*gray.red, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn sample_types() {
+        let half = scheme("R16G16B16A16_FLOAT");
+        assert_eq!(half.sample_type, SampleType::Float);
+        assert_eq!(half.bits_per_pixel, 64);
+        assert_eq!(half.alpha.len(), 16);
+        assert_eq!(&half, PixelMode::Rgba16f.decoding_scheme());
+
+        let snorm = scheme("16_snorm: L=0-15");
+        assert_eq!(snorm.sample_type, SampleType::Snorm);
+        assert_eq!(snorm.red.len(), 16);
+
+        assert_eq!(scheme("R11G11B10_FLOAT").blue.len(), 10);
+        assert_eq!(scheme("R32").sample_type, SampleType::Unorm);
+    }
+
+    #[test]
+    fn index() {
+        assert_eq!(&scheme("I4"), PixelMode::Indexed4.decoding_scheme());
+        assert_eq!(&scheme("8: I=0-7"), PixelMode::Indexed8.decoding_scheme());
+
+        let padded = scheme("X4I4");
+        assert_eq!(padded.bits_per_pixel, 8);
+        assert_eq!(padded.index_depth(), 4);
+        assert_eq!(padded.index[0], 4);
+    }
+
+    #[test]
+    fn bit_map_matches_built_in_scheme() {
+        let g3b5r5g3 = scheme("16: R=8-12 G=13-15,0-2 B=3-7");
+        assert_eq!(&g3b5r5g3, PixelMode::G3b5r5g3.decoding_scheme());
+
+        let rgba4444 = scheme("16: r=12-15 g=8-11 b=4-7 a=0-3");
+        assert_eq!(&rgba4444, PixelMode::Rgba4444.decoding_scheme());
+
+        // Descending ranges read the bits backwards
+        let reversed = scheme("8: L=7-0");
+        assert_eq!(reversed.red[0], 7);
+        assert_eq!(reversed.red[7], 0);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |format: &str| format.parse::<CustomFormat>().unwrap_err();
+
+        assert_eq!(error("  "), FormatError::Empty);
+        assert_eq!(error("R5G6Q5"), FormatError::UnexpectedCharacter('Q'));
+        assert_eq!(error("RG6B5"), FormatError::MissingWidth('R'));
+        assert_eq!(
+            error("R33G8B8"),
+            FormatError::ChannelTooWide {
+                channel: 'R',
+                bits: 33,
+                max: 32
+            }
+        );
+        assert_eq!(
+            error("I9"),
+            FormatError::ChannelTooWide {
+                channel: 'I',
+                bits: 9,
+                max: 8
+            }
+        );
+        assert_eq!(
+            error("R8G8B8_FLOAT"),
+            FormatError::UnsupportedDepth {
+                sample_type: SampleType::Float,
+                bits: 8
+            }
+        );
+        assert_eq!(
+            error("R8_HALF"),
+            FormatError::UnknownSampleType("HALF".to_owned())
+        );
+        assert_eq!(error("R5R5"), FormatError::DuplicateChannel('R'));
+        assert_eq!(error("A8X8"), FormatError::NoColorChannels);
+        assert_eq!(error("L8R8"), FormatError::GrayWithColor);
+        assert_eq!(error("I4A4"), FormatError::IndexWithColor);
+        assert_eq!(
+            error("0: R=0"),
+            FormatError::InvalidBitsPerPixel("0".to_owned())
+        );
+        assert_eq!(
+            error("8: R=0-8"),
+            FormatError::BitOutOfRange {
+                bit: 8,
+                bits_per_pixel: 8
+            }
+        );
+        assert_eq!(error("8: R=a"), FormatError::InvalidBit("a".to_owned()));
+        assert_eq!(error("8: R"), FormatError::MissingBits("R".to_owned()));
+    }
+}
@@ -0,0 +1,89 @@
+use std::{fmt::Display, io, path::PathBuf};
+
+use super::file_data::FileData;
+use super::process_memory::{self, MemoryRegion};
+
+/// Where the bytes being viewed come from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DataSource {
+    /// A file, or a named pipe, which is read to its end
+    File(PathBuf),
+    /// Data piped in, which can only be read once
+    Stdin,
+    /// A region of a running process's memory, read from `offset` bytes in
+    /// for regions too big to read whole
+    Process {
+        pid: u32,
+        region: MemoryRegion,
+        offset: u64,
+    },
+}
+
+impl DataSource {
+    /// The source a command line path names, `-` being standard input.
+    pub fn from_path(path: PathBuf) -> Self {
+        if path.as_os_str() == "-" {
+            DataSource::Stdin
+        } else {
+            DataSource::File(path)
+        }
+    }
+
+    /// Reads the source. Big files are mapped rather than read, the way
+    /// `FileData::load` does it, and `progress` works like it does there.
+    pub fn load(&self, progress: impl FnMut(u64, Option<u64>) -> bool) -> io::Result<FileData> {
+        match self {
+            DataSource::File(path) => FileData::load(path, progress),
+            DataSource::Stdin => FileData::load_stdin(progress),
+            DataSource::Process {
+                pid,
+                region,
+                offset,
+            } => process_memory::read_region(*pid, region, *offset, progress),
+        }
+    }
+
+    /// Reads the source like `load`, but never maps files, for sources that
+    /// are watched. Tools that rewrite a file usually truncate it first,
+    /// which would make reading a map of it fault.
+    pub fn load_copy(
+        &self,
+        progress: impl FnMut(u64, Option<u64>) -> bool,
+    ) -> io::Result<FileData> {
+        match self {
+            DataSource::File(path) => FileData::load_copy(path, progress),
+            _ => self.load(progress),
+        }
+    }
+
+    /// Whether the source can be read more than once.
+    pub fn can_reload(&self) -> bool {
+        !matches!(self, DataSource::Stdin)
+    }
+
+    /// Whether the source is refreshed whether or not it is being watched.
+    /// Process memory has no way to tell it changed, and is rarely worth
+    /// looking at as it was.
+    pub fn always_refreshed(&self) -> bool {
+        matches!(self, DataSource::Process { .. })
+    }
+}
+
+impl Display for DataSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataSource::File(path) => write!(f, "{}", path.to_string_lossy()),
+            DataSource::Stdin => f.write_str("standard input"),
+            DataSource::Process {
+                pid,
+                region,
+                offset: 0,
+            } => write!(f, "process {pid} at {region}"),
+            DataSource::Process {
+                pid,
+                region,
+                offset,
+            } => write!(f, "process {pid} at {region} + {offset:#x}"),
+        }
+    }
+}
@@ -0,0 +1,1119 @@
+use crate::alpha_mode::AlphaMode;
+use crate::byte_order::ByteOrder;
+use crate::channel_scaling::ChannelScaling;
+use crate::palette::Palette;
+use crate::pixel_order::PixelOrder;
+use crate::planar::PlanarLayout;
+use crate::preview::{DataWindow, Pixel};
+use crate::row_stride::RowStride;
+use crate::sample::ToneSettings;
+use crate::shader::DecodingScheme;
+use crate::tiling::Tiling;
+use crate::yuv::{self, Frame420, YuvSettings};
+
+/// Reads a single bit from the packed buffer. Bit 0 is the most significant
+/// bit of the first word, and bits past the end of the buffer read as 0, the
+/// same way `fs_main` in `shader.wgsl` does it.
+fn read_bit(buffer: &[u32], bit_index: u64) -> u32 {
+    let array_index = bit_index / 32;
+    let bit_shift = 31 - (bit_index % 32);
+
+    usize::try_from(array_index)
+        .ok()
+        .and_then(|i| buffer.get(i))
+        .map(|word| (word >> bit_shift) & 1)
+        .unwrap_or(0)
+}
+
+/// Reads the 8 bit sample starting at `bit_index`, the same way
+/// `read_sample` in `shader.wgsl` does.
+fn read_sample(buffer: &[u32], bit_index: u64) -> u8 {
+    (0..8).fold(0, |value, bit| {
+        (value << 1) | read_bit(buffer, bit_index + bit) as u8
+    })
+}
+
+/// One of the numbers a pixel is decoded from: a channel, a palette index
+/// or a YUV sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelValue {
+    pub name: &'static str,
+    /// The bits of the channel, most significant first, as they are stored
+    pub raw: u32,
+    pub depth: u32,
+    /// The number the bits stand for, complemented for inverted schemes
+    pub value: f32,
+}
+
+/// Where a pixel is read from and what it decodes to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelInspection {
+    /// The bits of the file the pixel is read from, as `pixel_file_bits`
+    /// finds them
+    pub file_bits: Vec<u64>,
+    /// Whether each of `file_bits` is set. Bits past the end of the file
+    /// read as 0.
+    pub raw_bits: Vec<bool>,
+    /// What the pixel is decoded from. Empty for compressed blocks, which
+    /// are only decoded whole.
+    pub channels: Vec<ChannelValue>,
+    pub pixel: Pixel,
+}
+
+/// Decodes pixels on the CPU with the same results as `fs_main` in
+/// `shader.wgsl`.
+#[derive(Clone, Copy)]
+pub struct Decoder<'a> {
+    pub decoding_scheme: &'a DecodingScheme,
+    pub channel_scaling: ChannelScaling,
+    pub pixel_order: PixelOrder,
+    pub byte_order: ByteOrder,
+    pub planar_layout: PlanarLayout,
+    pub tiling: Tiling,
+    pub row_stride: RowStride,
+    pub palette: &'a Palette,
+    pub yuv: YuvSettings,
+    pub tone: ToneSettings,
+}
+
+impl<'a> Decoder<'a> {
+    /// Decodes `decoding_scheme` with every other setting left at its
+    /// default, looking indices up in `palette`.
+    pub fn new(decoding_scheme: &'a DecodingScheme, palette: &'a Palette) -> Self {
+        Self {
+            decoding_scheme,
+            channel_scaling: ChannelScaling::default(),
+            pixel_order: PixelOrder::default(),
+            byte_order: ByteOrder::default(),
+            planar_layout: PlanarLayout::default(),
+            tiling: Tiling::default(),
+            row_stride: RowStride::UNPADDED,
+            palette,
+            yuv: YuvSettings::default(),
+            tone: ToneSettings::default(),
+        }
+    }
+}
+
+impl Decoder<'_> {
+    /// Where bit `color_bit` of the pixel at `pixel_index` is in the window,
+    /// the same way `bit_address` in `shader.wgsl` finds it.
+    fn bit_address(
+        &self,
+        window: &DataWindow,
+        target_width: u32,
+        pixel_index: u64,
+        color_bit: u32,
+    ) -> u64 {
+        self.data_bit_address(
+            u64::from(window.plane_stride),
+            target_width,
+            pixel_index,
+            color_bit,
+        ) + u64::from(window.bit_offset)
+    }
+
+    /// Where bit `color_bit` of the pixel at `pixel_index` is from the start
+    /// of the data, for planes that are whole images `plane_stride` bits
+    /// apart.
+    fn data_bit_address(
+        &self,
+        plane_stride: u64,
+        target_width: u32,
+        pixel_index: u64,
+        color_bit: u32,
+    ) -> u64 {
+        let bits_per_pixel = self.decoding_scheme.bits_per_pixel;
+        let layout = &self.planar_layout;
+
+        if !layout.is_planar() {
+            return self
+                .row_stride
+                .pixel_bit_index(pixel_index, target_width, |pixel_index| {
+                    self.pixel_order
+                        .pixel_bit_index(pixel_index, bits_per_pixel)
+                })
+                + u64::from(color_bit);
+        }
+
+        let plane_bits = layout.bits_per_plane_pixel(bits_per_pixel);
+        let plane = u64::from(layout.plane_for_field(color_bit / plane_bits));
+        let plane_bit = self.pixel_order.pixel_bit_index(pixel_index, plane_bits)
+            + u64::from(color_bit % plane_bits);
+
+        let chunk_bits = u64::from(layout.chunk_bits(bits_per_pixel, target_width));
+        if chunk_bits == 0 {
+            return plane * plane_stride + plane_bit;
+        }
+        let chunk = plane_bit / chunk_bits;
+        chunk * chunk_bits * u64::from(layout.planes())
+            + plane * chunk_bits
+            + plane_bit % chunk_bits
+    }
+
+    /// Assembles one channel from its assigned bits, most significant bit
+    /// first, returning its value and how many bits it has.
+    fn assemble_channel(
+        &self,
+        channel: &[u32],
+        window: &DataWindow,
+        target_width: u32,
+        pixel_index: u64,
+    ) -> (u32, u32) {
+        channel
+            .iter()
+            .fold((0u32, 0u32), |(value, depth), color_bit| {
+                let address = self.bit_address(window, target_width, pixel_index, *color_bit);
+                let bit = read_bit(&window.words, address);
+                ((value << 1) | bit, depth + 1)
+            })
+    }
+
+    /// Decodes the pixel at `x`, `y` of the image in `window`.
+    pub fn decode_pixel(&self, window: &DataWindow, target_width: u32, x: u32, y: u32) -> Pixel {
+        let decoding_scheme = self.decoding_scheme;
+        let pixel_index =
+            self.tiling
+                .pixel_index(x, y, target_width, decoding_scheme.bits_per_pixel);
+
+        let assemble = |channel| self.assemble_channel(channel, window, target_width, pixel_index);
+        // Assembles one channel, complementing it if `inverted`, and brings
+        // it to 8 bits
+        let decode_channel = |channel, inverted: bool, is_color: bool| {
+            let (mut value, depth) = assemble(channel);
+            if inverted && depth > 0 {
+                value ^= u32::MAX >> (32 - depth);
+            }
+            let sample_type = decoding_scheme.sample_type;
+            if depth > 0 && self.tone.maps(sample_type, depth) {
+                self.tone.map(sample_type, value, depth, is_color)
+            } else {
+                self.channel_scaling.scale(value, depth)
+            }
+        };
+
+        if decoding_scheme.block.is_some() {
+            // Blocks are decoded when the window is read
+            let word = window.words[(y * target_width + x) as usize];
+            let [red, green, blue, alpha] = word.to_be_bytes();
+            return Pixel {
+                red,
+                green,
+                blue,
+                alpha,
+            };
+        }
+
+        if let Some(format) = decoding_scheme.yuv {
+            let samples = yuv::sample_bits(format, window, target_width, self.row_stride, x, y);
+            let [y, u, v] = samples.map(|bit| read_sample(&window.words, bit));
+            return self.yuv.convert(y, u, v);
+        }
+
+        if decoding_scheme.is_indexed() {
+            let (index, _) = assemble(&decoding_scheme.index);
+            return self.palette.color(index);
+        }
+
+        let inverted = decoding_scheme.inverted;
+        Pixel {
+            red: decode_channel(&decoding_scheme.red, inverted, true),
+            green: decode_channel(&decoding_scheme.green, inverted, true),
+            blue: decode_channel(&decoding_scheme.blue, inverted, true),
+            alpha: if decoding_scheme.has_alpha() {
+                decode_channel(&decoding_scheme.alpha, false, false)
+            } else {
+                u8::MAX
+            },
+        }
+    }
+
+    /// The bits of the `file_len` byte file the pixel at `x`, `y` of the
+    /// image starting at `start_bit` is read from, in the order the decoding
+    /// scheme numbers them. Empty for pixels that aren't read from their own
+    /// bits: 4:2:0 frames and compressed blocks.
+    pub fn pixel_file_bits(
+        &self,
+        file_len: usize,
+        start_bit: u64,
+        target_width: u32,
+        x: u32,
+        y: u32,
+    ) -> Vec<u64> {
+        let decoding_scheme = self.decoding_scheme;
+        if decoding_scheme.block.is_some() {
+            return vec![];
+        }
+
+        let bits: Vec<u64> = match decoding_scheme.yuv {
+            Some(format) if format.is_420() => return vec![],
+            Some(format) => {
+                let window = DataWindow::default();
+                yuv::sample_bits(format, &window, target_width, self.row_stride, x, y)
+                    .iter()
+                    .flat_map(|sample| *sample..sample + 8)
+                    .collect()
+            }
+            None => {
+                let bits_per_pixel = decoding_scheme.bits_per_pixel;
+                let pixel_index = self.tiling.pixel_index(x, y, target_width, bits_per_pixel);
+                let plane_stride = self.planar_layout.plane_stride(file_len) * 8;
+                (0..bits_per_pixel)
+                    .map(|bit| self.data_bit_address(plane_stride, target_width, pixel_index, bit))
+                    .collect()
+            }
+        };
+
+        bits.into_iter()
+            .map(|bit| self.byte_order.file_bit(start_bit + bit))
+            .collect()
+    }
+
+    /// Reads and decodes the pixel at `x`, `y` of the image starting at
+    /// `start_bit`, keeping what it is made of along the way.
+    pub fn inspect_pixel(
+        &self,
+        file_data: &[u8],
+        start_bit: u64,
+        target_width: u32,
+        x: u32,
+        y: u32,
+    ) -> PixelInspection {
+        let scheme = self.decoding_scheme;
+        let pixel_index = self
+            .tiling
+            .pixel_index(x, y, target_width, scheme.bits_per_pixel);
+        // Tiles can put the pixel further in than its line
+        let lines = (pixel_index / u64::from(target_width.max(1))).max(u64::from(y)) + 1;
+        let lines = u32::try_from(lines).unwrap_or(u32::MAX);
+        let window = self.read_file_lines(file_data, start_bit, target_width, lines);
+
+        let channel_value = |name, channel: &[u32], inverted: bool| {
+            let (raw, depth) = self.assemble_channel(channel, &window, target_width, pixel_index);
+            let bits = if inverted && depth > 0 {
+                raw ^ (u32::MAX >> (32 - depth))
+            } else {
+                raw
+            };
+            ChannelValue {
+                name,
+                raw,
+                depth,
+                value: scheme.sample_type.value(bits, depth),
+            }
+        };
+
+        let channels = if scheme.block.is_some() {
+            vec![]
+        } else if let Some(format) = scheme.yuv {
+            let samples = yuv::sample_bits(format, &window, target_width, self.row_stride, x, y);
+            ["Y", "U", "V"]
+                .into_iter()
+                .zip(samples)
+                .map(|(name, bit)| {
+                    let raw = u32::from(read_sample(&window.words, bit));
+                    ChannelValue {
+                        name,
+                        raw,
+                        depth: 8,
+                        value: raw as f32,
+                    }
+                })
+                .collect()
+        } else if scheme.is_indexed() {
+            let (raw, depth) =
+                self.assemble_channel(&scheme.index, &window, target_width, pixel_index);
+            vec![ChannelValue {
+                name: "Index",
+                raw,
+                depth,
+                value: raw as f32,
+            }]
+        } else {
+            [
+                channel_value("R", &scheme.red, scheme.inverted),
+                channel_value("G", &scheme.green, scheme.inverted),
+                channel_value("B", &scheme.blue, scheme.inverted),
+                channel_value("A", &scheme.alpha, false),
+            ]
+            .into_iter()
+            .filter(|channel| channel.depth > 0)
+            .collect()
+        };
+
+        let file_bits = self.pixel_file_bits(file_data.len(), start_bit, target_width, x, y);
+        let raw_bits = file_bits
+            .iter()
+            .map(|bit| {
+                usize::try_from(bit / 8)
+                    .ok()
+                    .and_then(|i| file_data.get(i))
+                    .is_some_and(|byte| (byte >> (7 - bit % 8)) & 1 == 1)
+            })
+            .collect();
+
+        PixelInspection {
+            file_bits,
+            raw_bits,
+            channels,
+            pixel: self.decode_pixel(&window, target_width, x, y),
+        }
+    }
+
+    /// The smallest and largest color channel values of `lines` full lines,
+    /// for normalizing them. `None` for schemes that aren't made of channel
+    /// bits.
+    pub fn value_range(
+        &self,
+        window: &DataWindow,
+        target_width: u32,
+        lines: u32,
+    ) -> Option<(f32, f32)> {
+        let scheme = self.decoding_scheme;
+        if scheme.yuv.is_some() || scheme.block.is_some() || scheme.is_indexed() {
+            return None;
+        }
+
+        let mut range: Option<(f32, f32)> = None;
+        for (x, y) in (0..lines).flat_map(|y| (0..target_width).map(move |x| (x, y))) {
+            let pixel_index = self
+                .tiling
+                .pixel_index(x, y, target_width, scheme.bits_per_pixel);
+            for channel in [&scheme.red, &scheme.green, &scheme.blue] {
+                let (mut raw, depth) =
+                    self.assemble_channel(channel, window, target_width, pixel_index);
+                if depth == 0 {
+                    continue;
+                }
+                if scheme.inverted {
+                    raw ^= u32::MAX >> (32 - depth);
+                }
+                let value = scheme.sample_type.value(raw, depth);
+                range = Some(range.map_or((value, value), |(min, max)| {
+                    (min.min(value), max.max(value))
+                }));
+            }
+        }
+
+        range.map(|(min, max)| {
+            if min < max {
+                (min, max)
+            } else {
+                (min, min + 1.0)
+            }
+        })
+    }
+
+    /// Decodes `lines` full lines of `target_width` pixels, row by row.
+    pub fn decode_lines(&self, window: &DataWindow, target_width: u32, lines: u32) -> Vec<Pixel> {
+        (0..lines)
+            .flat_map(|y| (0..target_width).map(move |x| (x, y)))
+            .map(|(x, y)| self.decode_pixel(window, target_width, x, y))
+            .collect()
+    }
+
+    /// Decodes `lines` full lines straight from file data, starting at
+    /// `start_bit`. Only the bytes those lines cover are reordered and
+    /// packed.
+    pub fn decode_file_lines(
+        &self,
+        file_data: &[u8],
+        start_bit: u64,
+        target_width: u32,
+        lines: u32,
+    ) -> Vec<Pixel> {
+        let window = self.read_file_lines(file_data, start_bit, target_width, lines);
+        self.decode_lines(&window, target_width, lines)
+    }
+
+    /// The range of color channel values in `lines` full lines of file data,
+    /// like `value_range`.
+    pub fn file_value_range(
+        &self,
+        file_data: &[u8],
+        start_bit: u64,
+        target_width: u32,
+        lines: u32,
+    ) -> Option<(f32, f32)> {
+        let window = self.read_file_lines(file_data, start_bit, target_width, lines);
+        self.value_range(&window, target_width, lines)
+    }
+
+    /// Reads the window `lines` full lines starting at `start_bit` decode
+    /// from.
+    fn read_file_lines(
+        &self,
+        file_data: &[u8],
+        start_bit: u64,
+        target_width: u32,
+        lines: u32,
+    ) -> DataWindow {
+        if let Some(format) = self.decoding_scheme.yuv.filter(|format| format.is_420()) {
+            let frame = Frame420::locate(
+                format,
+                file_data.len(),
+                start_bit,
+                target_width,
+                self.yuv.frame_height,
+            );
+            let luma_bits = u64::from(lines) * u64::from(target_width) * 8;
+            return frame.read(file_data, self.byte_order, luma_bits);
+        }
+
+        if let Some(block) = self.decoding_scheme.block {
+            return block.read(file_data, self.byte_order, start_bit, target_width, lines);
+        }
+
+        let line_bits = match self.row_stride.bits() {
+            0 => {
+                u64::from(target_width)
+                    * u64::from(
+                        self.planar_layout
+                            .bits_per_plane_pixel(self.decoding_scheme.bits_per_pixel),
+                    )
+            }
+            bits => u64::from(bits),
+        };
+        let plane_bits = u64::from(lines) * line_bits;
+
+        self.planar_layout
+            .read(file_data, self.byte_order, start_bit, plane_bits)
+    }
+}
+
+/// Background shown through transparent pixels, in 8x8 image pixel squares.
+fn checkerboard(x: u32, y: u32) -> u32 {
+    if ((x / 8) + (y / 8)).is_multiple_of(2) {
+        204
+    } else {
+        255
+    }
+}
+
+/// Flattens a decoded pixel to the opaque color the preview shows for it.
+/// `x` and `y` are the pixel's position in the image.
+pub fn apply_alpha_mode(pixel: Pixel, alpha_mode: AlphaMode, x: u32, y: u32) -> Pixel {
+    let alpha = u32::from(pixel.alpha);
+    let transparency = u32::from(u8::MAX) - alpha;
+
+    let composite = |channel: u8, premultiplied: bool| -> u8 {
+        let background = checkerboard(x, y) * transparency;
+        let value = if premultiplied {
+            u32::from(channel) + (background + 127) / 255
+        } else {
+            (u32::from(channel) * alpha + background + 127) / 255
+        };
+        value.min(255) as u8
+    };
+
+    let (red, green, blue) = match alpha_mode {
+        AlphaMode::Ignore => (pixel.red, pixel.green, pixel.blue),
+        AlphaMode::Checkerboard => (
+            composite(pixel.red, false),
+            composite(pixel.green, false),
+            composite(pixel.blue, false),
+        ),
+        AlphaMode::PremultipliedCheckerboard => (
+            composite(pixel.red, true),
+            composite(pixel.green, true),
+            composite(pixel.blue, true),
+        ),
+        AlphaMode::AlphaOnly => (pixel.alpha, pixel.alpha, pixel.alpha),
+    };
+
+    Pixel {
+        red,
+        green,
+        blue,
+        alpha: u8::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_order::{Endianness, UnitSize};
+    use crate::pixel_mode::PixelMode;
+    use crate::planar::PlaneInterleave;
+    use crate::sample::ToneMapping;
+    use crate::tiling::TileOrder;
+    use crate::yuv::YuvRange;
+
+    fn pixel(red: u8, green: u8, blue: u8) -> Pixel {
+        pixel_alpha(red, green, blue, u8::MAX)
+    }
+
+    fn pixel_alpha(red: u8, green: u8, blue: u8, alpha: u8) -> Pixel {
+        Pixel {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    /// Zero fill keeps every bit where the scheme puts it, so these goldens
+    /// show exactly which bits each mode reads.
+    fn decode_bytes(mode: PixelMode, bytes: &[u8], target_width: u32, lines: u32) -> Vec<Pixel> {
+        decoder(&mode, ChannelScaling::ZeroFill).decode_lines(
+            &DataWindow::new(bytes, 0),
+            target_width,
+            lines,
+        )
+    }
+
+    fn decoder(mode: &PixelMode, channel_scaling: ChannelScaling) -> Decoder<'_> {
+        static PALETTE: std::sync::OnceLock<Palette> = std::sync::OnceLock::new();
+        let palette = PALETTE.get_or_init(|| Palette::grayscale(8));
+        Decoder {
+            channel_scaling,
+            ..Decoder::new(mode.decoding_scheme(), palette)
+        }
+    }
+
+    #[test]
+    fn rgb() {
+        let pixels = decode_bytes(PixelMode::Rgb, &[0x11, 0x22, 0x33, 0xAA, 0xBB, 0xCC], 2, 1);
+        assert_eq!(pixels, [pixel(0x33, 0x22, 0x11), pixel(0xCC, 0xBB, 0xAA)]);
+    }
+
+    #[test]
+    fn bgr() {
+        // The blue byte is read from past the end of the 24 bit pixel
+        let pixels = decode_bytes(
+            PixelMode::Bgr,
+            &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77],
+            2,
+            1,
+        );
+        assert_eq!(pixels, [pixel(0x22, 0x33, 0x44), pixel(0x55, 0x66, 0x77)]);
+    }
+
+    #[test]
+    fn bpp8() {
+        let pixels = decode_bytes(PixelMode::Bpp8, &[0x00, 0x7F, 0xFF], 3, 1);
+        assert_eq!(
+            pixels,
+            [
+                pixel(0x00, 0x00, 0x00),
+                pixel(0x7F, 0x7F, 0x7F),
+                pixel(0xFF, 0xFF, 0xFF)
+            ]
+        );
+    }
+
+    #[test]
+    fn g3b5r5g3() {
+        let pixels = decode_bytes(PixelMode::G3b5r5g3, &[0x1F, 0xF8, 0xE0, 0x07], 2, 1);
+        assert_eq!(pixels, [pixel(0xF8, 0x00, 0xF8), pixel(0x00, 0xFC, 0x00)]);
+    }
+
+    #[test]
+    fn rgba32() {
+        let pixels = decode_bytes(PixelMode::Rgba32, &[0x11, 0x22, 0x33, 0x44], 1, 1);
+        assert_eq!(pixels, [pixel_alpha(0x44, 0x33, 0x22, 0x11)]);
+    }
+
+    #[test]
+    fn rgb565() {
+        let pixels = decode_bytes(PixelMode::Rgb565, &[0xF8, 0x1F, 0x07, 0xE0], 2, 1);
+        assert_eq!(pixels, [pixel(0xF8, 0x00, 0xF8), pixel(0x00, 0xFC, 0x00)]);
+    }
+
+    #[test]
+    fn argb8888() {
+        let pixels = decode_bytes(PixelMode::Argb8888, &[0x11, 0x22, 0x33, 0x44], 1, 1);
+        assert_eq!(pixels, [pixel_alpha(0x33, 0x22, 0x11, 0x44)]);
+    }
+
+    #[test]
+    fn rgba4444() {
+        let pixels = decode_bytes(PixelMode::Rgba4444, &[0x12, 0x34], 1, 1);
+        assert_eq!(pixels, [pixel_alpha(0x40, 0x30, 0x20, 0x10)]);
+    }
+
+    #[test]
+    fn indexed_modes_look_up_the_palette() {
+        let palette = Palette::new((0..=255).map(|i| pixel(i, 255 - i, 0x55)).collect());
+        let decode = |mode: PixelMode, bytes: &[u8], target_width| {
+            Decoder {
+                // Indices are never scaled, whatever the scaling mode
+                channel_scaling: ChannelScaling::Replicate,
+                ..Decoder::new(mode.decoding_scheme(), &palette)
+            }
+            .decode_lines(&DataWindow::new(bytes, 0), target_width, 1)
+        };
+
+        let indices = |pixels: Vec<Pixel>| pixels.iter().map(|p| p.red).collect::<Vec<_>>();
+        assert_eq!(
+            indices(decode(PixelMode::Indexed1, &[0b1010_0000], 4)),
+            [1, 0, 1, 0]
+        );
+        assert_eq!(
+            indices(decode(PixelMode::Indexed2, &[0b1110_0100], 4)),
+            [3, 2, 1, 0]
+        );
+        assert_eq!(indices(decode(PixelMode::Indexed4, &[0xA5], 2)), [10, 5]);
+        assert_eq!(
+            decode(PixelMode::Indexed8, &[0xC8], 1),
+            [pixel(0xC8, 0x37, 0x55)]
+        );
+    }
+
+    #[test]
+    fn indices_past_the_palette_are_black() {
+        let palette = Palette::new(vec![pixel(1, 2, 3)]);
+        let decoder = Decoder {
+            channel_scaling: ChannelScaling::Replicate,
+            ..Decoder::new(PixelMode::Indexed2.decoding_scheme(), &palette)
+        };
+        assert_eq!(
+            decoder.decode_lines(&DataWindow::new(&[0b0001_0000], 0), 2, 1),
+            [pixel(1, 2, 3), pixel(0, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn sub_byte_gray_modes() {
+        let decode = |mode: PixelMode, pixel_order, bytes: &[u8], target_width| {
+            let mut decoder = decoder(&mode, ChannelScaling::Replicate);
+            decoder.pixel_order = pixel_order;
+            decoder
+                .decode_lines(&DataWindow::new(bytes, 0), target_width, 1)
+                .iter()
+                .map(|p| {
+                    assert!(p.red == p.green && p.green == p.blue);
+                    p.red
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let msb = PixelOrder::MsbFirst;
+        let lsb = PixelOrder::LsbFirst;
+        assert_eq!(
+            decode(PixelMode::Mono1, msb, &[0b1100_0001], 8),
+            [255, 255, 0, 0, 0, 0, 0, 255]
+        );
+        assert_eq!(
+            decode(PixelMode::Mono1, lsb, &[0b1100_0001], 8),
+            [255, 0, 0, 0, 0, 0, 255, 255]
+        );
+        assert_eq!(
+            decode(PixelMode::Mono1Inverted, msb, &[0b1100_0001], 8),
+            [0, 0, 255, 255, 255, 255, 255, 0]
+        );
+        assert_eq!(
+            decode(PixelMode::Gray2, msb, &[0b0001_1011], 4),
+            [0, 85, 170, 255]
+        );
+        assert_eq!(
+            decode(PixelMode::Gray2, lsb, &[0b0001_1011], 4),
+            [255, 170, 85, 0]
+        );
+        assert_eq!(
+            decode(PixelMode::Gray4, msb, &[0x1E, 0x80], 4),
+            [0x11, 0xEE, 0x88, 0x00]
+        );
+        assert_eq!(
+            decode(PixelMode::Gray4, lsb, &[0x1E, 0x80], 4),
+            [0xEE, 0x11, 0x00, 0x88]
+        );
+    }
+
+    #[test]
+    fn pixel_order_leaves_wide_pixels_alone() {
+        for bits_per_pixel in [3, 8, 12, 24] {
+            for pixel_index in 0..10 {
+                assert_eq!(
+                    PixelOrder::LsbFirst.pixel_bit_index(pixel_index, bits_per_pixel),
+                    pixel_index * u64::from(bits_per_pixel)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn little_endian_rgb565() {
+        let mode = PixelMode::Rgb565;
+        let mut decoder = decoder(&mode, ChannelScaling::ZeroFill);
+        decoder.byte_order = ByteOrder {
+            unit_size: UnitSize::Bits16,
+            endianness: Endianness::Little,
+        };
+
+        // The same pixels as the rgb565 test, stored little endian
+        let data = [0x1F, 0xF8, 0xE0, 0x07];
+        assert_eq!(
+            decoder.decode_file_lines(&data, 0, 2, 1),
+            [pixel(0xF8, 0x00, 0xF8), pixel(0x00, 0xFC, 0x00)]
+        );
+        // Starting at the second pixel begins the read mid way through the
+        // file's first 32 bit unit
+        decoder.byte_order.unit_size = UnitSize::Bits32;
+        assert_eq!(
+            decoder.decode_file_lines(&[0xE0, 0x07, 0x1F, 0xF8], 16, 1, 1),
+            [pixel(0x00, 0xFC, 0x00)]
+        );
+    }
+
+    #[test]
+    fn line_interleaved_bitplanes() {
+        let mode = PixelMode::Indexed2;
+        // The test palette is an 8 bit grayscale ramp, so red is the index
+        let mut decoder = decoder(&mode, ChannelScaling::Replicate);
+        // Plane 0 holds the low bit of each index, like Amiga bitplanes
+        decoder.planar_layout = PlanarLayout::new(2, &[1, 0], PlaneInterleave::Line, 0).unwrap();
+
+        // Two 8 pixel lines, each plane 0 then plane 1
+        let data = [0b1010_1010, 0b1100_1100, 0xFF, 0x00];
+        let indices = decoder
+            .decode_file_lines(&data, 0, 8, 2)
+            .iter()
+            .map(|p| p.red)
+            .collect::<Vec<_>>();
+        assert_eq!(indices, [3, 2, 1, 0, 3, 2, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn word_interleaved_bitplanes() {
+        let mode = PixelMode::Indexed2;
+        let mut decoder = decoder(&mode, ChannelScaling::Replicate);
+        decoder.planar_layout = PlanarLayout::new(2, &[], PlaneInterleave::Word, 0).unwrap();
+
+        // 16 pixels of plane 0, then 16 of plane 1, then the next 16 pixels
+        let data = [0x80, 0x00, 0x80, 0x01, 0x00, 0x01, 0x00, 0x00];
+        let indices = decoder
+            .decode_file_lines(&data, 0, 32, 1)
+            .iter()
+            .map(|p| p.red)
+            .collect::<Vec<_>>();
+        assert_eq!(indices[0], 3);
+        assert_eq!(indices[15], 1);
+        assert_eq!(indices[31], 2);
+        assert_eq!(indices.iter().filter(|&&i| i != 0).count(), 3);
+    }
+
+    #[test]
+    fn image_interleaved_color_planes() {
+        let mode = PixelMode::Rgb;
+        let mut decoder = decoder(&mode, ChannelScaling::Replicate);
+        // Red, green then blue planes. The rgb mode reads blue from the first
+        // byte, so the first field comes from the last plane
+        decoder.planar_layout =
+            PlanarLayout::new(3, &[2, 1, 0], PlaneInterleave::Image, 0).unwrap();
+
+        let data = [0x10, 0x11, 0x20, 0x21, 0x30, 0x31];
+        assert_eq!(
+            decoder.decode_file_lines(&data, 0, 2, 1),
+            [pixel(0x10, 0x20, 0x30), pixel(0x11, 0x21, 0x31)]
+        );
+        // Later lines start as far into every plane
+        assert_eq!(
+            decoder.decode_file_lines(&data, 8, 1, 1),
+            [pixel(0x11, 0x21, 0x31)]
+        );
+    }
+
+    #[test]
+    fn tiled_pixels() {
+        let mode = PixelMode::Bpp8;
+        let mut decoder = decoder(&mode, ChannelScaling::Replicate);
+        decoder.tiling = Tiling::new(TileOrder::Tiles, 2, 2).unwrap();
+
+        // Two 2x2 tiles side by side, storing the image's pixels 0 to 7
+        let data = [0, 1, 4, 5, 2, 3, 6, 7];
+        let gray = |pixels: Vec<Pixel>| pixels.iter().map(|p| p.red).collect::<Vec<_>>();
+        assert_eq!(
+            gray(decoder.decode_file_lines(&data, 0, 4, 2)),
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        );
+    }
+
+    #[test]
+    fn padded_rows() {
+        let (gray, yuyv) = (PixelMode::Bpp8, PixelMode::Yuyv);
+        let mut gray_decoder = decoder(&gray, ChannelScaling::Replicate);
+        let mut yuyv_decoder = decoder(&yuyv, ChannelScaling::Replicate);
+        gray_decoder.row_stride = RowStride::new(4 * 8);
+        yuyv_decoder.row_stride = RowStride::new(4 * 8);
+
+        // Rows of 3 pixels padded to 4 bytes
+        let data = [1, 2, 3, 0xEE, 4, 5, 6, 0xEE, 7, 8, 9];
+        let values = |pixels: Vec<Pixel>| pixels.iter().map(|p| p.red).collect::<Vec<_>>();
+        assert_eq!(
+            values(gray_decoder.decode_file_lines(&data, 0, 3, 3)),
+            [1, 2, 3, 4, 5, 6, 7, 8, 9]
+        );
+
+        // Odd widths of packed YUV start each padded row on a new pair
+        let white = pixel(255, 255, 255);
+        assert_eq!(
+            yuyv_decoder.decode_file_lines(&[235, 128, 16, 128, 235, 128, 16, 128], 0, 1, 2),
+            [white, white]
+        );
+    }
+
+    #[test]
+    fn pixel_file_bits() {
+        let mut rgb565 = decoder(&PixelMode::Rgb565, ChannelScaling::Replicate);
+        assert_eq!(
+            rgb565.pixel_file_bits(64, 4, 10, 2, 1),
+            (4 + 12 * 16..4 + 13 * 16).collect::<Vec<_>>()
+        );
+
+        // Swapped bytes come from the other byte of their unit
+        rgb565.byte_order = ByteOrder {
+            unit_size: UnitSize::Bits16,
+            endianness: Endianness::Little,
+        };
+        let bits = rgb565.pixel_file_bits(64, 0, 10, 0, 0);
+        assert_eq!(bits[..8], (8..16).collect::<Vec<_>>());
+        assert_eq!(bits[8..], (0..8).collect::<Vec<_>>());
+
+        // A bit from each plane, lines of 16 pixels of each plane in turn
+        let mut planar = decoder(&PixelMode::Indexed4, ChannelScaling::Replicate);
+        planar.planar_layout = PlanarLayout::new(4, &[], PlaneInterleave::Line, 0).unwrap();
+        assert_eq!(planar.pixel_file_bits(40, 0, 16, 3, 1), [67, 83, 99, 115]);
+
+        // or planes that are whole images, splitting the file in four
+        planar.planar_layout = PlanarLayout::new(4, &[], PlaneInterleave::Image, 0).unwrap();
+        assert_eq!(planar.pixel_file_bits(40, 0, 16, 3, 1), [19, 99, 179, 259]);
+    }
+
+    #[test]
+    fn inspect_pixel() {
+        let rgb565 = decoder(&PixelMode::Rgb565, ChannelScaling::Replicate);
+        let inspection = rgb565.inspect_pixel(&[0, 0, 0xF8, 0x1F, 0x07, 0xE0], 16, 1, 0, 1);
+
+        assert_eq!(inspection.file_bits, (32..48).collect::<Vec<_>>());
+        let set_bits: Vec<bool> = (0..16).map(|bit| (5..11).contains(&bit)).collect();
+        assert_eq!(inspection.raw_bits, set_bits);
+        let channels: Vec<_> = inspection
+            .channels
+            .iter()
+            .map(|channel| (channel.name, channel.raw, channel.depth))
+            .collect();
+        assert_eq!(channels, [("R", 0, 5), ("G", 0x3F, 6), ("B", 0, 5)]);
+        assert_eq!(inspection.pixel, pixel(0, 0xFF, 0));
+
+        let yuyv = decoder(&PixelMode::Yuyv, ChannelScaling::Replicate);
+        let inspection = yuyv.inspect_pixel(&[235, 128, 16, 128], 0, 2, 1, 0);
+        let samples: Vec<_> = inspection
+            .channels
+            .iter()
+            .map(|channel| (channel.name, channel.raw))
+            .collect();
+        assert_eq!(samples, [("Y", 16), ("U", 128), ("V", 128)]);
+    }
+
+    #[test]
+    fn packed_yuv() {
+        let decode = |mode: PixelMode, bytes: &[u8]| {
+            decoder(&mode, ChannelScaling::Replicate).decode_lines(&DataWindow::new(bytes, 0), 2, 1)
+        };
+        let white_then_black = [pixel(255, 255, 255), pixel(0, 0, 0)];
+
+        assert_eq!(
+            decode(PixelMode::Yuyv, &[235, 128, 16, 128]),
+            white_then_black
+        );
+        assert_eq!(
+            decode(PixelMode::Uyvy, &[128, 235, 128, 16]),
+            white_then_black
+        );
+    }
+
+    #[test]
+    fn planar_yuv_420() {
+        // A 2x4 frame, red over gray, with one chroma sample per 2x2 block
+        let luma = [76, 76, 76, 76, 128, 128, 128, 128];
+        let frames = [
+            (PixelMode::Nv12, [85, 255, 128, 128]),
+            (PixelMode::Nv21, [255, 85, 128, 128]),
+            (PixelMode::I420, [85, 128, 255, 128]),
+            (PixelMode::Yv12, [255, 128, 85, 128]),
+        ];
+
+        let red = pixel(254, 0, 0);
+        let gray = pixel(128, 128, 128);
+        for (mode, chroma) in frames {
+            let mut decoder = decoder(&mode, ChannelScaling::Replicate);
+            decoder.yuv.range = YuvRange::Full;
+            let data = [luma.as_slice(), &chroma].concat();
+
+            assert_eq!(
+                decoder.decode_file_lines(&data, 0, 2, 4),
+                [red, red, red, red, gray, gray, gray, gray],
+                "{mode}"
+            );
+            // Starting on the second line of a pair still uses its chroma
+            assert_eq!(
+                decoder.decode_file_lines(&data, 16, 2, 3),
+                [red, red, gray, gray, gray, gray],
+                "{mode}"
+            );
+        }
+    }
+
+    #[test]
+    fn bit_offset_is_not_byte_aligned() {
+        let pixels = decoder(&PixelMode::Bpp8, ChannelScaling::ZeroFill).decode_lines(
+            &DataWindow::new(&[0x12, 0x34], 4),
+            1,
+            1,
+        );
+        assert_eq!(pixels, [pixel(0x23, 0x23, 0x23)]);
+    }
+
+    #[test]
+    fn lines_wrap_at_target_width() {
+        let pixels = decode_bytes(PixelMode::Bpp8, &[1, 2, 3, 4, 5, 6], 3, 2);
+        let expected = [1, 2, 3, 4, 5, 6].map(|v| pixel(v, v, v));
+        assert_eq!(pixels, expected);
+        assert_eq!(
+            decoder(&PixelMode::Bpp8, ChannelScaling::ZeroFill).decode_pixel(
+                &DataWindow::new(&[1, 2, 3, 4, 5, 6], 0),
+                3,
+                1,
+                1
+            ),
+            pixel(5, 5, 5)
+        );
+    }
+
+    #[test]
+    fn past_end_of_buffer_is_black() {
+        let pixels = decode_bytes(PixelMode::Bpp8, &[0xFF], 6, 1);
+        // The last word is zero padded, and anything past it reads as 0 too
+        assert_eq!(pixels[0], pixel(0xFF, 0xFF, 0xFF));
+        assert!(pixels[1..].iter().all(|p| *p == pixel(0, 0, 0)));
+    }
+
+    #[test]
+    fn file_lines_start_mid_byte() {
+        let data = [0xFF, 0x12, 0x34, 0x56];
+        let pixels =
+            decoder(&PixelMode::Bpp8, ChannelScaling::ZeroFill).decode_file_lines(&data, 12, 2, 1);
+        assert_eq!(pixels, [pixel(0x23, 0x23, 0x23), pixel(0x45, 0x45, 0x45)]);
+    }
+
+    #[test]
+    fn alpha_modes() {
+        let half = pixel_alpha(0xFF, 0x00, 0x80, 0x80);
+
+        assert_eq!(
+            apply_alpha_mode(half, AlphaMode::Ignore, 0, 0),
+            pixel(0xFF, 0x00, 0x80)
+        );
+        assert_eq!(
+            apply_alpha_mode(half, AlphaMode::AlphaOnly, 0, 0),
+            pixel(0x80, 0x80, 0x80)
+        );
+        // Dark square of the checkerboard, then the light one
+        assert_eq!(
+            apply_alpha_mode(half, AlphaMode::Checkerboard, 0, 0),
+            pixel(0xE6, 0x66, 0xA6)
+        );
+        assert_eq!(
+            apply_alpha_mode(half, AlphaMode::Checkerboard, 8, 0),
+            pixel(0xFF, 0x7F, 0xBF)
+        );
+        assert_eq!(
+            apply_alpha_mode(
+                pixel_alpha(0x40, 0x00, 0x80, 0x80),
+                AlphaMode::PremultipliedCheckerboard,
+                0,
+                0
+            ),
+            pixel(0xA6, 0x66, 0xE6)
+        );
+    }
+
+    #[test]
+    fn opaque_schemes_ignore_alpha_mode() {
+        let pixels = decode_bytes(PixelMode::Rgb, &[0x11, 0x22, 0x33], 1, 1);
+        assert_eq!(pixels, [pixel(0x33, 0x22, 0x11)]);
+        assert_eq!(
+            AlphaMode::AlphaOnly.for_scheme(PixelMode::Rgb.decoding_scheme()),
+            AlphaMode::Ignore
+        );
+    }
+
+    #[test]
+    fn channel_scaling() {
+        let window = DataWindow::new(&[0xF8, 0x1F, 0x47, 0x0A], 0);
+        let decode = |channel_scaling| {
+            decoder(&PixelMode::Rgb565, channel_scaling).decode_lines(&window, 2, 1)
+        };
+
+        // Raw values are 31, 0, 31 and 10, 56, 8
+        assert_eq!(
+            decode(ChannelScaling::Replicate),
+            [pixel(0xFF, 0x00, 0xFF), pixel(0x52, 0xE3, 0x42)]
+        );
+        assert_eq!(
+            decode(ChannelScaling::Linear),
+            [pixel(0xFF, 0x00, 0xFF), pixel(0x52, 0xE3, 0x42)]
+        );
+        assert_eq!(
+            decode(ChannelScaling::ZeroFill),
+            [pixel(0xF8, 0x00, 0xF8), pixel(0x50, 0xE0, 0x40)]
+        );
+        assert_eq!(
+            decode(ChannelScaling::Raw),
+            [pixel(31, 0, 31), pixel(10, 56, 8)]
+        );
+    }
+
+    #[test]
+    fn scaling_depths() {
+        for depth in 1..=8 {
+            let max_value = (1 << depth) - 1;
+            for scaling in ChannelScaling::ALL {
+                assert_eq!(scaling.scale(0, depth), 0);
+            }
+            assert_eq!(ChannelScaling::Replicate.scale(max_value, depth), 255);
+            assert_eq!(ChannelScaling::Linear.scale(max_value, depth), 255);
+        }
+        assert_eq!(ChannelScaling::Replicate.scale(0b101, 3), 0b1011_0110);
+        assert_eq!(ChannelScaling::Linear.scale(1, 2), 85);
+        assert_eq!(ChannelScaling::Raw.scale(3, 2), 3);
+    }
+
+    #[test]
+    fn high_bit_depth_and_float_channels() {
+        // Channel values are stored most significant byte first
+        assert_eq!(
+            decode_bytes(PixelMode::Gray16, &[0xFF, 0xFF, 0x80, 0x00], 2, 1),
+            [pixel(255, 255, 255), pixel(128, 128, 128)]
+        );
+
+        let half = |value: u16| value.to_be_bytes();
+        let bytes = [half(0x3C00), half(0x3800), half(0xBC00), half(0x3C00)].concat();
+        assert_eq!(
+            decode_bytes(PixelMode::Rgba16f, &bytes, 1, 1),
+            [pixel_alpha(255, 128, 0, 255)]
+        );
+
+        let bytes = 2.0f32.to_be_bytes();
+        assert_eq!(
+            decode_bytes(PixelMode::Gray32f, &bytes, 1, 1),
+            [pixel(255, 255, 255)]
+        );
+
+        let mut decoder = decoder(&PixelMode::Gray16, ChannelScaling::Replicate);
+        let window = DataWindow::new(&[0x03, 0xE8, 0x07, 0xD0, 0x0B, 0xB8], 0);
+        assert_eq!(decoder.value_range(&window, 3, 1), Some((1000.0, 3000.0)));
+
+        decoder.tone = ToneSettings {
+            tone_mapping: ToneMapping::Normalize,
+            min: 1000.0,
+            max: 3000.0,
+            ..ToneSettings::default()
+        };
+        assert_eq!(
+            decoder.decode_lines(&window, 3, 1),
+            [pixel(0, 0, 0), pixel(128, 128, 128), pixel(255, 255, 255)]
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Decoding of ETC1 and ETC2 RGB blocks.
+
+use super::block_format::expand_bits;
+use super::preview::Pixel;
+
+const MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+/// Distances between the colors of the ETC2 T and H modes.
+const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/// `bits` bits of `block` from `low` up.
+fn bits(block: u64, low: u32, bits: u32) -> u32 {
+    ((block >> low) & ((1 << bits) - 1)) as u32
+}
+
+/// The two bit index of each pixel, stored column by column as its high
+/// bits in the upper half, then its low bits.
+fn pixel_index(block: u64, x: usize, y: usize) -> usize {
+    let p = (x * 4 + y) as u32;
+    (bits(block, 16 + p, 1) << 1 | bits(block, p, 1)) as usize
+}
+
+fn rgb(red: i32, green: i32, blue: i32) -> Pixel {
+    Pixel {
+        red: red.clamp(0, 255) as u8,
+        green: green.clamp(0, 255) as u8,
+        blue: blue.clamp(0, 255) as u8,
+        alpha: u8::MAX,
+    }
+}
+
+fn expand4(value: u32) -> i32 {
+    i32::from(expand_bits(value, 4))
+}
+
+/// Decodes an 8 byte ETC1 block, or with `etc2` an ETC2 RGB block, into
+/// its pixels, row by row.
+pub fn decode_block(block: &[u8], etc2: bool) -> [Pixel; 16] {
+    let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let differential = bits(block, 33, 1) == 1;
+
+    let mut base = [[0; 3]; 2];
+    if differential {
+        for (channel, low) in [59, 51, 43].into_iter().enumerate() {
+            let value = bits(block, low, 5) as i32;
+            // Signed three bit delta
+            let delta = (bits(block, low - 3, 3) as i32) << 29 >> 29;
+            let second = value + delta;
+
+            if etc2 && !(0..32).contains(&second) {
+                return match channel {
+                    0 => decode_t_block(block),
+                    1 => decode_h_block(block),
+                    _ => decode_planar_block(block),
+                };
+            }
+            base[0][channel] = i32::from(expand_bits(value as u32, 5));
+            base[1][channel] = i32::from(expand_bits(second as u32 & 0x1F, 5));
+        }
+    } else {
+        for (channel, low) in [56, 48, 40].into_iter().enumerate() {
+            base[0][channel] = expand4(bits(block, low + 4, 4));
+            base[1][channel] = expand4(bits(block, low, 4));
+        }
+    }
+
+    let tables = [bits(block, 37, 3) as usize, bits(block, 34, 3) as usize];
+    let flip = bits(block, 32, 1) == 1;
+
+    std::array::from_fn(|i| {
+        let (x, y) = (i % 4, i / 4);
+        let subblock = usize::from(if flip { y >= 2 } else { x >= 2 });
+        let [small, large] = MODIFIERS[tables[subblock]];
+        let modifier = [small, large, -small, -large][pixel_index(block, x, y)];
+
+        let [red, green, blue] = base[subblock].map(|value| value + modifier);
+        rgb(red, green, blue)
+    })
+}
+
+/// Paints each pixel with one of four colors.
+fn paint(block: u64, colors: [Pixel; 4]) -> [Pixel; 16] {
+    std::array::from_fn(|i| colors[pixel_index(block, i % 4, i / 4)])
+}
+
+/// The ETC2 T mode: one color, and three around a second one.
+fn decode_t_block(block: u64) -> [Pixel; 16] {
+    let c1 = [
+        bits(block, 59, 2) << 2 | bits(block, 56, 2),
+        bits(block, 52, 4),
+        bits(block, 48, 4),
+    ]
+    .map(expand4);
+    let c2 = [bits(block, 44, 4), bits(block, 40, 4), bits(block, 36, 4)].map(expand4);
+    let distance = DISTANCES[(bits(block, 34, 2) << 1 | bits(block, 32, 1)) as usize];
+
+    let shifted = |color: [i32; 3], by: i32| rgb(color[0] + by, color[1] + by, color[2] + by);
+    paint(
+        block,
+        [
+            shifted(c1, 0),
+            shifted(c2, distance),
+            shifted(c2, 0),
+            shifted(c2, -distance),
+        ],
+    )
+}
+
+/// The ETC2 H mode: two pairs of colors around two base colors.
+fn decode_h_block(block: u64) -> [Pixel; 16] {
+    let c1 = [
+        bits(block, 59, 4),
+        bits(block, 56, 3) << 1 | bits(block, 52, 1),
+        bits(block, 51, 1) << 3 | bits(block, 47, 3),
+    ];
+    let c2 = [bits(block, 43, 4), bits(block, 39, 4), bits(block, 35, 4)];
+
+    // The lowest bit of the distance is which base color is bigger
+    let value = |color: [u32; 3]| color[0] << 8 | color[1] << 4 | color[2];
+    let distance_index =
+        bits(block, 34, 1) << 2 | bits(block, 32, 1) << 1 | u32::from(value(c1) >= value(c2));
+    let distance = DISTANCES[distance_index as usize];
+
+    let (c1, c2) = (c1.map(expand4), c2.map(expand4));
+    let shifted = |color: [i32; 3], by: i32| rgb(color[0] + by, color[1] + by, color[2] + by);
+    paint(
+        block,
+        [
+            shifted(c1, distance),
+            shifted(c1, -distance),
+            shifted(c2, distance),
+            shifted(c2, -distance),
+        ],
+    )
+}
+
+/// The ETC2 planar mode: a gradient from the origin color at the top left
+/// towards a color to the right and one below.
+fn decode_planar_block(block: u64) -> [Pixel; 16] {
+    let expand = |value: u32, bits: u32| i32::from(expand_bits(value, bits));
+
+    let origin = [
+        expand(bits(block, 57, 6), 6),
+        expand(bits(block, 56, 1) << 6 | bits(block, 49, 6), 7),
+        expand(
+            bits(block, 48, 1) << 5 | bits(block, 43, 2) << 3 | bits(block, 39, 3),
+            6,
+        ),
+    ];
+    let horizontal = [
+        expand(bits(block, 34, 5) << 1 | bits(block, 32, 1), 6),
+        expand(bits(block, 25, 7), 7),
+        expand(bits(block, 19, 6), 6),
+    ];
+    let vertical = [
+        expand(bits(block, 13, 6), 6),
+        expand(bits(block, 6, 7), 7),
+        expand(bits(block, 0, 6), 6),
+    ];
+
+    std::array::from_fn(|i| {
+        let (x, y) = ((i % 4) as i32, (i / 4) as i32);
+        let [red, green, blue] = std::array::from_fn(|c| {
+            (x * (horizontal[c] - origin[c]) + y * (vertical[c] - origin[c]) + 4 * origin[c] + 2)
+                >> 2
+        });
+        rgb(red, green, blue)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(pixel: Pixel) -> [u8; 4] {
+        [pixel.red, pixel.green, pixel.blue, pixel.alpha]
+    }
+
+    #[test]
+    fn individual_mode() {
+        // One color for both halves, with the smallest modifiers
+        let mut block = [0x88, 0x44, 0x22, 0, 0, 0, 0, 0];
+        assert!(decode_block(&block, false)
+            .iter()
+            .all(|pixel| rgba(*pixel) == [138, 70, 36, 255]));
+
+        // The pixel right of the first one takes the negative modifier
+        block[5] = 0x10;
+        let pixels = decode_block(&block, false);
+        assert_eq!(rgba(pixels[1]), [134, 66, 32, 255]);
+        assert_eq!(rgba(pixels[4]), [138, 70, 36, 255]);
+    }
+
+    #[test]
+    fn differential_mode() {
+        // Red 16, then 17 in the flipped bottom half
+        let block = [0x81, 0, 0, 0x03, 0, 0, 0, 0];
+        let pixels = decode_block(&block, false);
+        assert_eq!(rgba(pixels[0]), [134, 2, 2, 255]);
+        assert_eq!(rgba(pixels[12]), [142, 2, 2, 255]);
+    }
+
+    #[test]
+    fn etc2_t_mode() {
+        // Red, then blue shifted by 3, with red overflowing in ETC1 terms
+        let block: u64 = 0b11111 << 59 | 0b11 << 56 | 0xF << 36 | 1 << 33 | 1 << 20 | 1 << 4 | 1;
+        let pixels = decode_block(&block.to_be_bytes(), true);
+        assert_eq!(rgba(pixels[0]), [3, 3, 255, 255]);
+        assert_eq!(rgba(pixels[1]), [0, 0, 252, 255]);
+        assert_eq!(rgba(pixels[2]), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn etc2_planar_mode() {
+        // Red from black on the left to full on the right, with blue
+        // overflowing in ETC1 terms
+        let block: u64 = 1 << 42 | 0b11111 << 34 | 1 << 33 | 1 << 32;
+        let red = decode_block(&block.to_be_bytes(), true).map(|pixel| pixel.red);
+        assert_eq!(red[..4], [0, 64, 128, 191]);
+        assert_eq!(red[12..], [0, 64, 128, 191]);
+    }
+
+    /// Blocks of every mode with the pixels Mesa's ETC2 decoder (llvmpipe,
+    /// Mesa 22.3.6) reads out of them, as 0xRRGGBBAA row by row. The
+    /// individual and differential blocks don't overflow, so read the same
+    /// in ETC1.
+    const REFERENCE_BLOCKS: [([u8; 8], [u32; 16]); 10] = [
+        // Individual
+        (
+            [0x9e, 0xbb, 0x35, 0x94, 0x35, 0xa5, 0x30, 0x76],
+            [
+                0x87a921ff, 0xd5f76fff, 0xd6a33dff, 0x9e6b05ff, 0xd5f76fff, 0x5d7f00ff, 0xffd36dff,
+                0x9e6b05ff, 0x5d7f00ff, 0xd5f76fff, 0xd6a33dff, 0xffd36dff, 0xabcd45ff, 0x87a921ff,
+                0xffd36dff, 0xffd36dff,
+            ],
+        ),
+        // Individual
+        (
+            [0x2f, 0x79, 0x50, 0x45, 0xbb, 0x74, 0xa2, 0x70],
+            [
+                0x2b805eff, 0x055a38ff, 0x196e4cff, 0x196e4cff, 0x2b805eff, 0x055a38ff, 0x055a38ff,
+                0x055a38ff, 0xfa9400ff, 0xee8800ff, 0xff9e05ff, 0xff9e05ff, 0xff9e05ff, 0xff9e05ff,
+                0xfa9400ff, 0xee8800ff,
+            ],
+        ),
+        // Differential
+        (
+            [0xd5, 0xb7, 0xce, 0xd2, 0x37, 0x66, 0x96, 0xdd],
+            [
+                0xffffffff, 0xffffffff, 0xab9babff, 0x817181ff, 0xb594adff, 0xb594adff, 0x817181ff,
+                0xab9babff, 0x6c4b64ff, 0x6c4b64ff, 0x817181ff, 0xcfbfcfff, 0xffffffff, 0xffffffff,
+                0xcfbfcfff, 0xf9e9f9ff,
+            ],
+        ),
+        // Differential
+        (
+            [0x29, 0x4f, 0x65, 0x32, 0xc0, 0x2d, 0x5b, 0x74],
+            [
+                0x24455eff, 0x3a5b74ff, 0x6d7e86ff, 0x6d7e86ff, 0x2e4f68ff, 0x183952ff, 0x6d7e86ff,
+                0x43545cff, 0x183952ff, 0x3a5b74ff, 0x43545cff, 0x00060eff, 0x24455eff, 0x2e4f68ff,
+                0x6d7e86ff, 0x1f3038ff,
+            ],
+        ),
+        // T mode
+        (
+            [0x07, 0x2e, 0x0f, 0x1a, 0xa2, 0xdb, 0x9f, 0xac],
+            [
+                0x00ff11ff, 0x00ff11ff, 0x17ff28ff, 0x17ff28ff, 0x00ff11ff, 0x17ff28ff, 0x00e800ff,
+                0x00ff11ff, 0x17ff28ff, 0x00ff11ff, 0x17ff28ff, 0x3322eeff, 0x00e800ff, 0x00e800ff,
+                0x17ff28ff, 0x00e800ff,
+            ],
+        ),
+        // T mode
+        (
+            [0x14, 0x58, 0x7e, 0x1f, 0x94, 0x31, 0x20, 0x85],
+            [
+                0x37ae00ff, 0x77ee11ff, 0x885588ff, 0x77ee11ff, 0x885588ff, 0x77ee11ff, 0x885588ff,
+                0xb7ff51ff, 0xb7ff51ff, 0x885588ff, 0x77ee11ff, 0x885588ff, 0x885588ff, 0xb7ff51ff,
+                0x885588ff, 0x77ee11ff,
+            ],
+        ),
+        // H mode
+        (
+            [0x2c, 0x06, 0xdc, 0x56, 0x4e, 0xf2, 0xea, 0x40],
+            [
+                0x6c9f6cff, 0xd29fc1ff, 0x6c9f6cff, 0x6c9f6cff, 0xd29fc1ff, 0xd29fc1ff, 0xa47193ff,
+                0x3e713eff, 0x6c9f6cff, 0xa47193ff, 0xd29fc1ff, 0xa47193ff, 0x6c9f6cff, 0xd29fc1ff,
+                0xa47193ff, 0x3e713eff,
+            ],
+        ),
+        // H mode
+        (
+            [0x3c, 0x05, 0x9a, 0x47, 0x61, 0x23, 0x91, 0x44],
+            [
+                0x7384c8ff, 0xb7c873ff, 0x000448ff, 0x374800ff, 0x7384c8ff, 0x7384c8ff, 0xb7c873ff,
+                0x7384c8ff, 0x374800ff, 0x374800ff, 0xb7c873ff, 0x7384c8ff, 0xb7c873ff, 0xb7c873ff,
+                0xb7c873ff, 0x374800ff,
+            ],
+        ),
+        // Planar
+        (
+            [0x9d, 0xad, 0x1c, 0xfb, 0x6c, 0x87, 0x6b, 0x08],
+            [
+                0x38ade7ff, 0x689dbeff, 0x988d94ff, 0xc77c6bff, 0x6698b5ff, 0x96888cff, 0xc57762ff,
+                0xf56739ff, 0x948384ff, 0xc3725aff, 0xf36231ff, 0xff5207ff, 0xc16d52ff, 0xf15d28ff,
+                0xff4d00ff, 0xff3d00ff,
+            ],
+        ),
+        // Planar
+        (
+            [0xb5, 0xe0, 0x15, 0xab, 0x7a, 0x76, 0xf1, 0x35],
+            [
+                0x69e14dff, 0x64c748ff, 0x5fae43ff, 0x5a943dff, 0x87cb70ff, 0x82b16aff, 0x7d9865ff,
+                0x787e60ff, 0xa4b592ff, 0x9f9b8dff, 0x9a8288ff, 0x956882ff, 0xc29fb5ff, 0xbd85afff,
+                0xb86caaff, 0xb352a5ff,
+            ],
+        ),
+    ];
+
+    #[test]
+    fn matches_reference_decoder() {
+        for (index, (block, expected)) in REFERENCE_BLOCKS.into_iter().enumerate() {
+            // The first four are the ETC1 modes
+            let versions: &[bool] = if index < 4 { &[false, true] } else { &[true] };
+            for &etc2 in versions {
+                let pixels =
+                    decode_block(&block, etc2).map(|pixel| u32::from_be_bytes(rgba(pixel)));
+                assert_eq!(pixels, expected, "block {block:02x?}, ETC2 {etc2}");
+            }
+        }
+    }
+}
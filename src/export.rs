@@ -0,0 +1,29 @@
+use std::{error::Error, fs::File, io::BufWriter, path::Path};
+
+use super::preview::Pixel;
+
+/// Writes `pixels`, row by row, as an 8 bit RGBA PNG.
+pub fn write_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    pixels: &[Pixel],
+) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgba_bytes(pixels))?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// `pixels` as 8 bit RGBA bytes.
+pub fn rgba_bytes(pixels: &[Pixel]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|p| [p.red, p.green, p.blue, p.alpha])
+        .collect()
+}
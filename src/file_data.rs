@@ -0,0 +1,193 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    ops::Deref,
+    path::Path,
+    sync::Arc,
+};
+
+use memmap2::Mmap;
+
+/// The bytes of an opened file. Files up to `MAP_THRESHOLD` are read into
+/// memory. Bigger ones are memory mapped, so opening one costs the same
+/// whatever its size and only the pages that are actually read, like the
+/// window around the start bit, are loaded from disk.
+///
+/// Cloning is cheap and shares the same bytes.
+#[derive(Clone, Default)]
+pub struct FileData(Arc<Backing>);
+
+/// Bytes read between progress reports when a file has to be read whole.
+const READ_CHUNK: usize = 4 << 20;
+
+/// Biggest file read into memory rather than mapped. Another process
+/// truncating a mapped file makes reading it fault, so only files too big
+/// to copy quickly take that risk.
+const MAP_THRESHOLD: u64 = 256 << 20;
+
+#[derive(Default)]
+enum Backing {
+    #[default]
+    Empty,
+    Memory(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl FileData {
+    /// Maps the file at `path`. Empty files, which can't be mapped, are
+    /// empty data.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(Self::default());
+        }
+
+        // SAFETY: the map is only ever read. Another process truncating the
+        // file while it is mapped can still make reading it fault, which
+        // can't be ruled out without copying the whole file. `load` only
+        // maps files past `MAP_THRESHOLD` for that reason, and watched
+        // files, which are the ones expected to change, are never mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self(Arc::new(Backing::Mapped(map))))
+    }
+
+    /// Reads the file at `path` into memory, or maps it like `open` when it
+    /// is bigger than `MAP_THRESHOLD`. Files that can't be mapped, e.g.
+    /// ones whose size isn't known up front, are read whatever their size.
+    /// `progress` is told the bytes read so far and the total, if known, and
+    /// returns false to cancel, which fails with `ErrorKind::Interrupted`.
+    pub fn load(
+        path: &Path,
+        mut progress: impl FnMut(u64, Option<u64>) -> bool,
+    ) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        if len > MAP_THRESHOLD {
+            if let Ok(data) = Self::open(path) {
+                progress(len, Some(len));
+                return Ok(data);
+            }
+        }
+
+        Self::read(file, len, progress)
+    }
+
+    /// Reads the file at `path` into memory whatever its size, for data that
+    /// must not be mapped.
+    pub fn load_copy(
+        path: &Path,
+        progress: impl FnMut(u64, Option<u64>) -> bool,
+    ) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Self::read(file, len, progress)
+    }
+
+    /// Whether the bytes are a map of a file, which another process can
+    /// break by truncating it.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.0.as_ref(), Backing::Mapped(_))
+    }
+
+    /// Reads standard input into memory, for data piped in.
+    pub fn load_stdin(progress: impl FnMut(u64, Option<u64>) -> bool) -> io::Result<Self> {
+        Self::read(io::stdin().lock(), 0, progress)
+    }
+
+    fn read(
+        mut reader: impl Read,
+        len: u64,
+        mut progress: impl FnMut(u64, Option<u64>) -> bool,
+    ) -> io::Result<Self> {
+        let total = (len > 0).then_some(len);
+        let mut bytes = Vec::with_capacity(usize::try_from(len).unwrap_or(0));
+        loop {
+            let read = (&mut reader)
+                .take(READ_CHUNK as u64)
+                .read_to_end(&mut bytes)?;
+            if !progress(bytes.len() as u64, total) {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "loading was cancelled",
+                ));
+            }
+            if read == 0 {
+                break;
+            }
+        }
+
+        Ok(Self::from(bytes))
+    }
+}
+
+impl From<Vec<u8>> for FileData {
+    fn from(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            return Self::default();
+        }
+        Self(Arc::new(Backing::Memory(bytes)))
+    }
+}
+
+impl Deref for FileData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.0.as_ref() {
+            Backing::Empty => &[],
+            Backing::Memory(bytes) => bytes,
+            Backing::Mapped(map) => map,
+        }
+    }
+}
+
+impl std::fmt::Debug for FileData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileData")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_files() {
+        let dir = std::env::temp_dir().join(format!("binlens-file-data-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("data.bin");
+        std::fs::write(&path, [1, 2, 3]).unwrap();
+        let mapped = FileData::open(&path).unwrap();
+        assert_eq!(&*mapped, [1, 2, 3]);
+        assert!(mapped.is_mapped());
+
+        let empty = dir.join("empty.bin");
+        std::fs::write(&empty, []).unwrap();
+        assert!(FileData::open(&empty).unwrap().is_empty());
+
+        assert!(FileData::open(&dir.join("missing.bin")).is_err());
+
+        let mut reports = vec![];
+        let loaded = FileData::load(&path, |loaded, total| {
+            reports.push((loaded, total));
+            true
+        });
+        // Small files are read rather than mapped
+        assert!(matches!(
+            loaded.unwrap().0.as_ref(),
+            Backing::Memory(bytes) if bytes == &[1, 2, 3]
+        ));
+        assert_eq!(reports.last(), Some(&(3, Some(3))));
+
+        let copied = FileData::load_copy(&path, |_, _| true).unwrap();
+        assert_eq!(&*copied, [1, 2, 3]);
+        assert!(!copied.is_mapped());
+
+        let cancelled = FileData::load(&empty, |_, _| false);
+        assert_eq!(cancelled.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use iced::advanced::Hasher;
+use iced_futures::{futures, subscription::EventStream};
+
+use super::data_source::DataSource;
+use super::file_data::FileData;
+
+/// Loads a data source on a background thread, reporting progress as it
+/// goes. With `copy`, files are read rather than mapped, for sources that
+/// will be watched. Dropping the subscription cancels the load.
+pub struct FileLoader {
+    source: DataSource,
+    copy: bool,
+}
+
+impl FileLoader {
+    pub fn new(source: DataSource, copy: bool) -> Self {
+        Self { source, copy }
+    }
+}
+
+/// When a source was read, and for files their size and modification time
+/// just before, to tell when the data read is out of date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadStamp {
+    read_at: SystemTime,
+    file: Option<(u64, SystemTime)>,
+}
+
+impl ReadStamp {
+    /// Stamps `source` as it is now, before it is read.
+    pub fn take(source: &DataSource) -> Self {
+        Self {
+            read_at: SystemTime::now(),
+            file: match source {
+                DataSource::File(path) => file_stamp(path),
+                _ => None,
+            },
+        }
+    }
+
+    /// Whether `source` may have changed since it was stamped: files whose
+    /// size or modification time changed, and process memory once
+    /// `PROCESS_REFRESH_INTERVAL` has passed.
+    fn is_outdated(&self, source: &DataSource) -> bool {
+        match source {
+            DataSource::File(path) => file_stamp(path).is_some_and(|now| Some(now) != self.file),
+            _ => self
+                .read_at
+                .elapsed()
+                .map_or(true, |elapsed| elapsed >= PROCESS_REFRESH_INTERVAL),
+        }
+    }
+}
+
+/// The size and modification time of the file at `path`.
+fn file_stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    fs::metadata(path)
+        .and_then(|metadata| Ok((metadata.len(), metadata.modified()?)))
+        .ok()
+}
+
+#[derive(Debug, Clone)]
+pub enum LoadEvent {
+    /// Bytes loaded so far, out of the total if it is known
+    Progress {
+        loaded: u64,
+        total: Option<u64>,
+    },
+    Loaded(FileData, ReadStamp),
+    Failed(String),
+}
+
+impl iced_futures::subscription::Recipe for FileLoader {
+    type Output = LoadEvent;
+
+    fn hash(&self, state: &mut Hasher) {
+        use std::hash::Hash;
+        std::any::TypeId::of::<Self>().hash(state);
+        self.source.hash(state);
+    }
+
+    fn stream(
+        self: Box<Self>,
+        _input: EventStream,
+    ) -> futures::stream::BoxStream<'static, Self::Output> {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+
+        std::thread::spawn(move || {
+            // Sending fails once the subscription is dropped, which stops
+            // the load
+            let progress = |loaded, total| {
+                sender
+                    .unbounded_send(LoadEvent::Progress { loaded, total })
+                    .is_ok()
+            };
+            let stamp = ReadStamp::take(&self.source);
+            let result = if self.copy {
+                self.source.load_copy(progress)
+            } else {
+                self.source.load(progress)
+            };
+            let event = match result {
+                Ok(data) => LoadEvent::Loaded(data, stamp),
+                Err(why) => LoadEvent::Failed(why.to_string()),
+            };
+            let _ = sender.unbounded_send(event);
+        });
+
+        Box::pin(receiver)
+    }
+}
+
+/// How often a watched file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often process memory, which has no way to tell it changed, is read
+/// again.
+const PROCESS_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reloads a data source whenever it changes after it was read, as `stamp`
+/// tells. Files are reloaded when their size or modification time changes,
+/// and copied rather than mapped, so that a tool rewriting them can't break
+/// the data being shown. Data that was `mapped` is reloaded straight away
+/// for the same reason. Process memory is read every
+/// `PROCESS_REFRESH_INTERVAL`.
+pub struct FileWatcher {
+    source: DataSource,
+    stamp: ReadStamp,
+    mapped: bool,
+}
+
+impl FileWatcher {
+    pub fn new(source: DataSource, stamp: ReadStamp, mapped: bool) -> Self {
+        Self {
+            source,
+            stamp,
+            mapped,
+        }
+    }
+}
+
+impl iced_futures::subscription::Recipe for FileWatcher {
+    type Output = LoadEvent;
+
+    fn hash(&self, state: &mut Hasher) {
+        use std::hash::Hash;
+        std::any::TypeId::of::<Self>().hash(state);
+        self.source.hash(state);
+    }
+
+    fn stream(
+        self: Box<Self>,
+        _input: EventStream,
+    ) -> futures::stream::BoxStream<'static, Self::Output> {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+
+        std::thread::spawn(move || {
+            let mut stamp = self.stamp;
+            let mut reload = self.mapped;
+            // Sending fails once the subscription is dropped, which stops
+            // the watch
+            while !sender.is_closed() {
+                if reload || stamp.is_outdated(&self.source) {
+                    reload = false;
+                    stamp = ReadStamp::take(&self.source);
+                    let event = match self.source.load_copy(|_, _| !sender.is_closed()) {
+                        Ok(data) => LoadEvent::Loaded(data, stamp),
+                        Err(why) => LoadEvent::Failed(why.to_string()),
+                    };
+                    if sender.unbounded_send(event).is_err() {
+                        break;
+                    }
+                }
+
+                std::thread::sleep(WATCH_INTERVAL);
+            }
+        });
+
+        Box::pin(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamps_go_out_of_date() {
+        let dir = std::env::temp_dir().join(format!("binlens-file-loader-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        fs::write(&path, [1, 2, 3]).unwrap();
+        let source = DataSource::File(path.clone());
+
+        let stamp = ReadStamp::take(&source);
+        assert!(!stamp.is_outdated(&source));
+
+        // A rewrite within the same modification time still changes the size
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, [1, 2, 3, 4]).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert!(stamp.is_outdated(&source));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
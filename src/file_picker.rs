@@ -1,5 +1,15 @@
 #[derive(Default)]
-pub struct FilePicker {}
+pub struct FilePicker {
+    title: Option<&'static str>,
+}
+
+impl FilePicker {
+    /// A picker with its own dialog title, which can be open alongside
+    /// pickers with other titles.
+    pub fn with_title(title: &'static str) -> Self {
+        Self { title: Some(title) }
+    }
+}
 
 use std::path::PathBuf;
 
@@ -12,14 +22,20 @@ impl iced_futures::subscription::Recipe for FilePicker {
     fn hash(&self, state: &mut Hasher) {
         use std::hash::Hash;
         std::any::TypeId::of::<Self>().hash(state);
+        self.title.hash(state);
     }
 
     fn stream(
         self: Box<Self>,
         _input: EventStream,
     ) -> futures::stream::BoxStream<'static, Self::Output> {
+        let mut dialog = rfd::AsyncFileDialog::new();
+        if let Some(title) = self.title {
+            dialog = dialog.set_title(title);
+        }
+
         let f = futures::stream::once(
-            rfd::AsyncFileDialog::new()
+            dialog
                 .pick_file()
                 .map(|handle| handle.map(|h| h.path().to_owned())),
         );
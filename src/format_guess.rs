@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+use crate::byte_order::{ByteOrder, Endianness, UnitSize};
+use crate::decoder::Decoder;
+use crate::palette::Palette;
+use crate::pixel_mode::PixelMode;
+use crate::preview::Pixel;
+use crate::shader::DecodingScheme;
+use crate::width_detection::{self, WidthCandidate};
+
+/// A pixel format and width the data might have been written with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatGuess {
+    pub mode: PixelMode,
+    pub byte_order: ByteOrder,
+    pub width: u32,
+    /// How image-like the data looks decoded this way, from 0 to 1
+    pub score: f32,
+}
+
+/// Guesses returned at most.
+const GUESSES: usize = 5;
+
+/// Widths tried for each mode, besides the one keeping the current line
+/// length.
+const WIDTHS_PER_MODE: usize = 2;
+
+/// Pixels decoded to score a format and width.
+const MAX_PATCH_PIXELS: u32 = 8 << 10;
+
+/// Weights of the width confidence, cross-row smoothness, channel
+/// correlation and alpha plausibility in the score.
+const WEIGHTS: [f32; 4] = [0.35, 0.35, 0.15, 0.15];
+
+/// Finds the pixel formats and widths that make the data starting at
+/// `start_bit` look most like an image, best first.
+///
+/// Every uncompressed mode with its pixels side by side is tried in its
+/// byte order and, where its units are wider than a byte, little endian.
+/// Widths come from width detection on the mode's pixel size, plus the width
+/// that keeps lines `bits_per_line` long. Each is scored on a patch of pixels:
+/// images change little from one row to the next, their color channels tend
+/// to rise and fall together, and their alpha is mostly opaque or clear.
+pub fn guess_formats(
+    data: &[u8],
+    start_bit: u64,
+    bits_per_line: u64,
+    max_width: u32,
+) -> Vec<FormatGuess> {
+    let mut widths: HashMap<u32, Vec<WidthCandidate>> = HashMap::new();
+    let palette = Palette::grayscale(8);
+
+    let mut guesses: Vec<FormatGuess> = PixelMode::ALL
+        .iter()
+        .filter(|mode| is_guessable(mode.decoding_scheme()))
+        .flat_map(|mode| byte_orders(mode.decoding_scheme()).map(move |order| (mode, order)))
+        .filter_map(|(mode, byte_order)| {
+            let scheme = mode.decoding_scheme();
+            let bits_per_pixel = scheme.bits_per_pixel;
+            let detected = widths.entry(bits_per_pixel).or_insert_with(|| {
+                width_detection::detect_widths(data, start_bit, bits_per_pixel, max_width)
+            });
+
+            let mut candidates: Vec<WidthCandidate> =
+                detected.iter().take(WIDTHS_PER_MODE).copied().collect();
+            let line_width = bits_per_line / u64::from(bits_per_pixel);
+            if bits_per_line.is_multiple_of(u64::from(bits_per_pixel))
+                && (1..=u64::from(max_width)).contains(&line_width)
+                && !candidates.iter().any(|c| u64::from(c.width) == line_width)
+            {
+                let width = line_width as u32;
+                let confidence = detected
+                    .iter()
+                    .find(|c| c.width == width)
+                    .map_or(0.0, |c| c.confidence);
+                candidates.push(WidthCandidate { width, confidence });
+            }
+
+            let decoder = Decoder {
+                byte_order,
+                ..Decoder::new(scheme, &palette)
+            };
+
+            candidates
+                .iter()
+                .filter_map(|candidate| {
+                    let score = score(&decoder, data, start_bit, *candidate)?;
+                    Some(FormatGuess {
+                        mode: mode.clone(),
+                        byte_order,
+                        width: candidate.width,
+                        score,
+                    })
+                })
+                .max_by(|a, b| a.score.total_cmp(&b.score))
+        })
+        .collect();
+
+    // Stable, so modes listed first win ties, like rgb over bgr. Some modes
+    // are others with their bytes swapped, like G3B5R5G3 and rgb565, and
+    // decode to the same pixels; of those the one read without swapping is
+    // kept.
+    guesses.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.byte_order.is_swapped().cmp(&b.byte_order.is_swapped()))
+    });
+    guesses.dedup_by(|twin, kept| twin.score == kept.score && twin.width == kept.width);
+    guesses.truncate(GUESSES);
+    guesses
+}
+
+/// Whether the scheme's pixels can be scored on their own. Indexed modes
+/// read as gray, and 4:2:0 frames and compressed blocks aren't laid out in
+/// rows of pixels.
+fn is_guessable(scheme: &DecodingScheme) -> bool {
+    scheme.index.is_empty()
+        && scheme.block.is_none()
+        && scheme.yuv.is_none_or(|format| !format.is_420())
+}
+
+/// The byte orders worth trying for a scheme: its own, and little endian
+/// when its pixels, or the channels of wide pixels, span whole units of more
+/// than one byte. YUV formats already say where each byte goes.
+fn byte_orders(scheme: &DecodingScheme) -> impl Iterator<Item = ByteOrder> {
+    let unit_bits = if scheme.bits_per_pixel <= 32 {
+        scheme.bits_per_pixel
+    } else {
+        scheme.red.len() as u32
+    };
+    let unit_size = match unit_bits {
+        16 => Some(UnitSize::Bits16),
+        32 => Some(UnitSize::Bits32),
+        64 => Some(UnitSize::Bits64),
+        _ => None,
+    }
+    .filter(|_| scheme.yuv.is_none());
+
+    let little = unit_size.map(|unit_size| ByteOrder {
+        unit_size,
+        endianness: Endianness::Little,
+    });
+    std::iter::once(ByteOrder::default()).chain(little)
+}
+
+/// Scores the data decoded at a candidate width, or `None` when fewer than
+/// two rows fit.
+fn score(decoder: &Decoder, data: &[u8], start_bit: u64, candidate: WidthCandidate) -> Option<f32> {
+    let width = candidate.width;
+    let bits_per_line = u64::from(width) * u64::from(decoder.decoding_scheme.bits_per_pixel);
+    let available_bits = (data.len() as u64 * 8).checked_sub(start_bit)?;
+    let lines =
+        (available_bits / bits_per_line).min(u64::from((MAX_PATCH_PIXELS / width).max(2))) as u32;
+    if lines < 2 {
+        return None;
+    }
+
+    let pixels = decoder.decode_file_lines(data, start_bit, width, lines);
+    let scheme = decoder.decoding_scheme;
+    let is_gray = scheme.yuv.is_none() && scheme.red == scheme.green && scheme.green == scheme.blue;
+
+    let correlation = if is_gray {
+        // Says nothing either way
+        0.5
+    } else {
+        channel_correlation(&pixels)
+    };
+    let alpha = if scheme.has_alpha() {
+        alpha_plausibility(&pixels)
+    } else {
+        0.5
+    };
+
+    let [confidence_weight, smoothness_weight, correlation_weight, alpha_weight] = WEIGHTS;
+    Some(
+        confidence_weight * candidate.confidence.clamp(0.0, 1.0)
+            + smoothness_weight * smoothness(&pixels, width as usize)
+            + correlation_weight * correlation
+            + alpha_weight * alpha,
+    )
+}
+
+/// Sum of the differences between the color channels of two pixels.
+fn difference(a: Pixel, b: Pixel) -> u32 {
+    a.red.abs_diff(b.red) as u32 + a.green.abs_diff(b.green) as u32 + a.blue.abs_diff(b.blue) as u32
+}
+
+/// How much more alike pixels a row apart are than pixels half the patch
+/// apart, from 0 for no more alike to 1 for identical. A patch of a single
+/// color says nothing, and scores 0.
+fn smoothness(pixels: &[Pixel], width: usize) -> f32 {
+    let rows_apart: u64 = pixels
+        .iter()
+        .zip(&pixels[width..])
+        .map(|(a, b)| u64::from(difference(*a, *b)))
+        .sum();
+    let rows_apart = rows_apart as f32 / (pixels.len() - width) as f32;
+
+    let half = pixels.len() / 2;
+    let far_apart: u64 = pixels
+        .iter()
+        .zip(pixels[half..].iter().chain(&pixels[..half]))
+        .map(|(a, b)| u64::from(difference(*a, *b)))
+        .sum();
+    let far_apart = far_apart as f32 / pixels.len() as f32;
+
+    if far_apart > 0.0 {
+        (1.0 - rows_apart / far_apart).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Mean correlation of red with green and green with blue, with
+/// anticorrelation counting as none.
+fn channel_correlation(pixels: &[Pixel]) -> f32 {
+    let red: Vec<f32> = pixels.iter().map(|p| f32::from(p.red)).collect();
+    let green: Vec<f32> = pixels.iter().map(|p| f32::from(p.green)).collect();
+    let blue: Vec<f32> = pixels.iter().map(|p| f32::from(p.blue)).collect();
+
+    (correlation(&red, &green).max(0.0) + correlation(&green, &blue).max(0.0)) / 2.0
+}
+
+/// Pearson correlation of two equally long series, 0 when either is flat.
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let mean_a = a.iter().sum::<f32>() / n;
+    let mean_b = b.iter().sum::<f32>() / n;
+
+    let (covariance, variance_a, variance_b) = a.iter().zip(b).fold(
+        (0.0, 0.0, 0.0),
+        |(covariance, variance_a, variance_b), (x, y)| {
+            let (dx, dy) = (x - mean_a, y - mean_b);
+            (
+                covariance + dx * dy,
+                variance_a + dx * dx,
+                variance_b + dy * dy,
+            )
+        },
+    );
+
+    let spread = (variance_a * variance_b).sqrt();
+    if spread > 0.0 {
+        covariance / spread
+    } else {
+        0.0
+    }
+}
+
+/// Share of pixels that are fully opaque or fully clear. Real alpha
+/// channels are mostly one or the other; color data read as alpha isn't.
+fn alpha_plausibility(pixels: &[Pixel]) -> f32 {
+    let extremes = pixels
+        .iter()
+        .filter(|p| p.alpha == 0 || p.alpha == 255)
+        .count();
+    extremes as f32 / pixels.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{best, noise};
+
+    /// An opaque image whose channels follow each other, with detail along
+    /// the rows and slow changes down the columns.
+    fn image(width: u32, height: u32) -> Vec<Pixel> {
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let value = (noise(x) / 2).wrapping_add((y * 2) as u8);
+                Pixel {
+                    red: value,
+                    green: value / 4 * 3 + 20,
+                    blue: value / 2 + 60,
+                    alpha: 255,
+                }
+            })
+            .collect()
+    }
+
+    /// Writes pixels in a mode of unsigned channels, the reverse of decoding.
+    fn encode(mode: &PixelMode, pixels: &[Pixel]) -> Vec<u8> {
+        let scheme = mode.decoding_scheme();
+        let bits_per_pixel = scheme.bits_per_pixel as usize;
+        let mut data = vec![0u8; (pixels.len() * bits_per_pixel).div_ceil(8)];
+
+        for (i, pixel) in pixels.iter().enumerate() {
+            let channels = [
+                (&scheme.red, pixel.red),
+                (&scheme.green, pixel.green),
+                (&scheme.blue, pixel.blue),
+                (&scheme.alpha, pixel.alpha),
+            ];
+            for (bits, value) in channels {
+                let max = (1u64 << bits.len()) - 1;
+                let value = (u64::from(value) * max + 127) / 255;
+                for (n, bit) in bits.iter().enumerate() {
+                    if (value >> (bits.len() - 1 - n)) & 1 == 1 {
+                        let address = i * bits_per_pixel + *bit as usize;
+                        data[address / 8] |= 0x80 >> (address % 8);
+                    }
+                }
+            }
+        }
+        data
+    }
+
+    /// Wide enough for the test images, and quicker to search than the
+    /// whole range the app uses.
+    const MAX_WIDTH: u32 = 256;
+
+    #[test]
+    fn guesses_packed_formats() {
+        let pixels = image(100, 64);
+
+        let rgb565 = encode(&PixelMode::Rgb565, &pixels);
+        let guess = best(&guess_formats(&rgb565, 0, 0, MAX_WIDTH)).clone();
+        assert_eq!(guess.mode, PixelMode::Rgb565);
+        assert_eq!(guess.width, 100);
+        assert!(!guess.byte_order.is_swapped());
+
+        let rgba32 = encode(&PixelMode::Rgba32, &pixels);
+        let guesses = guess_formats(&rgba32, 0, 0, MAX_WIDTH);
+        assert_eq!(best(&guesses).mode, PixelMode::Rgba32);
+        assert_eq!(best(&guesses).width, 100);
+        assert!(guesses.len() <= GUESSES);
+    }
+
+    #[test]
+    fn guesses_little_endian() {
+        let pixels = image(90, 64);
+        let swap = |mut data: Vec<u8>| {
+            for unit in data.chunks_mut(2) {
+                unit.swap(0, 1);
+            }
+            data
+        };
+
+        let rgba4444 = swap(encode(&PixelMode::Rgba4444, &pixels));
+        let guess = best(&guess_formats(&rgba4444, 0, 0, MAX_WIDTH)).clone();
+        assert_eq!(guess.mode, PixelMode::Rgba4444);
+        assert_eq!(guess.width, 90);
+        assert_eq!(
+            guess.byte_order,
+            ByteOrder {
+                unit_size: UnitSize::Bits16,
+                endianness: Endianness::Little,
+            }
+        );
+
+        // Swapped rgb565 is G3B5R5G3, which is offered instead of its twin
+        let rgb565 = swap(encode(&PixelMode::Rgb565, &pixels));
+        let guesses = guess_formats(&rgb565, 0, 0, MAX_WIDTH);
+        assert_eq!(best(&guesses).mode, PixelMode::G3b5r5g3);
+        assert!(!best(&guesses).byte_order.is_swapped());
+        assert!(!guesses
+            .iter()
+            .any(|guess| guess.mode == PixelMode::Rgb565 && guess.width == 90));
+    }
+
+    #[test]
+    fn keeps_the_current_line_length() {
+        // Noise gives width detection nothing to go on
+        let data: Vec<u8> = (0..96 * 64).map(noise).collect();
+        let guesses = guess_formats(&data, 0, 96 * 8, MAX_WIDTH);
+        assert!(!guesses.is_empty());
+        assert!(guesses
+            .iter()
+            .all(|guess| guess.width * guess.mode.decoding_scheme().bits_per_pixel == 96 * 8));
+    }
+
+    #[test]
+    fn scores_nothing_too_short() {
+        assert!(guess_formats(&[1, 2, 3], 24, 24, MAX_WIDTH).is_empty());
+    }
+}
@@ -1,9 +1,6 @@
-use std::{
-    fs,
-    path::{Path, PathBuf},
-    sync::Arc,
-};
+use std::path::{Path, PathBuf};
 
+use clap::Parser;
 use iced::{
     mouse::ScrollDelta,
     widget::{checkbox, row, slider},
@@ -15,41 +12,244 @@ use preview::Preview;
 mod pixel_mode;
 use pixel_mode::PixelMode;
 
+mod custom_format;
+use custom_format::CustomFormat;
+
+mod alpha_mode;
+use alpha_mode::AlphaMode;
+
+mod channel_scaling;
+use channel_scaling::ChannelScaling;
+
+mod byte_order;
+use byte_order::{ByteOrder, Endianness, UnitSize};
+
+mod pixel_order;
+use pixel_order::PixelOrder;
+
+mod planar;
+use planar::{PlanarLayout, PlaneInterleave};
+
+mod palette;
+use palette::{BuiltinPalette, PaletteSource};
+
+mod astc;
+mod bc7;
+mod block_format;
+mod etc;
+
+mod tiling;
+use tiling::{TileOrder, Tiling};
+
+mod row_stride;
+use row_stride::{RowStride, StrideUnit};
+
+mod yuv;
+use yuv::{YuvMatrix, YuvRange, YuvSettings};
+
+mod sample;
+use sample::{ToneMapping, ToneSettings};
+
+mod file_data;
+use file_data::FileData;
+
+mod data_source;
+use data_source::DataSource;
+
+mod process_memory;
+use process_memory::MemoryRegion;
+
+mod width_detection;
+use width_detection::WidthCandidate;
+
+mod format_guess;
+use format_guess::FormatGuess;
+
+mod selection;
+use selection::{Selection, SelectionExport};
+
+mod file_loader;
+use file_loader::{FileLoader, FileWatcher, LoadEvent, ReadStamp};
+
 mod file_picker;
 use file_picker::FilePicker;
 
 mod shader;
 
+mod decoder;
+use decoder::PixelInspection;
+
+mod export;
+
+mod cli;
+use cli::{Cli, Command, OpenArgs};
+
+#[cfg(test)]
+mod test_util;
+
+/// Widest image the width slider and width detection go up to.
+const MAX_IMAGE_WIDTH: u32 = 2048;
+
 struct FileInfo {
-    data: Arc<Vec<u8>>,
-    path: PathBuf,
+    data: FileData,
+    source: DataSource,
+    /// When the data was read, so that only later changes reload it
+    stamp: ReadStamp,
+}
+
+/// A data source being loaded in the background.
+struct Loading {
+    source: DataSource,
+    loaded: u64,
+    total: Option<u64>,
 }
 struct ImageViewApp {
     pixel_mode: PixelMode,
+    custom_formats: Vec<CustomFormat>,
+    custom_format_str: String,
+    custom_format_error: Option<String>,
+    planes_str: String,
+    plane_order_str: String,
+    plane_size_str: String,
+    plane_interleave: PlaneInterleave,
+    planar_error: Option<String>,
+    palette_source: PaletteSource,
+    palette_offset_str: String,
+    palette_pixel_mode: PixelMode,
+    palette_error: Option<String>,
+    picking_palette: bool,
+    frame_height_str: String,
+    tile_order: TileOrder,
+    tile_width_str: String,
+    tile_height_str: String,
+    tiling_error: Option<String>,
+    /// Whether rows follow straight on, a row stride being the width
+    stride_locked: bool,
+    row_stride_str: String,
+    stride_unit: StrideUnit,
+    row_stride_error: Option<String>,
+    tone_min_str: String,
+    tone_max_str: String,
+    tone_error: Option<String>,
     file: Option<FileInfo>,
+    loading: Option<Loading>,
+    file_error: Option<String>,
+    /// Whether the data is reloaded when it changes
+    watching: bool,
+    pid_str: String,
+    /// Readable memory regions of the process being attached to
+    regions: Vec<MemoryRegion>,
     picking_file: bool,
     preview: Preview,
     image_width_str: String,
+    width_candidates: Vec<WidthCandidate>,
+    width_detection_error: Option<String>,
+    format_guesses: Vec<FormatGuess>,
+    /// Whether formats are being guessed in the background
+    guessing_formats: bool,
+    format_guess_error: Option<String>,
     scale_str: String,
     bit_offset_str: String,
+    /// Pixel of the view under the cursor
+    hovered_pixel: Option<(u32, u32)>,
+    /// Pixel of the view last clicked
+    selected_pixel: Option<(u32, u32)>,
+    /// The hovered pixel, or else the selected one, and where it is read
+    /// from and what it decodes to. Worked out as messages come in rather
+    /// than on every redraw.
+    inspection: Option<((u32, u32), PixelInspection)>,
+    show_hex: bool,
+    /// Kept open once used, as on some platforms what's copied only stays
+    /// on the clipboard while it's open
+    clipboard: Option<arboard::Clipboard>,
+    selection_error: Option<String>,
 }
 #[derive(Debug, Clone)]
 enum AppMessage {
     PixelModeSelected(PixelMode),
+    CustomFormatStrChanged(String),
+    AddCustomFormat,
+    RemoveCustomFormat,
+    GuessFormats,
+    FormatsGuessed(Vec<FormatGuess>),
+    FormatGuessSelected(FormatGuess),
+    AlphaModeSelected(AlphaMode),
+    ChannelScalingSelected(ChannelScaling),
+    PixelOrderSelected(PixelOrder),
+    PlanesStrChanged(String),
+    PlaneOrderStrChanged(String),
+    PlaneSizeStrChanged(String),
+    PlaneInterleaveSelected(PlaneInterleave),
+    UnitSizeSelected(UnitSize),
+    EndiannessSelected(Endianness),
+    BuiltinPaletteSelected(BuiltinPalette),
+    LoadPaletteDialog,
+    PalettePickResult(Option<PathBuf>),
+    PaletteOffsetStrChanged(String),
+    PalettePixelModeSelected(PixelMode),
+    PaletteFromData,
+    YuvMatrixSelected(YuvMatrix),
+    YuvRangeSelected(YuvRange),
+    FrameHeightStrChanged(String),
+    TileOrderSelected(TileOrder),
+    TileWidthStrChanged(String),
+    TileHeightStrChanged(String),
+    ToggleStrideLock(bool),
+    RowStrideStrChanged(String),
+    StrideUnitSelected(StrideUnit),
+    ToneMappingSelected(ToneMapping),
+    ExposureChanged(f32),
+    ToneMinStrChanged(String),
+    ToneMaxStrChanged(String),
+    AutoToneRange,
     ImageWidthSelected(u32),
     OpenFileDialog,
+    FileLoad(LoadEvent),
+    CancelLoad,
+    DismissFileError,
+    ToggleWatch(bool),
+    FileReloaded(LoadEvent),
+    PidStrChanged(String),
+    ListRegions,
+    RegionSelected(MemoryRegion),
+    /// Reads the window of the memory region being shown from this offset
+    RegionWindowMoved(u64),
     ImageScrollVertical(u32),
     ImageScrollHorizontal(u32),
     ImageScale(u32),
     ScrollWheel(ScrollDelta),
+    /// Scrolls by image pixels across and lines down
+    ScrollView {
+        columns: i64,
+        lines: i64,
+    },
+    /// Zooms in by `steps`, or out when negative, around a point of the view
+    ZoomAt {
+        position: iced::Point,
+        steps: i32,
+    },
     BitOffset(u32),
-    WindowResize { width: u32, height: u32 },
+    WindowResize {
+        width: u32,
+        height: u32,
+    },
     ToggleGrid(bool),
+    ToggleHexView(bool),
+    PixelHovered(Option<(u32, u32)>),
+    PixelSelected((u32, u32)),
+    HexByteSelected(u64),
+    SelectionDragged((u32, u32), (u32, u32)),
+    ClearSelection,
+    CopySelection,
+    SaveSelection(SelectionExport),
+    SelectionSavePathPicked(SelectionExport, Option<PathBuf>),
     FilePickResult(Option<PathBuf>),
+    FileDropped(PathBuf),
     ImageWidthStrChanged(String),
     ScaleStrChanged(String),
     BitOffsetStrChanged(String),
     IncrementImageWidth,
+    DetectWidth,
     DecrementImageWidth,
     IncrementScale,
     DecrementScale,
@@ -58,6 +258,246 @@ enum AppMessage {
 }
 
 impl ImageViewApp {
+    pub fn select_pixel_mode(&mut self, pixel_mode: PixelMode) {
+        self.pixel_mode = pixel_mode;
+        self.preview
+            .set_decoding_scheme(self.pixel_mode.decoding_scheme());
+        self.update_planar_layout();
+        self.update_palette();
+    }
+
+    /// Applies the planar settings if they are valid for the pixel mode,
+    /// otherwise falls back to chunky pixels and shows why.
+    fn update_planar_layout(&mut self) {
+        let layout = || -> Result<PlanarLayout, String> {
+            let planes = match self.planes_str.trim() {
+                "" => 1,
+                planes => planes
+                    .parse()
+                    .map_err(|_| format!("Invalid plane count {planes:?}"))?,
+            };
+            let plane_size = match self.plane_size_str.trim() {
+                "" => 0,
+                size => size
+                    .parse()
+                    .map_err(|_| format!("Invalid plane size {size:?}"))?,
+            };
+            let plane_order =
+                planar::parse_plane_order(&self.plane_order_str).map_err(|why| why.to_string())?;
+
+            let layout = PlanarLayout::new(planes, &plane_order, self.plane_interleave, plane_size)
+                .map_err(|why| why.to_string())?;
+            layout
+                .check(self.pixel_mode.decoding_scheme())
+                .map_err(|why| why.to_string())?;
+            Ok(layout)
+        };
+
+        match layout() {
+            Ok(layout) => {
+                self.planar_error = None;
+                self.preview.set_planar_layout(layout);
+            }
+            Err(why) => {
+                self.planar_error = Some(why);
+                self.preview.set_planar_layout(PlanarLayout::default());
+            }
+        }
+    }
+
+    /// Applies the tiling settings if they are valid, otherwise falls back to
+    /// linear lines and shows why.
+    fn update_tiling(&mut self) {
+        let tiling = || -> Result<Tiling, String> {
+            let tile_width = self
+                .tile_width_str
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid tile width {:?}", self.tile_width_str))?;
+            let tile_height = self
+                .tile_height_str
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid tile height {:?}", self.tile_height_str))?;
+            Tiling::new(self.tile_order, tile_width, tile_height).map_err(|why| why.to_string())
+        };
+
+        match tiling() {
+            Ok(tiling) => {
+                self.tiling_error = None;
+                self.preview.set_tiling(tiling);
+            }
+            Err(why) => {
+                self.tiling_error = Some(why);
+                self.preview.set_tiling(Tiling::default());
+            }
+        }
+    }
+
+    /// Applies the row stride if it is valid, otherwise falls back to rows
+    /// that follow straight on and shows why.
+    fn update_row_stride(&mut self) {
+        if self.stride_locked {
+            self.row_stride_error = None;
+            self.preview.set_row_stride(RowStride::UNPADDED);
+            return;
+        }
+
+        let row_stride = || -> Result<RowStride, String> {
+            let value = self
+                .row_stride_str
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid row stride {:?}", self.row_stride_str))?;
+            let bits = self.stride_unit.bits(value).ok_or_else(|| {
+                format!("A row stride of {value} {} is too large", self.stride_unit)
+            })?;
+            Ok(RowStride::new(bits))
+        };
+
+        match row_stride() {
+            Ok(row_stride) => {
+                self.row_stride_error = None;
+                self.preview.set_row_stride(row_stride);
+            }
+            Err(why) => {
+                self.row_stride_error = Some(why);
+                self.preview.set_row_stride(RowStride::UNPADDED);
+            }
+        }
+    }
+
+    /// Inspects the hovered pixel, or else the selected one, for the pixel
+    /// inspector and hex view to show.
+    fn update_inspection(&mut self) {
+        self.inspection = self
+            .hovered_pixel
+            .or(self.selected_pixel)
+            .map(|(x, y)| ((x, y), self.preview.inspect_pixel(x, y)));
+    }
+
+    /// Copies the selected pixels to the clipboard as an image.
+    fn copy_selection(&mut self, selection: &Selection) -> Result<(), Box<dyn std::error::Error>> {
+        let (width, height, pixels) = self.preview.selected_pixels(selection)?;
+        let image = arboard::ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: export::rgba_bytes(&pixels).into(),
+        };
+
+        let clipboard = match self.clipboard.take() {
+            Some(clipboard) => clipboard,
+            None => arboard::Clipboard::new()?,
+        };
+        self.clipboard.insert(clipboard).set_image(image)?;
+        Ok(())
+    }
+
+    /// Saves the selected pixels as a PNG, or the bytes they are read from.
+    fn save_selection(
+        &self,
+        selection: &Selection,
+        format: SelectionExport,
+        path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match format {
+            SelectionExport::Png => {
+                let (width, height, pixels) = self.preview.selected_pixels(selection)?;
+                export::write_png(path, width, height, &pixels)
+            }
+            SelectionExport::Bytes => {
+                let bytes = self.preview.selected_bytes(selection)?;
+                std::fs::write(path, bytes)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Applies the normalizing range if it is valid, otherwise falls back to
+    /// the default range and shows why.
+    fn update_tone_range(&mut self) {
+        let range = || -> Result<(f32, f32), String> {
+            let min: f32 = self
+                .tone_min_str
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid minimum {:?}", self.tone_min_str))?;
+            let max: f32 = self
+                .tone_max_str
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid maximum {:?}", self.tone_max_str))?;
+            if min >= max || min.is_nan() || max.is_nan() {
+                return Err(format!("The minimum {min} must be below the maximum {max}"));
+            }
+            Ok((min, max))
+        };
+
+        let (min, max) = match range() {
+            Ok(range) => {
+                self.tone_error = None;
+                range
+            }
+            Err(why) => {
+                self.tone_error = Some(why);
+                let default = ToneSettings::default();
+                (default.min, default.max)
+            }
+        };
+        self.preview.set_tone(ToneSettings {
+            min,
+            max,
+            ..self.preview.tone()
+        });
+    }
+
+    fn set_byte_order(&mut self, byte_order: ByteOrder) {
+        self.preview.set_byte_order(byte_order);
+        self.update_palette();
+    }
+
+    fn select_palette_source(&mut self, palette_source: PaletteSource) {
+        self.palette_source = palette_source;
+        self.update_palette();
+    }
+
+    /// Rebuilds the palette from its source, which may depend on the index
+    /// depth of the pixel mode and on the file's contents.
+    fn update_palette(&mut self) {
+        let decoding_scheme = self.pixel_mode.decoding_scheme();
+        if !decoding_scheme.is_indexed() {
+            return;
+        }
+
+        match self.palette_source.resolve(
+            self.preview.file_data(),
+            decoding_scheme.index_depth(),
+            self.preview.channel_scaling(),
+            self.preview.byte_order(),
+        ) {
+            Ok(palette) => {
+                self.palette_error = None;
+                self.preview.set_palette(palette);
+            }
+            Err(why) => {
+                self.palette_error = Some(format!("Could not load palette: {why}"));
+            }
+        }
+    }
+
+    pub fn pixel_modes(&self) -> Vec<PixelMode> {
+        PixelMode::ALL
+            .iter()
+            .cloned()
+            .chain(self.custom_formats.iter().cloned().map(PixelMode::Custom))
+            .collect()
+    }
+
+    fn save_custom_formats(&mut self) {
+        if let Err(why) = custom_format::save_custom_formats(&self.custom_formats) {
+            self.custom_format_error = Some(format!("Could not save custom formats: {why}"));
+        }
+    }
     pub fn update_pixel_decoding(&mut self) {
         match &self.file {
             Some(file) => {
@@ -69,19 +509,89 @@ impl ImageViewApp {
         }
     }
 
+    /// Swaps in the latest contents of a watched file, keeping the view where
+    /// it is. A file that can't be read keeps showing what was last read.
+    fn file_reloaded(&mut self, event: LoadEvent) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+
+        match event {
+            LoadEvent::Progress { .. } => {}
+            LoadEvent::Loaded(data, stamp) => {
+                file.data = data;
+                file.stamp = stamp;
+                self.file_error = None;
+                self.update_pixel_decoding();
+                self.update_palette();
+            }
+            LoadEvent::Failed(why) => {
+                self.file_error = Some(format!("Could not reload {} : {why}", file.source));
+            }
+        }
+    }
+
+    /// Lists the memory regions of the process in `pid_str` to pick one to
+    /// view.
+    fn list_regions(&mut self) {
+        let regions = match self.pid_str.trim().parse::<u32>() {
+            Ok(pid) => process_memory::regions(pid)
+                .map_err(|why| format!("Could not read the memory map of process {pid} : {why}")),
+            Err(_) => Err(format!("Invalid process ID {:?}", self.pid_str)),
+        };
+
+        match regions {
+            Ok(regions) => {
+                self.file_error = None;
+                self.regions = regions;
+            }
+            Err(why) => {
+                self.file_error = Some(why);
+                self.regions.clear();
+            }
+        }
+    }
+
     pub fn open_file(&mut self, path: &Path) {
-        match fs::read(path) {
-            Ok(data) => {
+        self.open_source(DataSource::File(path.to_owned()));
+    }
+
+    /// Starts loading `source` in the background. The data being shown stays
+    /// until it has loaded.
+    pub fn open_source(&mut self, source: DataSource) {
+        self.file_error = None;
+        self.loading = Some(Loading {
+            source,
+            loaded: 0,
+            total: None,
+        });
+    }
+
+    fn file_loaded(&mut self, event: LoadEvent) {
+        let Some(loading) = &mut self.loading else {
+            return;
+        };
+
+        match event {
+            LoadEvent::Progress { loaded, total } => {
+                loading.loaded = loaded;
+                loading.total = total;
+            }
+            LoadEvent::Loaded(data, stamp) => {
                 self.file = Some(FileInfo {
-                    data: Arc::new(data),
-                    path: path.to_owned(),
+                    data,
+                    source: loading.source.clone(),
+                    stamp,
                 });
+                self.loading = None;
                 self.update_pixel_decoding();
+                self.update_palette();
             }
-            Err(why) => {
-                eprintln!("Could not open file {path:#?} : {why}");
+            LoadEvent::Failed(why) => {
+                self.file_error = Some(format!("Could not open {} : {why}", loading.source));
+                self.loading = None;
             }
-        };
+        }
     }
 }
 
@@ -89,44 +599,290 @@ impl iced::Application for ImageViewApp {
     type Executor = iced::executor::Default;
     type Message = AppMessage;
     type Theme = iced::Theme;
-    type Flags = ();
+    type Flags = OpenArgs;
 
-    fn new(_flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
+    fn new(flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
         let preview = Preview::default();
         let image_width_str = preview.target_width().to_string();
-        let scale_str = preview.scale().to_string();
+        let scale_str = format_scale(&preview);
         let bit_offset_str = preview.start_bit().to_string();
-        (
-            Self {
-                pixel_mode: PixelMode::Rgb,
-                file: None,
-                preview,
-                picking_file: false,
-                image_width_str,
-                scale_str,
-                bit_offset_str,
-            },
-            iced::Command::none(),
-        )
+        let mut app = Self {
+            pixel_mode: PixelMode::Rgb,
+            custom_formats: custom_format::load_custom_formats(),
+            custom_format_str: String::new(),
+            custom_format_error: None,
+            planes_str: "1".to_owned(),
+            plane_order_str: String::new(),
+            plane_size_str: String::new(),
+            plane_interleave: PlaneInterleave::default(),
+            planar_error: None,
+            palette_source: PaletteSource::default(),
+            palette_offset_str: "0".to_owned(),
+            palette_pixel_mode: PixelMode::Rgb,
+            palette_error: None,
+            picking_palette: false,
+            frame_height_str: String::new(),
+            tile_order: TileOrder::default(),
+            tile_width_str: "8".to_owned(),
+            tile_height_str: "8".to_owned(),
+            tiling_error: None,
+            stride_locked: true,
+            row_stride_str: String::new(),
+            stride_unit: StrideUnit::default(),
+            row_stride_error: None,
+            tone_min_str: ToneSettings::default().min.to_string(),
+            tone_max_str: ToneSettings::default().max.to_string(),
+            tone_error: None,
+            file: None,
+            loading: None,
+            file_error: None,
+            watching: false,
+            pid_str: String::new(),
+            regions: vec![],
+            preview,
+            picking_file: false,
+            image_width_str,
+            width_candidates: vec![],
+            width_detection_error: None,
+            format_guesses: vec![],
+            guessing_formats: false,
+            format_guess_error: None,
+            scale_str,
+            bit_offset_str,
+            hovered_pixel: None,
+            selected_pixel: None,
+            inspection: None,
+            show_hex: true,
+            clipboard: None,
+            selection_error: None,
+        };
+
+        if let Some(pixel_mode) = flags.mode {
+            app.select_pixel_mode(pixel_mode);
+        }
+        if let Some(width) = flags.width {
+            app.preview.set_target_width(width);
+            app.image_width_str = width.to_string();
+        }
+        if let Some(start_bit) = flags.start_bit {
+            app.preview.set_start_bit(start_bit);
+            app.bit_offset_str = start_bit.to_string();
+        }
+        if let Some(path) = flags.path {
+            app.open_source(DataSource::from_path(path));
+        }
+        if let Some(pid) = flags.pid {
+            app.pid_str = pid.to_string();
+            app.list_regions();
+        }
+        app.watching = flags.watch;
+
+        (app, iced::Command::none())
     }
 
     fn title(&self) -> String {
         match &self.file {
-            Some(file) => format!("BinLens - {}", file.path.to_string_lossy()),
+            Some(file) => format!("BinLens - {}", file.source),
             None => "BinLens".to_owned(),
         }
     }
 
     fn update(&mut self, message: Self::Message) -> iced::Command<Self::Message> {
-        // dbg!("Got message {:?}", &message);
-
         match message {
             AppMessage::PixelModeSelected(pixel_mode) => {
-                self.pixel_mode = pixel_mode;
                 // self.update_pixel_decoding();
-                self.preview
-                    .set_decoding_scheme(self.pixel_mode.decoding_scheme());
+                self.select_pixel_mode(pixel_mode);
+            }
+            AppMessage::CustomFormatStrChanged(s) => {
+                self.custom_format_error = match s.trim().is_empty() {
+                    true => None,
+                    false => s.parse::<CustomFormat>().err().map(|why| why.to_string()),
+                };
+                self.custom_format_str = s;
+            }
+            AppMessage::AddCustomFormat => match self.custom_format_str.parse::<CustomFormat>() {
+                Ok(custom_format) => {
+                    self.custom_format_error = None;
+                    if !self.custom_formats.contains(&custom_format) {
+                        self.custom_formats.push(custom_format.clone());
+                        self.save_custom_formats();
+                    }
+                    self.select_pixel_mode(PixelMode::Custom(custom_format));
+                }
+                Err(why) => {
+                    self.custom_format_error = Some(why.to_string());
+                }
+            },
+            AppMessage::RemoveCustomFormat => {
+                if let PixelMode::Custom(custom_format) = &self.pixel_mode {
+                    self.custom_formats.retain(|f| f != custom_format);
+                    self.save_custom_formats();
+                    self.select_pixel_mode(PixelMode::Rgb);
+                }
+            }
+            AppMessage::AlphaModeSelected(alpha_mode) => {
+                self.preview.set_alpha_mode(alpha_mode);
+            }
+            AppMessage::ChannelScalingSelected(channel_scaling) => {
+                self.preview.set_channel_scaling(channel_scaling);
+                self.update_palette();
+            }
+            AppMessage::PlanesStrChanged(s) => {
+                self.planes_str = s;
+                self.update_planar_layout();
+            }
+            AppMessage::PlaneOrderStrChanged(s) => {
+                self.plane_order_str = s;
+                self.update_planar_layout();
+            }
+            AppMessage::PlaneSizeStrChanged(s) => {
+                self.plane_size_str = s;
+                self.update_planar_layout();
+            }
+            AppMessage::PlaneInterleaveSelected(plane_interleave) => {
+                self.plane_interleave = plane_interleave;
+                self.update_planar_layout();
+            }
+            AppMessage::UnitSizeSelected(unit_size) => {
+                self.set_byte_order(ByteOrder {
+                    unit_size,
+                    ..self.preview.byte_order()
+                });
+            }
+            AppMessage::EndiannessSelected(endianness) => {
+                self.set_byte_order(ByteOrder {
+                    endianness,
+                    ..self.preview.byte_order()
+                });
+            }
+            AppMessage::PixelOrderSelected(pixel_order) => {
+                self.preview.set_pixel_order(pixel_order);
+            }
+            AppMessage::BuiltinPaletteSelected(builtin) => {
+                self.select_palette_source(PaletteSource::Builtin(builtin));
+            }
+            AppMessage::LoadPaletteDialog => {
+                self.picking_palette = true;
+            }
+            AppMessage::PalettePickResult(path) => {
+                self.picking_palette = false;
+                if let Some(path) = path {
+                    self.select_palette_source(PaletteSource::File(path));
+                }
+            }
+            AppMessage::PaletteOffsetStrChanged(s) => {
+                self.palette_offset_str = s;
+            }
+            AppMessage::PalettePixelModeSelected(pixel_mode) => {
+                self.palette_pixel_mode = pixel_mode;
+            }
+            AppMessage::PaletteFromData => match self.palette_offset_str.parse() {
+                Ok(start_bit) => {
+                    self.select_palette_source(PaletteSource::Data {
+                        start_bit,
+                        pixel_mode: self.palette_pixel_mode.clone(),
+                    });
+                }
+                Err(_) => {
+                    self.palette_error = Some(format!(
+                        "Invalid palette start bit {:?}",
+                        self.palette_offset_str
+                    ));
+                }
+            },
+            AppMessage::YuvMatrixSelected(matrix) => {
+                self.preview.set_yuv(YuvSettings {
+                    matrix,
+                    ..self.preview.yuv()
+                });
+            }
+            AppMessage::YuvRangeSelected(range) => {
+                self.preview.set_yuv(YuvSettings {
+                    range,
+                    ..self.preview.yuv()
+                });
+            }
+            AppMessage::FrameHeightStrChanged(s) => {
+                let frame_height = match s.trim() {
+                    "" => Some(0),
+                    frame_height => frame_height.parse().ok(),
+                };
+                if let Some(frame_height) = frame_height {
+                    self.preview.set_yuv(YuvSettings {
+                        frame_height,
+                        ..self.preview.yuv()
+                    });
+                }
+                self.frame_height_str = s;
+            }
+            AppMessage::TileOrderSelected(tile_order) => {
+                self.tile_order = tile_order;
+                self.update_tiling();
+            }
+            AppMessage::TileWidthStrChanged(s) => {
+                self.tile_width_str = s;
+                self.update_tiling();
+            }
+            AppMessage::TileHeightStrChanged(s) => {
+                self.tile_height_str = s;
+                self.update_tiling();
             }
+            AppMessage::ToggleStrideLock(locked) => {
+                if !locked {
+                    // Start from the line length as it is
+                    let bits_per_line = self.preview.bits_per_line();
+                    self.stride_unit = if bits_per_line.is_multiple_of(8) {
+                        StrideUnit::Bytes
+                    } else {
+                        StrideUnit::Bits
+                    };
+                    let bits_per_unit = match self.stride_unit {
+                        StrideUnit::Bytes => 8,
+                        StrideUnit::Bits => 1,
+                    };
+                    self.row_stride_str = (bits_per_line / bits_per_unit).to_string();
+                }
+                self.stride_locked = locked;
+                self.update_row_stride();
+            }
+            AppMessage::RowStrideStrChanged(s) => {
+                self.row_stride_str = s;
+                self.update_row_stride();
+            }
+            AppMessage::StrideUnitSelected(stride_unit) => {
+                self.stride_unit = stride_unit;
+                self.update_row_stride();
+            }
+            AppMessage::ToneMappingSelected(tone_mapping) => {
+                self.preview.set_tone(ToneSettings {
+                    tone_mapping,
+                    ..self.preview.tone()
+                });
+            }
+            AppMessage::ExposureChanged(exposure) => {
+                self.preview.set_tone(ToneSettings {
+                    exposure,
+                    ..self.preview.tone()
+                });
+            }
+            AppMessage::ToneMinStrChanged(s) => {
+                self.tone_min_str = s;
+                self.update_tone_range();
+            }
+            AppMessage::ToneMaxStrChanged(s) => {
+                self.tone_max_str = s;
+                self.update_tone_range();
+            }
+            AppMessage::AutoToneRange => match self.preview.value_range() {
+                Some((min, max)) => {
+                    self.tone_min_str = min.to_string();
+                    self.tone_max_str = max.to_string();
+                    self.update_tone_range();
+                }
+                None => {
+                    self.tone_error = Some("There are no channel values to normalize".to_owned());
+                }
+            },
             AppMessage::ImageWidthSelected(image_width) => {
                 self.preview.set_target_width(image_width);
                 self.image_width_str = image_width.to_string();
@@ -134,6 +890,47 @@ impl iced::Application for ImageViewApp {
             AppMessage::OpenFileDialog => {
                 self.picking_file = true;
             }
+            AppMessage::FileLoad(event) => {
+                self.file_loaded(event);
+            }
+            AppMessage::CancelLoad => {
+                self.loading = None;
+            }
+            AppMessage::DismissFileError => {
+                self.file_error = None;
+            }
+            AppMessage::ToggleWatch(watching) => {
+                self.watching = watching;
+            }
+            AppMessage::FileReloaded(event) => {
+                self.file_reloaded(event);
+            }
+            AppMessage::PidStrChanged(s) => {
+                self.pid_str = s;
+            }
+            AppMessage::ListRegions => {
+                self.list_regions();
+            }
+            AppMessage::RegionSelected(region) => {
+                if let Ok(pid) = self.pid_str.trim().parse() {
+                    self.open_source(DataSource::Process {
+                        pid,
+                        region,
+                        offset: 0,
+                    });
+                }
+            }
+            AppMessage::RegionWindowMoved(offset) => {
+                let source = self.file.as_ref().map(|file| &file.source);
+                if let Some(DataSource::Process { pid, region, .. }) = source {
+                    let (pid, region) = (*pid, region.clone());
+                    self.open_source(DataSource::Process {
+                        pid,
+                        region,
+                        offset,
+                    });
+                }
+            }
             AppMessage::ImageScrollVertical(scroll) => {
                 let scroll = u32::MAX - scroll;
                 let ratio = f64::from(u32::MAX) / self.preview.total_lines() as f64;
@@ -153,35 +950,89 @@ impl iced::Application for ImageViewApp {
                 self.bit_offset_str = offset.to_string();
             }
             AppMessage::ScrollWheel(delta) => {
-                let lines_scrolled = match delta {
-                    iced::mouse::ScrollDelta::Lines { x: _, y } => y * 5.0,
-                    iced::mouse::ScrollDelta::Pixels { x: _, y } => {
-                        //(y + (self.preview.scale() - 1) as f32) / self.preview.scale() as f32
-                        y * 5.0
-                    }
-                }
-                .round() as i64;
-
-                let forward = lines_scrolled.is_negative();
-                let amount = lines_scrolled.unsigned_abs();
-
-                let go_to_line = if forward {
-                    self.preview.current_line().saturating_add(amount)
-                } else {
-                    self.preview.current_line().saturating_sub(amount)
+                let (x, y) = match delta {
+                    iced::mouse::ScrollDelta::Lines { x, y } => (x, y),
+                    iced::mouse::ScrollDelta::Pixels { x, y } => (x, y),
                 };
-
-                self.preview.go_to_line(go_to_line);
+                let columns = (-x * shader::WHEEL_STEP).round() as i64;
+                let lines = (-y * shader::WHEEL_STEP).round() as i64;
+                self.preview.scroll(columns, lines);
+            }
+            AppMessage::ScrollView { columns, lines } => {
+                self.preview.scroll(columns, lines);
+            }
+            AppMessage::ZoomAt { position, steps } => {
+                self.preview.zoom_at(position, steps);
+                self.scale_str = format_scale(&self.preview);
             }
             AppMessage::ToggleGrid(grid) => {
                 self.preview.set_grid(grid);
             }
+            AppMessage::ToggleHexView(show_hex) => {
+                self.show_hex = show_hex;
+            }
+            AppMessage::PixelHovered(pixel) => {
+                self.hovered_pixel = pixel;
+            }
+            AppMessage::PixelSelected(pixel) => {
+                self.selected_pixel = Some(pixel);
+                self.preview.set_selection(None);
+            }
+            AppMessage::HexByteSelected(byte) => {
+                let start_bit = byte * 8;
+                self.preview.set_start_bit(start_bit);
+                self.bit_offset_str = start_bit.to_string();
+                self.selected_pixel = None;
+            }
+            AppMessage::SelectionDragged(from, to) => {
+                let selection =
+                    (from != to).then(|| Selection::spanning(self.preview.start_bit(), from, to));
+                self.preview.set_selection(selection);
+                self.selection_error = None;
+            }
+            AppMessage::ClearSelection => {
+                self.preview.set_selection(None);
+                self.selection_error = None;
+            }
+            AppMessage::CopySelection => {
+                if let Some(selection) = self.preview.selection() {
+                    self.selection_error = self
+                        .copy_selection(&selection)
+                        .err()
+                        .map(|why| format!("Couldn't copy the selection: {why}"));
+                }
+            }
+            AppMessage::SaveSelection(format) => {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title(format.title())
+                    .add_filter(format.extension(), &[format.extension()])
+                    .save_file();
+                self.update_inspection();
+                return iced::Command::perform(dialog, move |handle| {
+                    AppMessage::SelectionSavePathPicked(
+                        format,
+                        handle.map(|handle| handle.path().to_owned()),
+                    )
+                });
+            }
+            AppMessage::SelectionSavePathPicked(format, path) => {
+                let selection = self.preview.selection();
+                if let (Some(selection), Some(path)) = (selection, path) {
+                    self.selection_error = self
+                        .save_selection(&selection, format, &path)
+                        .err()
+                        .map(|why| format!("Couldn't save {}: {why}", path.display()));
+                }
+            }
             AppMessage::FilePickResult(path) => {
                 self.picking_file = false;
                 if let Some(path) = path {
                     self.open_file(&path);
                 }
             }
+            AppMessage::FileDropped(path) => {
+                self.open_file(&path);
+            }
             AppMessage::ImageScrollHorizontal(scroll) => {
                 self.preview.set_x_scroll(scroll);
             }
@@ -193,8 +1044,18 @@ impl iced::Application for ImageViewApp {
             }
             AppMessage::ScaleStrChanged(s) => {
                 self.scale_str = s;
-                if let Ok(val) = self.scale_str.parse() {
-                    self.preview.set_scale(val);
+                // "1/4" zooms out to a quarter
+                match self.scale_str.trim().strip_prefix("1/") {
+                    Some(zoom_out) => {
+                        if let Ok(val) = zoom_out.trim().parse() {
+                            self.preview.set_zoom_out(val);
+                        }
+                    }
+                    None => {
+                        if let Ok(val) = self.scale_str.trim().parse() {
+                            self.preview.set_scale(val);
+                        }
+                    }
                 }
             }
             AppMessage::BitOffsetStrChanged(s) => {
@@ -203,6 +1064,35 @@ impl iced::Application for ImageViewApp {
                     self.preview.set_start_bit(val);
                 }
             }
+            AppMessage::GuessFormats => {
+                self.guessing_formats = true;
+                self.format_guess_error = None;
+                self.update_inspection();
+                return iced::Command::perform(
+                    self.preview.guess_formats(MAX_IMAGE_WIDTH),
+                    AppMessage::FormatsGuessed,
+                );
+            }
+            AppMessage::FormatsGuessed(guesses) => {
+                self.guessing_formats = false;
+                self.format_guess_error = guesses
+                    .is_empty()
+                    .then(|| "Too little data from the start bit to guess from".to_owned());
+                self.format_guesses = guesses;
+            }
+            AppMessage::FormatGuessSelected(guess) => {
+                self.select_pixel_mode(guess.mode);
+                self.set_byte_order(guess.byte_order);
+                self.preview.set_target_width(guess.width);
+                self.image_width_str = guess.width.to_string();
+            }
+            AppMessage::DetectWidth => {
+                self.width_candidates = self.preview.detect_widths(MAX_IMAGE_WIDTH);
+                self.width_detection_error = self
+                    .width_candidates
+                    .is_empty()
+                    .then(|| "No repeating rows found from the start bit".to_owned());
+            }
             AppMessage::IncrementImageWidth => {
                 let val = self.preview.target_width().saturating_add(1);
                 self.preview.set_target_width(val);
@@ -214,14 +1104,12 @@ impl iced::Application for ImageViewApp {
                 self.image_width_str = val.to_string();
             }
             AppMessage::IncrementScale => {
-                let val = self.preview.scale().saturating_add(1);
-                self.preview.set_scale(val);
-                self.scale_str = val.to_string();
+                self.preview.zoom(1);
+                self.scale_str = format_scale(&self.preview);
             }
             AppMessage::DecrementScale => {
-                let val = self.preview.scale().saturating_sub(1).max(1);
-                self.preview.set_scale(val);
-                self.scale_str = val.to_string();
+                self.preview.zoom(-1);
+                self.scale_str = format_scale(&self.preview);
             }
             AppMessage::IncrementBitOffset => {
                 let val = self.preview.start_bit().saturating_add(1);
@@ -235,6 +1123,7 @@ impl iced::Application for ImageViewApp {
             }
         }
 
+        self.update_inspection();
         iced::Command::none()
     }
 
@@ -242,14 +1131,18 @@ impl iced::Application for ImageViewApp {
         use iced::widget::vertical_rule;
 
         let preview = preview(self);
+        let hex_view = hex_view(self);
         let controls = controls(self);
-        row![preview, vertical_rule(2), controls].into()
+        row![preview, vertical_rule(2), hex_view, controls].into()
     }
 
     fn subscription(&self) -> Subscription<AppMessage> {
         let mut subcriptions = Vec::<Subscription<AppMessage>>::new();
 
-        let event_listener = iced::event::listen_with(|event, _| match event {
+        let event_listener = iced::event::listen_with(|event, status| match event {
+            Event::Window(_, window::Event::FileDropped(path)) => {
+                Some(AppMessage::FileDropped(path))
+            }
             Event::Window(_, window_event) => {
                 let new_size: Option<(u32, u32)> = match window_event {
                     window::Event::Opened { position: _, size } => {
@@ -261,7 +1154,10 @@ impl iced::Application for ImageViewApp {
 
                 new_size.map(|(width, height)| AppMessage::WindowResize { width, height })
             }
-            Event::Mouse(iced::mouse::Event::WheelScrolled { delta }) => {
+            // The preview scrolls itself when the wheel is over it
+            Event::Mouse(iced::mouse::Event::WheelScrolled { delta })
+                if status == iced::event::Status::Ignored =>
+            {
                 Some(AppMessage::ScrollWheel(delta))
             }
             _ => None,
@@ -274,11 +1170,38 @@ impl iced::Application for ImageViewApp {
             subcriptions.push(file_picker_subscription);
         }
 
+        if let Some(loading) = &self.loading {
+            let loader_subscription =
+                Subscription::from_recipe(FileLoader::new(loading.source.clone(), self.watching))
+                    .map(AppMessage::FileLoad);
+            subcriptions.push(loader_subscription);
+        }
+
+        let watched_file = self.file.as_ref().filter(|file| {
+            (self.watching && file.source.can_reload()) || file.source.always_refreshed()
+        });
+        if let Some(file) = watched_file {
+            if self.loading.is_none() {
+                let watcher =
+                    FileWatcher::new(file.source.clone(), file.stamp, file.data.is_mapped());
+                let watcher_subscription =
+                    Subscription::from_recipe(watcher).map(AppMessage::FileReloaded);
+                subcriptions.push(watcher_subscription);
+            }
+        }
+
+        if self.picking_palette {
+            let palette_picker_subscription =
+                Subscription::from_recipe(FilePicker::with_title("Open palette"))
+                    .map(AppMessage::PalettePickResult);
+            subcriptions.push(palette_picker_subscription);
+        }
+
         Subscription::batch(subcriptions)
     }
 }
 
-fn preview(app: &ImageViewApp) -> iced::Element<AppMessage> {
+fn preview(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
     use iced::Length;
     use iced::Padding;
 
@@ -331,7 +1254,8 @@ fn preview(app: &ImageViewApp) -> iced::Element<AppMessage> {
             right: 30.,
             bottom: 0.,
             left: 0.,
-        })
+        }),
+        pixel_inspector(app)
     )
     .padding(5)
     .width(Length::Fill)
@@ -339,22 +1263,71 @@ fn preview(app: &ImageViewApp) -> iced::Element<AppMessage> {
     .into()
 }
 
-fn controls(app: &ImageViewApp) -> iced::Element<AppMessage> {
+fn controls(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
     use iced::Length;
 
     use iced::widget::{
-        button, column, container, horizontal_rule, pick_list, row, slider, text, text_input,
+        button, column, container, horizontal_rule, pick_list, row, scrollable, slider, text,
+        text_input,
     };
 
     let controls = container(
         column!(
             open_button(app),
+            file_status(app),
             pick_list(
-                PixelMode::ALL,
-                Some(&app.pixel_mode),
+                app.pixel_modes(),
+                Some(app.pixel_mode.clone()),
                 AppMessage::PixelModeSelected
             )
             .width(Length::Fill),
+            format_guesses(app),
+            custom_format_editor(app),
+            palette_controls(app),
+            yuv_controls(app),
+            row!(
+                text("Alpha:"),
+                pick_list(
+                    AlphaMode::ALL,
+                    Some(app.preview.alpha_mode()),
+                    AppMessage::AlphaModeSelected
+                )
+                .width(Length::Fill)
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            row!(
+                text("Channel scaling:"),
+                pick_list(
+                    ChannelScaling::ALL,
+                    Some(app.preview.channel_scaling()),
+                    AppMessage::ChannelScalingSelected
+                )
+                .width(Length::Fill)
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            tone_controls(app),
+            pixel_order_picker(app),
+            planar_controls(app),
+            tiling_controls(app),
+            row!(
+                text("Byte order:"),
+                pick_list(
+                    UnitSize::ALL,
+                    Some(app.preview.byte_order().unit_size),
+                    AppMessage::UnitSizeSelected
+                )
+                .width(Length::Fill),
+                pick_list(
+                    Endianness::ALL,
+                    Some(app.preview.byte_order().endianness),
+                    AppMessage::EndiannessSelected
+                )
+                .width(Length::Fill)
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
             horizontal_rule(1),
             column!(
                 text(format!("Image width: {}", app.preview.target_width())),
@@ -362,17 +1335,20 @@ fn controls(app: &ImageViewApp) -> iced::Element<AppMessage> {
                     button("-").on_press(AppMessage::DecrementImageWidth),
                     text_input("Image width", &app.image_width_str)
                         .on_input(AppMessage::ImageWidthStrChanged),
-                    button("+").on_press(AppMessage::IncrementImageWidth)
+                    button("+").on_press(AppMessage::IncrementImageWidth),
+                    button("Auto-detect").on_press(AppMessage::DetectWidth)
                 )
                 .spacing(5),
                 slider(
-                    1..=2048,
+                    1..=MAX_IMAGE_WIDTH,
                     app.preview.target_width(),
                     AppMessage::ImageWidthSelected
-                )
+                ),
+                width_candidates(app),
+                row_stride_controls(app)
             ),
             column!(
-                text(format!("Scale: {}x", app.preview.scale())),
+                text(format!("Scale: {}x", format_scale(&app.preview))),
                 row!(
                     button("-").on_press(AppMessage::DecrementScale),
                     text_input("Scale", &app.scale_str)
@@ -416,15 +1392,700 @@ fn controls(app: &ImageViewApp) -> iced::Element<AppMessage> {
             }),
             checkbox("Grid", app.preview.grid())
                 .on_toggle(|checked| { AppMessage::ToggleGrid(checked) }),
+            checkbox("Hex view", app.show_hex).on_toggle(AppMessage::ToggleHexView),
+            selection_controls(app),
         )
         .width(400)
-        .height(Length::Fill)
         .padding(10),
     );
-    controls.into()
+    // Scrolls once the open sections are taller than the window
+    scrollable(controls).height(Length::Fill).into()
+}
+
+/// What the selection is, and buttons to copy or save it. Pixels are selected
+/// by dragging over the image.
+fn selection_controls(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{button, column, text};
+    use iced::Color;
+
+    let Some(selection) = app.preview.selection() else {
+        return column!().into();
+    };
+
+    let error = app
+        .selection_error
+        .as_ref()
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    column!(
+        text(format!(
+            "Selection: {}x{} pixels at {}, {}",
+            selection.width, selection.height, selection.x, selection.y
+        )),
+        row!(
+            button("Copy").on_press(AppMessage::CopySelection),
+            button("Save PNG").on_press(AppMessage::SaveSelection(SelectionExport::Png)),
+            button("Save bytes").on_press(AppMessage::SaveSelection(SelectionExport::Bytes)),
+            button("Clear").on_press(AppMessage::ClearSelection),
+        )
+        .spacing(5)
+    )
+    .push_maybe(error)
+    .spacing(5)
+    .into()
+}
+
+/// Where the hovered pixel, or else the selected one, is read from and what
+/// it decodes to.
+fn pixel_inspector(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{column, text};
+    use iced::Font;
+
+    let Some(((x, y), inspection)) = &app.inspection else {
+        return column!().into();
+    };
+
+    let offset = match inspection.file_bits.iter().min() {
+        Some(bit) => format!(
+            "Pixel {x}, {y} at bit {bit}, byte {} ({:#X}) + {} bits",
+            bit / 8,
+            bit / 8,
+            bit % 8
+        ),
+        None => format!("Pixel {x}, {y}, whose bits can't be shown for this layout"),
+    };
+
+    let raw_bits = (!inspection.raw_bits.is_empty()).then(|| {
+        let bits: Vec<String> = inspection
+            .raw_bits
+            .chunks(8)
+            .map(|byte| {
+                byte.iter()
+                    .map(|&set| if set { '1' } else { '0' })
+                    .collect()
+            })
+            .collect();
+        text(format!("Bits: {}", bits.join(" "))).font(Font::MONOSPACE)
+    });
+
+    let channels = (!inspection.channels.is_empty()).then(|| {
+        let channels: Vec<String> = inspection
+            .channels
+            .iter()
+            .map(|channel| {
+                format!(
+                    "{} {:#X} ({} bits) = {}",
+                    channel.name, channel.raw, channel.depth, channel.value
+                )
+            })
+            .collect();
+        text(channels.join(", ")).font(Font::MONOSPACE)
+    });
+
+    let pixel = inspection.pixel;
+    column!(text(offset))
+        .push_maybe(raw_bits)
+        .push_maybe(channels)
+        .push(text(format!(
+            "Decoded RGBA: {}, {}, {}, {}",
+            pixel.red, pixel.green, pixel.blue, pixel.alpha
+        )))
+        .spacing(2)
+        .padding(5)
+        .into()
+}
+
+/// Rows of bytes the hex view shows.
+const HEX_ROWS: u64 = 32;
+
+/// Bytes in each row of the hex view.
+const HEX_ROW_BYTES: u64 = 16;
+
+/// Most bytes the pixel only has some of the bits of that the hex view
+/// spells out bit by bit.
+const HEX_PARTIAL_BYTES: usize = 8;
+
+/// The bytes from the start of the view in hex and ASCII, with the bytes of
+/// the hovered or selected pixel highlighted. Bytes the pixel has only some
+/// of the bits of are dimmer, and spelled out bit by bit below. Clicking a
+/// byte starts the image there.
+fn hex_view(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use std::collections::BTreeMap;
+
+    use iced::widget::{column, container, lazy, text, vertical_rule};
+    use iced::Font;
+
+    if !app.show_hex {
+        return column!().into();
+    }
+
+    let data = app.preview.file_data();
+    let (pixel, pixel_bits) = match &app.inspection {
+        Some((pixel, inspection)) => (Some(*pixel), inspection.file_bits.as_slice()),
+        None => (None, &[][..]),
+    };
+    // The bits of each byte the pixel is read from, most significant first
+    let mut highlighted: BTreeMap<u64, u8> = BTreeMap::new();
+    for bit in pixel_bits {
+        *highlighted.entry(bit / 8).or_default() |= 0x80 >> (bit % 8);
+    }
+
+    // Rows go a page at a time from the start of the view, showing the page
+    // the pixel's first byte is on, so they stay put while the cursor moves
+    // over pixels on the same page
+    let start_row = app.preview.start_bit() / 8 / HEX_ROW_BYTES;
+    let first_row = match highlighted.first_key_value() {
+        Some((&first_byte, _)) => {
+            let pages = (first_byte / HEX_ROW_BYTES).saturating_sub(start_row) / HEX_ROWS;
+            start_row + pages * HEX_ROWS
+        }
+        None => start_row,
+    };
+    let page_start = first_row * HEX_ROW_BYTES;
+    let index = |offset: u64| usize::try_from(offset).map_or(data.len(), |i| i.min(data.len()));
+    let bytes = data[index(page_start)..index(page_start + HEX_ROWS * HEX_ROW_BYTES)].to_vec();
+    let masks = (page_start..page_start + bytes.len() as u64)
+        .map(|offset| highlighted.get(&offset).copied().unwrap_or(0))
+        .collect();
+    let page = HexPage {
+        first_row,
+        bytes,
+        masks,
+    };
+
+    let pixel_label = pixel.map(|(x, y)| {
+        let label = match (pixel_bits.iter().min(), pixel_bits.iter().max()) {
+            (Some(first), Some(last)) if last - first + 1 == pixel_bits.len() as u64 => format!(
+                "Pixel {x}, {y}: {} bits from byte {:#X} + {} bits",
+                pixel_bits.len(),
+                first / 8,
+                first % 8
+            ),
+            (Some(_), Some(_)) => format!(
+                "Pixel {x}, {y}: {} bits over {} bytes",
+                pixel_bits.len(),
+                highlighted.len()
+            ),
+            _ => format!("Pixel {x}, {y}: bits can't be shown for this layout"),
+        };
+        text(label).size(12)
+    });
+
+    // The pixel's bits of each partly covered byte, with dots for the rest
+    let partial_bytes = highlighted
+        .iter()
+        .filter(|(_, &mask)| mask != 0xFF)
+        .take(HEX_PARTIAL_BYTES)
+        .map(|(&offset, &mask)| {
+            let byte = usize::try_from(offset)
+                .ok()
+                .and_then(|i| data.get(i))
+                .copied()
+                .unwrap_or(0);
+            let bits: String = (0..8)
+                .map(
+                    |bit| match (mask << bit & 0x80 != 0, byte << bit & 0x80 != 0) {
+                        (false, _) => '.',
+                        (true, false) => '0',
+                        (true, true) => '1',
+                    },
+                )
+                .collect();
+            text(format!("{offset:08X}  {bits}"))
+                .font(Font::MONOSPACE)
+                .size(12)
+                .into()
+        });
+
+    row!(
+        container(
+            column!(lazy(page, hex_page))
+                .push_maybe(pixel_label)
+                .push(column(partial_bytes))
+                .spacing(2)
+        )
+        .padding(10),
+        vertical_rule(2)
+    )
+    .into()
+}
+
+/// The rows of bytes the hex view shows, and the bits of each the hovered
+/// or selected pixel is read from. Its buttons are only built again when
+/// this changes.
+#[derive(Hash)]
+struct HexPage {
+    first_row: u64,
+    /// Fewer than a page's worth at the end of the file
+    bytes: Vec<u8>,
+    /// The pixel's bits of each of `bytes`, most significant first
+    masks: Vec<u8>,
+}
+
+fn hex_page(page: &HexPage) -> iced::Element<'static, AppMessage> {
+    use iced::widget::{button, column, text, Row};
+    use iced::{theme, Font};
+
+    let byte_button = |i: usize, label: String| {
+        let style = match page.masks[i] {
+            0xFF => theme::Button::Primary,
+            0 => theme::Button::Text,
+            _ => theme::Button::Secondary,
+        };
+        let offset = page.first_row * HEX_ROW_BYTES + i as u64;
+        button(text(label).font(Font::MONOSPACE).size(12))
+            .padding(0)
+            .style(style)
+            .on_press(AppMessage::HexByteSelected(offset))
+            .into()
+    };
+
+    let rows = (0..HEX_ROWS).map(|row| {
+        let first = (row * HEX_ROW_BYTES) as usize;
+        let indices = first..(first + HEX_ROW_BYTES as usize).min(page.bytes.len());
+
+        let hex = indices
+            .clone()
+            .map(|i| byte_button(i, format!("{:02X}", page.bytes[i])));
+        let ascii = indices.map(|i| {
+            let character = match page.bytes[i] {
+                byte @ 0x20..=0x7E => byte as char,
+                _ => '.',
+            };
+            byte_button(i, character.to_string())
+        });
+
+        Row::new()
+            .push(
+                text(format!("{:08X}", (page.first_row + row) * HEX_ROW_BYTES))
+                    .font(Font::MONOSPACE)
+                    .size(12),
+            )
+            .push(Row::with_children(hex).spacing(4))
+            .push(Row::with_children(ascii))
+            .spacing(10)
+            .into()
+    });
+
+    column(rows).spacing(2).into()
+}
+
+fn custom_format_editor(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{button, column, text, text_input};
+    use iced::Color;
+
+    let remove_button = match app.pixel_mode {
+        PixelMode::Custom(_) => Some(button("Remove").on_press(AppMessage::RemoveCustomFormat)),
+        _ => None,
+    };
+
+    let error = app
+        .custom_format_error
+        .as_ref()
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    column!(row!(
+        text_input("Custom format, e.g. R5G6B5", &app.custom_format_str)
+            .on_input(AppMessage::CustomFormatStrChanged)
+            .on_submit(AppMessage::AddCustomFormat),
+        button("Add").on_press(AppMessage::AddCustomFormat)
+    )
+    .push_maybe(remove_button)
+    .spacing(5))
+    .push_maybe(error)
+    .spacing(5)
+    .into()
+}
+
+/// Tone mapping settings, shown for modes made of channel bits. Minimum and
+/// maximum are only shown when normalizing.
+fn tone_controls(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{button, column, pick_list, slider, text, text_input};
+    use iced::{Color, Length};
+
+    let decoding_scheme = app.pixel_mode.decoding_scheme();
+    if decoding_scheme.yuv.is_some()
+        || decoding_scheme.block.is_some()
+        || decoding_scheme.is_indexed()
+    {
+        return column!().into();
+    }
+
+    let tone = app.preview.tone();
+
+    let range = (tone.tone_mapping == ToneMapping::Normalize).then(|| {
+        row!(
+            text("Range:"),
+            text_input("Minimum", &app.tone_min_str).on_input(AppMessage::ToneMinStrChanged),
+            text("to"),
+            text_input("Maximum", &app.tone_max_str).on_input(AppMessage::ToneMaxStrChanged),
+            button("Auto").on_press(AppMessage::AutoToneRange)
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+    });
+
+    let error = app
+        .tone_error
+        .as_ref()
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    column!(
+        row!(
+            text("Tone mapping:"),
+            pick_list(
+                ToneMapping::ALL,
+                Some(tone.tone_mapping),
+                AppMessage::ToneMappingSelected
+            )
+            .width(Length::Fill)
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center),
+        row!(
+            text(format!("Exposure: {:+.1}", tone.exposure)),
+            slider(-8.0..=8.0, tone.exposure, AppMessage::ExposureChanged)
+                .step(0.5)
+                .width(Length::Fill)
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+    )
+    .push_maybe(range)
+    .push_maybe(error)
+    .spacing(5)
+    .into()
+}
+
+/// Widths found by width detection, best first, each applied by clicking it.
+fn width_candidates(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{button, column, text};
+    use iced::{Color, Length};
+
+    const PER_ROW: usize = 4;
+
+    let error = app
+        .width_detection_error
+        .as_ref()
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    let rows = app.width_candidates.chunks(PER_ROW).map(|candidates| {
+        let buttons = candidates.iter().map(|candidate| {
+            button(text(format!(
+                "{} ({:.0}%)",
+                candidate.width,
+                candidate.confidence * 100.0
+            )))
+            .on_press(AppMessage::ImageWidthSelected(candidate.width))
+            .width(Length::Fill)
+            .into()
+        });
+        iced::widget::Row::with_children(buttons).spacing(5).into()
+    });
+
+    column(rows).push_maybe(error).spacing(5).into()
+}
+
+/// The "Guess format" button and the formats it found, best first, each
+/// applied with its byte order and width by clicking it.
+fn format_guesses(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{button, column, text};
+    use iced::{Color, Length};
+
+    let guess_button = button(if app.guessing_formats {
+        "Guessing format..."
+    } else {
+        "Guess format"
+    })
+    .on_press_maybe((!app.guessing_formats).then_some(AppMessage::GuessFormats))
+    .width(Length::Fill);
+
+    let error = app
+        .format_guess_error
+        .as_ref()
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    let guesses = app.format_guesses.iter().map(|guess| {
+        let byte_order = if guess.byte_order.is_swapped() {
+            format!(", {} little endian", guess.byte_order.unit_size)
+        } else {
+            String::new()
+        };
+        button(text(format!(
+            "{}{byte_order}, {} wide ({:.0}%)",
+            guess.mode,
+            guess.width,
+            guess.score * 100.0
+        )))
+        .on_press(AppMessage::FormatGuessSelected(guess.clone()))
+        .width(Length::Fill)
+        .into()
+    });
+
+    column!(guess_button)
+        .extend(guesses)
+        .push_maybe(error)
+        .spacing(5)
+        .into()
 }
 
-fn open_button(_app: &ImageViewApp) -> iced::Element<AppMessage> {
+/// Pixel order only matters for pixels narrower than a byte, so it is only
+/// shown for those modes.
+fn pixel_order_picker(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{column, pick_list, text};
+    use iced::Length;
+
+    if app.pixel_mode.decoding_scheme().bits_per_pixel >= 8 {
+        return column!().into();
+    }
+
+    row!(
+        text("Pixel order:"),
+        pick_list(
+            PixelOrder::ALL,
+            Some(app.preview.pixel_order()),
+            AppMessage::PixelOrderSelected
+        )
+        .width(Length::Fill)
+    )
+    .spacing(5)
+    .align_items(iced::Alignment::Center)
+    .into()
+}
+
+fn planar_controls(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{column, pick_list, text, text_input};
+    use iced::{Color, Length};
+
+    let planar = app.preview.planar_layout().is_planar() || app.planar_error.is_some();
+
+    let plane_order = planar.then(|| {
+        text_input(
+            "Plane order, most significant first, e.g. 3 2 1 0",
+            &app.plane_order_str,
+        )
+        .on_input(AppMessage::PlaneOrderStrChanged)
+    });
+
+    let plane_size = (planar && app.plane_interleave == PlaneInterleave::Image).then(|| {
+        text_input(
+            "Plane size in bytes, or empty to split the file",
+            &app.plane_size_str,
+        )
+        .on_input(AppMessage::PlaneSizeStrChanged)
+    });
+
+    let error = app
+        .planar_error
+        .as_ref()
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    column!(row!(
+        text("Planes:"),
+        text_input("1", &app.planes_str)
+            .on_input(AppMessage::PlanesStrChanged)
+            .width(Length::Fixed(50.0)),
+        pick_list(
+            PlaneInterleave::ALL,
+            Some(app.plane_interleave),
+            AppMessage::PlaneInterleaveSelected
+        )
+        .width(Length::Fill)
+    )
+    .spacing(5)
+    .align_items(iced::Alignment::Center))
+    .push_maybe(plane_order)
+    .push_maybe(plane_size)
+    .push_maybe(error)
+    .spacing(5)
+    .into()
+}
+
+fn tiling_controls(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{column, pick_list, text, text_input};
+    use iced::{Color, Length};
+
+    let tile_size = app.tile_order.has_tile_size().then(|| {
+        row!(
+            text("Tile size:"),
+            text_input("Width", &app.tile_width_str).on_input(AppMessage::TileWidthStrChanged),
+            text("x"),
+            text_input("Height", &app.tile_height_str).on_input(AppMessage::TileHeightStrChanged)
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+    });
+
+    let error = app
+        .tiling_error
+        .as_ref()
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    column!(row!(
+        text("Pixel layout:"),
+        pick_list(
+            TileOrder::ALL,
+            Some(app.tile_order),
+            AppMessage::TileOrderSelected
+        )
+        .width(Length::Fill)
+    )
+    .spacing(5)
+    .align_items(iced::Alignment::Center))
+    .push_maybe(tile_size)
+    .push_maybe(error)
+    .spacing(5)
+    .into()
+}
+
+/// The row stride, hidden behind a lock that keeps rows the width apart.
+fn row_stride_controls(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{checkbox, column, pick_list, text, text_input};
+    use iced::{Color, Length};
+
+    let lock =
+        checkbox("Row stride = width", app.stride_locked).on_toggle(AppMessage::ToggleStrideLock);
+
+    let stride = (!app.stride_locked).then(|| {
+        row!(
+            text("Row stride:"),
+            text_input("Row stride", &app.row_stride_str).on_input(AppMessage::RowStrideStrChanged),
+            pick_list(
+                StrideUnit::ALL,
+                Some(app.stride_unit),
+                AppMessage::StrideUnitSelected
+            )
+            .width(Length::Fixed(100.0))
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+    });
+
+    let error = app
+        .row_stride_error
+        .clone()
+        .or_else(|| app.preview.row_stride_error().map(|why| why.to_string()))
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    column!(lock)
+        .push_maybe(stride)
+        .push_maybe(error)
+        .spacing(5)
+        .into()
+}
+
+/// Palette settings, only shown for indexed pixel modes.
+fn palette_controls(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{button, column, pick_list, text, text_input};
+    use iced::{Color, Length};
+
+    if !app.pixel_mode.decoding_scheme().is_indexed() {
+        return column!().into();
+    }
+
+    let selected_builtin = match &app.palette_source {
+        PaletteSource::Builtin(builtin) => Some(*builtin),
+        _ => None,
+    };
+
+    let source = match &app.palette_source {
+        PaletteSource::Builtin(_) => None,
+        PaletteSource::File(path) => Some(text(format!("From {}", path.to_string_lossy()))),
+        PaletteSource::Data {
+            start_bit,
+            pixel_mode,
+        } => Some(text(format!("From start bit {start_bit} as {pixel_mode}"))),
+    };
+
+    let error = app
+        .palette_error
+        .as_ref()
+        .map(|why| text(why).style(Color::from_rgb(0.9, 0.2, 0.2)));
+
+    let color_count = text(format!("{} colors", app.preview.palette().colors().len()));
+
+    column!(
+        row!(
+            text("Palette:"),
+            pick_list(
+                BuiltinPalette::ALL,
+                selected_builtin,
+                AppMessage::BuiltinPaletteSelected
+            )
+            .placeholder("Custom")
+            .width(Length::Fill),
+            button("Load").on_press(AppMessage::LoadPaletteDialog)
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center),
+        row!(
+            text_input("Palette start bit", &app.palette_offset_str)
+                .on_input(AppMessage::PaletteOffsetStrChanged)
+                .on_submit(AppMessage::PaletteFromData),
+            pick_list(
+                PixelMode::ALL
+                    .iter()
+                    .filter(|mode| !mode.decoding_scheme().is_indexed())
+                    .cloned()
+                    .collect::<Vec<_>>(),
+                Some(app.palette_pixel_mode.clone()),
+                AppMessage::PalettePixelModeSelected
+            ),
+            button("From file data").on_press(AppMessage::PaletteFromData)
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center),
+    )
+    .push(color_count)
+    .push_maybe(source)
+    .push_maybe(error)
+    .spacing(5)
+    .into()
+}
+
+/// YUV conversion settings, only shown for YUV pixel modes. Frame height only
+/// matters where the chroma planes follow the luma plane.
+fn yuv_controls(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{column, pick_list, text, text_input};
+    use iced::Length;
+
+    let Some(format) = app.pixel_mode.decoding_scheme().yuv else {
+        return column!().into();
+    };
+
+    let frame_height = format.is_420().then(|| {
+        row!(
+            text("Frame height:"),
+            text_input("Fit to file", &app.frame_height_str)
+                .on_input(AppMessage::FrameHeightStrChanged)
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+    });
+
+    column!(row!(
+        text("YUV:"),
+        pick_list(
+            YuvMatrix::ALL,
+            Some(app.preview.yuv().matrix),
+            AppMessage::YuvMatrixSelected
+        )
+        .width(Length::Fill),
+        pick_list(
+            YuvRange::ALL,
+            Some(app.preview.yuv().range),
+            AppMessage::YuvRangeSelected
+        )
+        .width(Length::Fill)
+    )
+    .spacing(5)
+    .align_items(iced::Alignment::Center))
+    .push_maybe(frame_height)
+    .spacing(5)
+    .into()
+}
+
+fn open_button(_app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
     use iced::Length;
 
     let button = iced::widget::Button::new("Open")
@@ -434,7 +2095,157 @@ fn open_button(_app: &ImageViewApp) -> iced::Element<AppMessage> {
     button.into()
 }
 
+/// Where data comes from besides the open button: reloading, attaching to a
+/// process, progress of the data being loaded and why the last couldn't be.
+fn file_status(app: &ImageViewApp) -> iced::Element<'_, AppMessage> {
+    use iced::widget::{button, column, pick_list, progress_bar, text, text_input};
+    use iced::{Color, Length};
+
+    let loading = app.loading.as_ref().map(|loading| {
+        let name = match &loading.source {
+            DataSource::File(path) => path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+            source => source.to_string(),
+        };
+        let (progress, fraction) = match loading.total {
+            Some(total) => (
+                format!(
+                    "{} of {}",
+                    format_bytes(loading.loaded),
+                    format_bytes(total)
+                ),
+                loading.loaded as f32 / total as f32,
+            ),
+            None => (format_bytes(loading.loaded), 0.0),
+        };
+
+        column!(
+            text(format!("Loading {name}: {progress}")),
+            row!(
+                progress_bar(0.0..=1.0, fraction).height(Length::Fixed(10.0)),
+                button("Cancel").on_press(AppMessage::CancelLoad)
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center)
+        )
+        .spacing(5)
+    });
+
+    let error = app.file_error.as_ref().map(|why| {
+        row!(
+            text(why)
+                .style(Color::from_rgb(0.9, 0.2, 0.2))
+                .width(Length::Fill),
+            button("Dismiss").on_press(AppMessage::DismissFileError)
+        )
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+    });
+
+    let watch =
+        checkbox("Reload when the data changes", app.watching).on_toggle(AppMessage::ToggleWatch);
+
+    let (selected_region, offset) = match app.file.as_ref().map(|file| &file.source) {
+        Some(DataSource::Process { region, offset, .. }) => (Some(region.clone()), *offset),
+        _ => (None, 0),
+    };
+    let process = row!(
+        text("Process:"),
+        text_input("PID", &app.pid_str)
+            .on_input(AppMessage::PidStrChanged)
+            .on_submit(AppMessage::ListRegions)
+            .width(Length::Fixed(80.0)),
+        button("Attach").on_press(AppMessage::ListRegions)
+    )
+    .spacing(5)
+    .align_items(iced::Alignment::Center);
+    // Regions too big to read whole are read a window at a time, moved half
+    // a window along so what straddles the edge of one is whole in the next
+    let window = selected_region
+        .as_ref()
+        .filter(|region| region.is_windowed())
+        .map(|region| {
+            let window = region.window(offset);
+            let step = process_memory::MAX_READ_LEN / 2;
+            let previous = (window.start > 0)
+                .then(|| AppMessage::RegionWindowMoved(window.start.saturating_sub(step)));
+            let next = (window.end < region.len())
+                .then(|| AppMessage::RegionWindowMoved(window.start + step));
+            row!(
+                button("Previous").on_press_maybe(previous),
+                text(format!(
+                    "Bytes {:#x} to {:#x} of {}",
+                    window.start,
+                    window.end,
+                    format_bytes(region.len())
+                ))
+                .width(Length::Fill),
+                button("Next").on_press_maybe(next)
+            )
+            .spacing(5)
+            .align_items(iced::Alignment::Center)
+        });
+    let regions = (!app.regions.is_empty()).then(|| {
+        pick_list(
+            app.regions.as_slice(),
+            selected_region,
+            AppMessage::RegionSelected,
+        )
+        .placeholder("Memory region")
+        .width(Length::Fill)
+    });
+
+    column!()
+        .push(watch)
+        .push(process)
+        .push_maybe(regions)
+        .push_maybe(window)
+        .push_maybe(loading)
+        .push_maybe(error)
+        .spacing(5)
+        .into()
+}
+
+/// Formats the scale the way the scale field takes it, e.g. "3" for 3x or
+/// "1/4" when zoomed out to a quarter.
+fn format_scale(preview: &Preview) -> String {
+    match preview.zoom_out() {
+        1 => preview.scale().to_string(),
+        zoom_out => format!("1/{zoom_out}"),
+    }
+}
+
+/// Formats a byte count with a binary unit, e.g. "1.5 MiB".
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{bytes} bytes"),
+        _ => format!("{value:.1} {}", UNITS[unit]),
+    }
+}
+
 pub fn main() -> iced::Result {
-    let settings = iced::Settings::default();
+    let cli = Cli::parse();
+
+    if let Some(Command::Render(args)) = &cli.command {
+        if let Err(why) = cli::render(args) {
+            eprintln!("Could not render {:#?} : {why}", args.path);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let settings = iced::Settings::with_flags(cli.open);
     ImageViewApp::run(settings)
 }
@@ -0,0 +1,498 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use super::byte_order::ByteOrder;
+use super::channel_scaling::ChannelScaling;
+use super::decoder::Decoder;
+use super::pixel_mode::PixelMode;
+use super::preview::Pixel;
+use super::shader::DecodingScheme;
+
+/// Colors looked up by indexed pixel modes. Entries past the end of the
+/// palette show as black.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    colors: Vec<Pixel>,
+}
+
+impl Palette {
+    /// Palettes hold at most this many colors, enough for 8 bit indices.
+    pub const MAX_COLORS: usize = 256;
+
+    pub fn new(mut colors: Vec<Pixel>) -> Self {
+        colors.truncate(Self::MAX_COLORS);
+        Self { colors }
+    }
+
+    /// A ramp from black to white over every value of a `depth` bit index.
+    pub fn grayscale(depth: u32) -> Self {
+        let max_index = (1u32 << depth.min(8)) - 1;
+        let colors = (0..=max_index)
+            .map(|i| {
+                let value = (i * 255 + max_index / 2)
+                    .checked_div(max_index)
+                    .unwrap_or(0) as u8;
+                rgb(value, value, value)
+            })
+            .collect();
+        Self { colors }
+    }
+
+    pub fn colors(&self) -> &[Pixel] {
+        &self.colors
+    }
+
+    pub fn color(&self, index: u32) -> Pixel {
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| self.colors.get(i))
+            .copied()
+            .unwrap_or(rgb(0, 0, 0))
+    }
+
+    /// Packs the palette into `MAX_COLORS` RGBA words for the shader, padded
+    /// with black.
+    pub fn shader_buffer(&self) -> Vec<u32> {
+        (0..Self::MAX_COLORS as u32)
+            .map(|i| {
+                let color = self.color(i);
+                u32::from_be_bytes([color.red, color.green, color.blue, color.alpha])
+            })
+            .collect()
+    }
+
+    /// Reads a JASC-PAL, GIMP `.gpl` or Adobe `.act` palette, or failing
+    /// those, raw RGB triplets.
+    pub fn load(path: &Path) -> Result<Self, PaletteError> {
+        let data = fs::read(path).map_err(|why| PaletteError::Io(why.to_string()))?;
+        Self::parse(&data)
+    }
+
+    /// Decodes `count` colors stored in the file itself, starting at
+    /// `start_bit`. An indexed scheme reads its own colors as grayscale.
+    pub fn from_data(
+        file_data: &[u8],
+        start_bit: u64,
+        decoding_scheme: &DecodingScheme,
+        channel_scaling: ChannelScaling,
+        byte_order: ByteOrder,
+        count: u32,
+    ) -> Self {
+        let count = count.min(Self::MAX_COLORS as u32);
+        let grayscale = Self::grayscale(decoding_scheme.index_depth());
+        let decoder = Decoder {
+            channel_scaling,
+            byte_order,
+            ..Decoder::new(decoding_scheme, &grayscale)
+        };
+        Self::new(decoder.decode_file_lines(file_data, start_bit, count, 1))
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, PaletteError> {
+        if data.starts_with(b"JASC-PAL") {
+            parse_jasc(&String::from_utf8_lossy(data))
+        } else if data.starts_with(b"GIMP Palette") {
+            parse_gimp(&String::from_utf8_lossy(data))
+        } else {
+            parse_act(data)
+        }
+    }
+}
+
+fn rgb(red: u8, green: u8, blue: u8) -> Pixel {
+    Pixel {
+        red,
+        green,
+        blue,
+        alpha: u8::MAX,
+    }
+}
+
+fn rgb_hex(color: u32) -> Pixel {
+    let [_, red, green, blue] = color.to_be_bytes();
+    rgb(red, green, blue)
+}
+
+/// Parses a line of whitespace separated decimal `R G B`, ignoring anything
+/// after the blue value.
+fn parse_rgb_line(line: &str) -> Result<Pixel, PaletteError> {
+    let mut values = line.split_whitespace().map(|value| {
+        value
+            .parse::<u8>()
+            .map_err(|_| PaletteError::InvalidColor(line.to_owned()))
+    });
+
+    let mut next = || {
+        values
+            .next()
+            .unwrap_or_else(|| Err(PaletteError::InvalidColor(line.to_owned())))
+    };
+
+    Ok(rgb(next()?, next()?, next()?))
+}
+
+fn parse_jasc(contents: &str) -> Result<Palette, PaletteError> {
+    let mut lines = contents.lines().map(str::trim);
+
+    // Magic, then the version
+    lines.next();
+    lines.next();
+
+    let count = lines
+        .next()
+        .and_then(|count| count.parse::<usize>().ok())
+        .ok_or(PaletteError::InvalidHeader)?;
+
+    let colors = lines
+        .filter(|line| !line.is_empty())
+        .take(count)
+        .map(parse_rgb_line)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Palette::new(colors))
+}
+
+fn parse_gimp(contents: &str) -> Result<Palette, PaletteError> {
+    let colors = contents
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| {
+            !line.is_empty()
+                && !line.starts_with('#')
+                && !line.starts_with("Name:")
+                && !line.starts_with("Columns:")
+        })
+        .map(parse_rgb_line)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Palette::new(colors))
+}
+
+/// Adobe `.act` files are 256 RGB triplets, optionally followed by a big
+/// endian color count and transparent index. A count of 0 means all 256,
+/// as some tools write it. Anything else is treated as plain RGB triplets.
+fn parse_act(data: &[u8]) -> Result<Palette, PaletteError> {
+    let count = match data.len() {
+        772 => match usize::from(u16::from_be_bytes([data[768], data[769]])) {
+            0 => 256,
+            count => count.min(256),
+        },
+        len => len / 3,
+    };
+
+    if count == 0 {
+        return Err(PaletteError::Empty);
+    }
+
+    let colors = data
+        .chunks_exact(3)
+        .take(count)
+        .map(|rgb_bytes| rgb(rgb_bytes[0], rgb_bytes[1], rgb_bytes[2]))
+        .collect();
+
+    Ok(Palette::new(colors))
+}
+
+/// Where the colors for indexed pixel modes come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteSource {
+    Builtin(BuiltinPalette),
+    File(PathBuf),
+    /// Colors decoded from the viewed file itself.
+    Data {
+        start_bit: u64,
+        pixel_mode: PixelMode,
+    },
+}
+
+impl Default for PaletteSource {
+    fn default() -> Self {
+        Self::Builtin(BuiltinPalette::default())
+    }
+}
+
+impl PaletteSource {
+    /// Builds the palette for a `index_depth` bit index, reading colors stored
+    /// in the file from `file_data`.
+    pub fn resolve(
+        &self,
+        file_data: &[u8],
+        index_depth: u32,
+        channel_scaling: ChannelScaling,
+        byte_order: ByteOrder,
+    ) -> Result<Palette, PaletteError> {
+        match self {
+            PaletteSource::Builtin(builtin) => Ok(builtin.palette(index_depth)),
+            PaletteSource::File(path) => Palette::load(path),
+            PaletteSource::Data {
+                start_bit,
+                pixel_mode,
+            } => Ok(Palette::from_data(
+                file_data,
+                *start_bit,
+                pixel_mode.decoding_scheme(),
+                channel_scaling,
+                byte_order,
+                1 << index_depth.min(8),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteError {
+    Io(String),
+    InvalidHeader,
+    InvalidColor(String),
+    Empty,
+}
+
+impl Display for PaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaletteError::Io(why) => write!(f, "{why}"),
+            PaletteError::InvalidHeader => write!(f, "Invalid palette header"),
+            PaletteError::InvalidColor(line) => write!(f, "Invalid palette color {line:?}"),
+            PaletteError::Empty => write!(f, "Palette has no colors"),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum BuiltinPalette {
+    #[default]
+    Grayscale,
+    Vga,
+    GameBoy,
+    Nes,
+}
+
+impl BuiltinPalette {
+    pub const ALL: &'static [Self] = &[Self::Grayscale, Self::Vga, Self::GameBoy, Self::Nes];
+
+    /// The grayscale ramp covers every value of a `depth` bit index, the
+    /// others are fixed.
+    pub fn palette(&self, depth: u32) -> Palette {
+        match self {
+            BuiltinPalette::Grayscale => Palette::grayscale(depth),
+            BuiltinPalette::Vga => vga_palette(),
+            BuiltinPalette::GameBoy => {
+                Palette::new(GAME_BOY.iter().copied().map(rgb_hex).collect())
+            }
+            BuiltinPalette::Nes => Palette::new(NES.iter().copied().map(rgb_hex).collect()),
+        }
+    }
+}
+
+impl Display for BuiltinPalette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BuiltinPalette::Grayscale => "grayscale",
+            BuiltinPalette::Vga => "vga",
+            BuiltinPalette::GameBoy => "gameboy",
+            BuiltinPalette::Nes => "nes",
+        })
+    }
+}
+
+impl FromStr for BuiltinPalette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BuiltinPalette::ALL
+            .iter()
+            .find(|palette| palette.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown palette {s:?}"))
+    }
+}
+
+/// The original Game Boy's shades of green, lightest first.
+const GAME_BOY: [u32; 4] = [0x9BBC0F, 0x8BAC0F, 0x306230, 0x0F380F];
+
+/// The NES PPU's 64 colors.
+const NES: [u32; 64] = [
+    0x7C7C7C, 0x0000FC, 0x0000BC, 0x4428BC, 0x940084, 0xA80020, 0xA81000, 0x881400, //
+    0x503000, 0x007800, 0x006800, 0x005800, 0x004058, 0x000000, 0x000000, 0x000000, //
+    0xBCBCBC, 0x0078F8, 0x0058F8, 0x6844FC, 0xD800CC, 0xE40058, 0xF83800, 0xE45C10, //
+    0xAC7C00, 0x00B800, 0x00A800, 0x00A844, 0x008888, 0x000000, 0x000000, 0x000000, //
+    0xF8F8F8, 0x3CBCFC, 0x6888FC, 0x9878F8, 0xF878F8, 0xF85898, 0xF87858, 0xFCA044, //
+    0xF8B800, 0xB8F818, 0x58D854, 0x58F898, 0x00E8D8, 0x787878, 0x000000, 0x000000, //
+    0xFCFCFC, 0xA4E4FC, 0xB8B8F8, 0xD8B8F8, 0xF8B8F8, 0xF8A4C0, 0xF0D0B0, 0xFCE0A8, //
+    0xF8D878, 0xD8F878, 0xB8F8B8, 0xB8F8D8, 0x00FCFC, 0xF8D8F8, 0x000000, 0x000000, //
+];
+
+/// Builds the default VGA mode 13h palette: the 16 EGA colors, 16 grays, 9
+/// rings of 24 hues at three brightnesses and saturations, then black.
+fn vga_palette() -> Palette {
+    // 6 bit DAC values, widened to 8 bits by repeating the top bits
+    let dac = |value: u8| (value << 2) | (value >> 4);
+
+    let ega = [
+        0x000000, 0x0000AA, 0x00AA00, 0x00AAAA, 0xAA0000, 0xAA00AA, 0xAA5500, 0xAAAAAA, 0x555555,
+        0x5555FF, 0x55FF55, 0x55FFFF, 0xFF5555, 0xFF55FF, 0xFFFF55, 0xFFFFFF,
+    ];
+    let grays = [0, 5, 8, 11, 14, 17, 20, 24, 28, 32, 36, 40, 45, 50, 56, 63];
+    let rings: [[u8; 5]; 9] = [
+        [0, 16, 31, 47, 63],
+        [31, 39, 47, 55, 63],
+        [45, 49, 54, 58, 63],
+        [0, 7, 14, 21, 28],
+        [14, 17, 21, 24, 28],
+        [20, 22, 24, 26, 28],
+        [0, 4, 8, 12, 16],
+        [8, 10, 12, 14, 16],
+        [11, 12, 13, 15, 16],
+    ];
+    // Indices into a ring's values for each hue, going from blue to red to
+    // green and back to blue
+    let hues: [[usize; 3]; 24] = [
+        [0, 0, 4],
+        [1, 0, 4],
+        [2, 0, 4],
+        [3, 0, 4],
+        [4, 0, 4],
+        [4, 0, 3],
+        [4, 0, 2],
+        [4, 0, 1],
+        [4, 0, 0],
+        [4, 1, 0],
+        [4, 2, 0],
+        [4, 3, 0],
+        [4, 4, 0],
+        [3, 4, 0],
+        [2, 4, 0],
+        [1, 4, 0],
+        [0, 4, 0],
+        [0, 4, 1],
+        [0, 4, 2],
+        [0, 4, 3],
+        [0, 4, 4],
+        [0, 3, 4],
+        [0, 2, 4],
+        [0, 1, 4],
+    ];
+
+    let colors = ega
+        .iter()
+        .copied()
+        .map(rgb_hex)
+        .chain(
+            grays
+                .iter()
+                .map(|&gray| rgb(dac(gray), dac(gray), dac(gray))),
+        )
+        .chain(rings.iter().flat_map(|ring| {
+            hues.iter()
+                .map(|[r, g, b]| rgb(dac(ring[*r]), dac(ring[*g]), dac(ring[*b])))
+        }))
+        .chain(std::iter::repeat_n(rgb(0, 0, 0), 8))
+        .collect();
+
+    Palette::new(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_sizes() {
+        assert_eq!(BuiltinPalette::Vga.palette(8).colors().len(), 256);
+        assert_eq!(BuiltinPalette::Nes.palette(8).colors().len(), 64);
+        assert_eq!(BuiltinPalette::GameBoy.palette(8).colors().len(), 4);
+        assert_eq!(BuiltinPalette::Grayscale.palette(2).colors().len(), 4);
+    }
+
+    #[test]
+    fn vga_landmarks() {
+        let vga = BuiltinPalette::Vga.palette(8);
+        assert_eq!(vga.color(1), rgb(0x00, 0x00, 0xAA));
+        assert_eq!(vga.color(15), rgb(0xFF, 0xFF, 0xFF));
+        assert_eq!(vga.color(31), rgb(0xFF, 0xFF, 0xFF));
+        // The first ring starts at bright blue and reaches bright red
+        assert_eq!(vga.color(32), rgb(0x00, 0x00, 0xFF));
+        assert_eq!(vga.color(40), rgb(0xFF, 0x00, 0x00));
+        assert_eq!(vga.color(255), rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn grayscale_spans_index_depth() {
+        let gray = Palette::grayscale(2);
+        assert_eq!(gray.color(0), rgb(0, 0, 0));
+        assert_eq!(gray.color(1), rgb(85, 85, 85));
+        assert_eq!(gray.color(3), rgb(255, 255, 255));
+        assert_eq!(gray.color(4), rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn from_data() {
+        let data = [0xFF, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+        // The repo's rgb mode reads blue first
+        let palette = Palette::from_data(
+            &data,
+            8,
+            PixelMode::Rgb.decoding_scheme(),
+            ChannelScaling::Replicate,
+            ByteOrder::default(),
+            2,
+        );
+        assert_eq!(
+            palette.colors(),
+            [rgb(0x33, 0x22, 0x11), rgb(0x66, 0x55, 0x44)]
+        );
+    }
+
+    #[test]
+    fn jasc() {
+        let palette = Palette::parse(b"JASC-PAL\r\n0100\r\n2\r\n255 0 0\r\n0 128 255\r\n").unwrap();
+        assert_eq!(palette.colors(), [rgb(255, 0, 0), rgb(0, 128, 255)]);
+    }
+
+    #[test]
+    fn gimp() {
+        let palette = Palette::parse(
+            b"GIMP Palette\nName: Test\nColumns: 4\n# comment\n  1   2   3\tFirst\n255 255 255 White\n",
+        )
+        .unwrap();
+        assert_eq!(palette.colors(), [rgb(1, 2, 3), rgb(255, 255, 255)]);
+    }
+
+    #[test]
+    fn act_and_raw() {
+        let mut act = vec![0u8; 772];
+        act[3..6].copy_from_slice(&[10, 20, 30]);
+        act[768..770].copy_from_slice(&2u16.to_be_bytes());
+        let palette = Palette::parse(&act).unwrap();
+        assert_eq!(palette.colors(), [rgb(0, 0, 0), rgb(10, 20, 30)]);
+
+        act[768..770].copy_from_slice(&0u16.to_be_bytes());
+        let palette = Palette::parse(&act).unwrap();
+        assert_eq!(palette.colors().len(), 256);
+        assert_eq!(palette.colors()[1], rgb(10, 20, 30));
+
+        let raw = Palette::parse(&[1, 2, 3, 4, 5, 6, 7]).unwrap();
+        assert_eq!(raw.colors(), [rgb(1, 2, 3), rgb(4, 5, 6)]);
+
+        assert_eq!(Palette::parse(&[1, 2]), Err(PaletteError::Empty));
+    }
+
+    #[test]
+    fn invalid_text_palettes() {
+        assert_eq!(
+            Palette::parse(b"JASC-PAL\n0100\nmany\n"),
+            Err(PaletteError::InvalidHeader)
+        );
+        assert_eq!(
+            Palette::parse(b"GIMP Palette\n1 2\n"),
+            Err(PaletteError::InvalidColor("1 2".to_owned()))
+        );
+    }
+}
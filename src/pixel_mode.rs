@@ -1,17 +1,51 @@
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display, str::FromStr};
 
+use super::block_format::BlockFormat;
+use super::custom_format::CustomFormat;
+use super::sample::SampleType;
 use super::shader::DecodingScheme;
+use super::yuv::YuvFormat;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PixelMode {
     Rgb,
     Bgr,
     Bpp8,
+    Mono1,
+    Mono1Inverted,
+    Gray2,
+    Gray4,
     G3b5r5g3,
     Rgba32,
     Rgb565,
     Argb8888,
     Rgba4444,
+    Rgb10a2,
+    Rgba16,
+    Rgba16f,
+    Rgba32f,
+    Gray16,
+    Gray32f,
+    Indexed1,
+    Indexed2,
+    Indexed4,
+    Indexed8,
+    Yuyv,
+    Uyvy,
+    Nv12,
+    Nv21,
+    I420,
+    Yv12,
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    Bc7,
+    Etc1,
+    Etc2,
+    Astc,
+    Custom(CustomFormat),
 }
 
 impl PixelMode {
@@ -19,261 +53,522 @@ impl PixelMode {
         Self::Rgb,
         Self::Bgr,
         Self::Bpp8,
+        Self::Mono1,
+        Self::Mono1Inverted,
+        Self::Gray2,
+        Self::Gray4,
         Self::G3b5r5g3,
         Self::Rgba32,
         Self::Rgb565,
         Self::Argb8888,
         Self::Rgba4444,
+        Self::Rgb10a2,
+        Self::Rgba16,
+        Self::Rgba16f,
+        Self::Rgba32f,
+        Self::Gray16,
+        Self::Gray32f,
+        Self::Indexed1,
+        Self::Indexed2,
+        Self::Indexed4,
+        Self::Indexed8,
+        Self::Yuyv,
+        Self::Uyvy,
+        Self::Nv12,
+        Self::Nv21,
+        Self::I420,
+        Self::Yv12,
+        Self::Bc1,
+        Self::Bc2,
+        Self::Bc3,
+        Self::Bc4,
+        Self::Bc5,
+        Self::Bc7,
+        Self::Etc1,
+        Self::Etc2,
+        Self::Astc,
     ];
 
-    pub fn decoding_scheme(&self) -> &'static DecodingScheme {
+    pub fn decoding_scheme(&self) -> &DecodingScheme {
         match &self {
             PixelMode::Rgb => &DecodingScheme {
-                red: [
-                    Some(23),
-                    Some(22),
-                    Some(21),
-                    Some(20),
-                    Some(19),
-                    Some(18),
-                    Some(17),
-                    Some(16),
-                ],
-                green: [
-                    Some(15),
-                    Some(14),
-                    Some(13),
-                    Some(12),
-                    Some(11),
-                    Some(10),
-                    Some(9),
-                    Some(8),
-                ],
-                blue: [
-                    Some(7),
-                    Some(6),
-                    Some(5),
-                    Some(4),
-                    Some(3),
-                    Some(2),
-                    Some(1),
-                    Some(0),
-                ],
+                red: Cow::Borrowed(&[16, 17, 18, 19, 20, 21, 22, 23]),
+                green: Cow::Borrowed(&[8, 9, 10, 11, 12, 13, 14, 15]),
+                blue: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
                 bits_per_pixel: 24,
             },
             PixelMode::Bgr => &DecodingScheme {
-                red: [
-                    Some(15),
-                    Some(14),
-                    Some(13),
-                    Some(12),
-                    Some(11),
-                    Some(10),
-                    Some(9),
-                    Some(8),
-                ],
-                green: [
-                    Some(23),
-                    Some(22),
-                    Some(21),
-                    Some(20),
-                    Some(19),
-                    Some(18),
-                    Some(17),
-                    Some(16),
-                ],
-                blue: [
-                    Some(31),
-                    Some(30),
-                    Some(29),
-                    Some(28),
-                    Some(27),
-                    Some(26),
-                    Some(25),
-                    Some(24),
-                ],
+                red: Cow::Borrowed(&[8, 9, 10, 11, 12, 13, 14, 15]),
+                green: Cow::Borrowed(&[16, 17, 18, 19, 20, 21, 22, 23]),
+                blue: Cow::Borrowed(&[24, 25, 26, 27, 28, 29, 30, 31]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
                 bits_per_pixel: 24,
             },
             PixelMode::Bpp8 => &DecodingScheme {
-                red: [
-                    Some(7),
-                    Some(6),
-                    Some(5),
-                    Some(4),
-                    Some(3),
-                    Some(2),
-                    Some(1),
-                    Some(0),
-                ],
-                green: [
-                    Some(7),
-                    Some(6),
-                    Some(5),
-                    Some(4),
-                    Some(3),
-                    Some(2),
-                    Some(1),
-                    Some(0),
-                ],
-                blue: [
-                    Some(7),
-                    Some(6),
-                    Some(5),
-                    Some(4),
-                    Some(3),
-                    Some(2),
-                    Some(1),
-                    Some(0),
-                ],
+                red: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7]),
+                green: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7]),
+                blue: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
                 bits_per_pixel: 8,
             },
+            PixelMode::Mono1 => &DecodingScheme {
+                red: Cow::Borrowed(&[0]),
+                green: Cow::Borrowed(&[0]),
+                blue: Cow::Borrowed(&[0]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 1,
+            },
+            PixelMode::Mono1Inverted => &DecodingScheme {
+                red: Cow::Borrowed(&[0]),
+                green: Cow::Borrowed(&[0]),
+                blue: Cow::Borrowed(&[0]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: true,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 1,
+            },
+            PixelMode::Gray2 => &DecodingScheme {
+                red: Cow::Borrowed(&[0, 1]),
+                green: Cow::Borrowed(&[0, 1]),
+                blue: Cow::Borrowed(&[0, 1]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 2,
+            },
+            PixelMode::Gray4 => &DecodingScheme {
+                red: Cow::Borrowed(&[0, 1, 2, 3]),
+                green: Cow::Borrowed(&[0, 1, 2, 3]),
+                blue: Cow::Borrowed(&[0, 1, 2, 3]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 4,
+            },
             PixelMode::G3b5r5g3 => &DecodingScheme {
-                red: [
-                    None,
-                    None,
-                    None,
-                    Some(12),
-                    Some(11),
-                    Some(10),
-                    Some(9),
-                    Some(8),
-                ],
-                green: [
-                    None,
-                    None,
-                    Some(2),
-                    Some(1),
-                    Some(0),
-                    Some(15),
-                    Some(14),
-                    Some(13),
-                ],
-                blue: [
-                    None,
-                    None,
-                    None,
-                    Some(7),
-                    Some(6),
-                    Some(5),
-                    Some(4),
-                    Some(3),
-                ],
+                red: Cow::Borrowed(&[8, 9, 10, 11, 12]),
+                green: Cow::Borrowed(&[13, 14, 15, 0, 1, 2]),
+                blue: Cow::Borrowed(&[3, 4, 5, 6, 7]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
                 bits_per_pixel: 16,
             },
             PixelMode::Rgba32 => &DecodingScheme {
-                red: [
-                    Some(31),
-                    Some(30),
-                    Some(29),
-                    Some(28),
-                    Some(27),
-                    Some(26),
-                    Some(25),
-                    Some(24),
-                ],
-                green: [
-                    Some(23),
-                    Some(22),
-                    Some(21),
-                    Some(20),
-                    Some(19),
-                    Some(18),
-                    Some(17),
-                    Some(16),
-                ],
-                blue: [
-                    Some(15),
-                    Some(14),
-                    Some(13),
-                    Some(12),
-                    Some(11),
-                    Some(10),
-                    Some(9),
-                    Some(8),
-                ],
+                red: Cow::Borrowed(&[24, 25, 26, 27, 28, 29, 30, 31]),
+                green: Cow::Borrowed(&[16, 17, 18, 19, 20, 21, 22, 23]),
+                blue: Cow::Borrowed(&[8, 9, 10, 11, 12, 13, 14, 15]),
+                alpha: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
                 bits_per_pixel: 32,
             },
             PixelMode::Rgb565 => &DecodingScheme {
-                red: [
-                    None,
-                    None,
-                    None,
-                    Some(15),
-                    Some(14),
-                    Some(13),
-                    Some(12),
-                    Some(11),
-                ],
-                green: [
-                    None,
-                    None,
-                    Some(10),
-                    Some(9),
-                    Some(8),
-                    Some(7),
-                    Some(6),
-                    Some(5),
-                ],
-                blue: [
-                    None,
-                    None,
-                    None,
-                    Some(4),
-                    Some(3),
-                    Some(2),
-                    Some(1),
-                    Some(0),
-                ],
+                red: Cow::Borrowed(&[11, 12, 13, 14, 15]),
+                green: Cow::Borrowed(&[5, 6, 7, 8, 9, 10]),
+                blue: Cow::Borrowed(&[0, 1, 2, 3, 4]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
                 bits_per_pixel: 16,
             },
             PixelMode::Argb8888 => &DecodingScheme {
-                red: [
-                    Some(23),
-                    Some(22),
-                    Some(21),
-                    Some(20),
-                    Some(19),
-                    Some(18),
-                    Some(17),
-                    Some(16),
-                ],
-                green: [
-                    Some(15),
-                    Some(14),
-                    Some(13),
-                    Some(12),
-                    Some(11),
-                    Some(10),
-                    Some(9),
-                    Some(8),
-                ],
-                blue: [
-                    Some(7),
-                    Some(6),
-                    Some(5),
-                    Some(4),
-                    Some(3),
-                    Some(2),
-                    Some(1),
-                    Some(0),
-                ],
+                red: Cow::Borrowed(&[16, 17, 18, 19, 20, 21, 22, 23]),
+                green: Cow::Borrowed(&[8, 9, 10, 11, 12, 13, 14, 15]),
+                blue: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7]),
+                alpha: Cow::Borrowed(&[24, 25, 26, 27, 28, 29, 30, 31]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
                 bits_per_pixel: 32,
             },
             PixelMode::Rgba4444 => &DecodingScheme {
-                red: [
-                    None,
-                    None,
-                    None,
-                    None,
-                    Some(15),
-                    Some(14),
-                    Some(13),
-                    Some(12),
-                ],
-                green: [None, None, None, None, Some(11), Some(10), Some(9), Some(8)],
-                blue: [None, None, None, None, Some(7), Some(6), Some(5), Some(4)],
+                red: Cow::Borrowed(&[12, 13, 14, 15]),
+                green: Cow::Borrowed(&[8, 9, 10, 11]),
+                blue: Cow::Borrowed(&[4, 5, 6, 7]),
+                alpha: Cow::Borrowed(&[0, 1, 2, 3]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 16,
+            },
+            PixelMode::Rgb10a2 => &DecodingScheme {
+                red: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]),
+                green: Cow::Borrowed(&[10, 11, 12, 13, 14, 15, 16, 17, 18, 19]),
+                blue: Cow::Borrowed(&[20, 21, 22, 23, 24, 25, 26, 27, 28, 29]),
+                alpha: Cow::Borrowed(&[30, 31]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 32,
+            },
+            PixelMode::Rgba16 => &DecodingScheme {
+                red: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+                green: Cow::Borrowed(&[
+                    16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+                ]),
+                blue: Cow::Borrowed(&[
+                    32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47,
+                ]),
+                alpha: Cow::Borrowed(&[
+                    48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63,
+                ]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 64,
+            },
+            PixelMode::Rgba16f => &DecodingScheme {
+                red: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+                green: Cow::Borrowed(&[
+                    16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+                ]),
+                blue: Cow::Borrowed(&[
+                    32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47,
+                ]),
+                alpha: Cow::Borrowed(&[
+                    48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63,
+                ]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Float,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 64,
+            },
+            PixelMode::Rgba32f => &DecodingScheme {
+                red: Cow::Borrowed(&[
+                    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21,
+                    22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+                ]),
+                green: Cow::Borrowed(&[
+                    32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51,
+                    52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63,
+                ]),
+                blue: Cow::Borrowed(&[
+                    64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83,
+                    84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95,
+                ]),
+                alpha: Cow::Borrowed(&[
+                    96, 97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111,
+                    112, 113, 114, 115, 116, 117, 118, 119, 120, 121, 122, 123, 124, 125, 126, 127,
+                ]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Float,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 128,
+            },
+            PixelMode::Gray16 => &DecodingScheme {
+                red: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+                green: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+                blue: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 16,
+            },
+            PixelMode::Gray32f => &DecodingScheme {
+                red: Cow::Borrowed(&[
+                    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21,
+                    22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+                ]),
+                green: Cow::Borrowed(&[
+                    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21,
+                    22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+                ]),
+                blue: Cow::Borrowed(&[
+                    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21,
+                    22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+                ]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Float,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 32,
+            },
+            PixelMode::Indexed1 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[0]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 1,
+            },
+            PixelMode::Indexed2 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[0, 1]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 2,
+            },
+            PixelMode::Indexed4 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[0, 1, 2, 3]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 4,
+            },
+            PixelMode::Indexed8 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: None,
+                bits_per_pixel: 8,
+            },
+            PixelMode::Yuyv => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: Some(YuvFormat::Yuyv),
+                block: None,
                 bits_per_pixel: 16,
             },
+            PixelMode::Uyvy => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: Some(YuvFormat::Uyvy),
+                block: None,
+                bits_per_pixel: 16,
+            },
+            PixelMode::Nv12 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: Some(YuvFormat::Nv12),
+                block: None,
+                bits_per_pixel: 8,
+            },
+            PixelMode::Nv21 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: Some(YuvFormat::Nv21),
+                block: None,
+                bits_per_pixel: 8,
+            },
+            PixelMode::I420 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: Some(YuvFormat::I420),
+                block: None,
+                bits_per_pixel: 8,
+            },
+            PixelMode::Yv12 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: Some(YuvFormat::Yv12),
+                block: None,
+                bits_per_pixel: 8,
+            },
+            PixelMode::Bc1 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Bc1),
+                bits_per_pixel: 4,
+            },
+            PixelMode::Bc2 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Bc2),
+                bits_per_pixel: 8,
+            },
+            PixelMode::Bc3 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Bc3),
+                bits_per_pixel: 8,
+            },
+            PixelMode::Bc4 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Bc4),
+                bits_per_pixel: 4,
+            },
+            PixelMode::Bc5 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Bc5),
+                bits_per_pixel: 8,
+            },
+            PixelMode::Bc7 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Bc7),
+                bits_per_pixel: 8,
+            },
+            PixelMode::Etc1 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Etc1),
+                bits_per_pixel: 4,
+            },
+            PixelMode::Etc2 => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Etc2Rgb),
+                bits_per_pixel: 4,
+            },
+            PixelMode::Astc => &DecodingScheme {
+                red: Cow::Borrowed(&[]),
+                green: Cow::Borrowed(&[]),
+                blue: Cow::Borrowed(&[]),
+                alpha: Cow::Borrowed(&[]),
+                index: Cow::Borrowed(&[]),
+                sample_type: SampleType::Unorm,
+                inverted: false,
+                yuv: None,
+                block: Some(BlockFormat::Astc),
+                bits_per_pixel: 8,
+            },
+            PixelMode::Custom(custom_format) => custom_format.decoding_scheme(),
         }
     }
 }
@@ -281,14 +576,73 @@ impl PixelMode {
 impl Display for PixelMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
+            PixelMode::Custom(custom_format) => custom_format.format(),
             PixelMode::Rgb => "rgb",
             PixelMode::Bgr => "bgr",
             PixelMode::Bpp8 => "8bpp",
+            PixelMode::Mono1 => "1bpp",
+            PixelMode::Mono1Inverted => "1bpp inverted",
+            PixelMode::Gray2 => "2bpp",
+            PixelMode::Gray4 => "4bpp",
             PixelMode::G3b5r5g3 => "G3B5R5G3 (AW RAS)",
             PixelMode::Rgba32 => "rgba32",
             PixelMode::Rgb565 => "rgb565",
             PixelMode::Argb8888 => "argb8888",
             PixelMode::Rgba4444 => "rgba4444",
+            PixelMode::Rgb10a2 => "rgb10a2",
+            PixelMode::Rgba16 => "rgba16",
+            PixelMode::Rgba16f => "rgba16f",
+            PixelMode::Rgba32f => "rgba32f",
+            PixelMode::Gray16 => "gray16",
+            PixelMode::Gray32f => "gray32f",
+            PixelMode::Indexed1 => "indexed1",
+            PixelMode::Indexed2 => "indexed2",
+            PixelMode::Indexed4 => "indexed4",
+            PixelMode::Indexed8 => "indexed8",
+            PixelMode::Yuyv => "yuyv",
+            PixelMode::Uyvy => "uyvy",
+            PixelMode::Nv12 => "nv12",
+            PixelMode::Nv21 => "nv21",
+            PixelMode::I420 => "i420",
+            PixelMode::Yv12 => "yv12",
+            PixelMode::Bc1 => "bc1",
+            PixelMode::Bc2 => "bc2",
+            PixelMode::Bc3 => "bc3",
+            PixelMode::Bc4 => "bc4",
+            PixelMode::Bc5 => "bc5",
+            PixelMode::Bc7 => "bc7",
+            PixelMode::Etc1 => "etc1",
+            PixelMode::Etc2 => "etc2",
+            PixelMode::Astc => "astc 4x4",
+        })
+    }
+}
+
+impl FromStr for PixelMode {
+    type Err = String;
+
+    /// Accepts the displayed name of a mode, just its first word, or a custom
+    /// format.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let built_in = PixelMode::ALL.iter().find(|mode| {
+            let name = mode.to_string();
+            let short_name = name.split_whitespace().next().unwrap_or_default();
+            name.eq_ignore_ascii_case(s) || short_name.eq_ignore_ascii_case(s)
+        });
+
+        if let Some(mode) = built_in {
+            return Ok(mode.clone());
+        }
+
+        s.parse().map(PixelMode::Custom).map_err(|why| {
+            let names = PixelMode::ALL
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "unknown pixel mode {s:?}, expected one of: {names}, or a custom format ({why})"
+            )
         })
     }
 }
@@ -0,0 +1,70 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Order of pixels narrower than a byte within each byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum PixelOrder {
+    /// The first pixel is in the most significant bits
+    #[default]
+    MsbFirst,
+    /// The first pixel is in the least significant bits
+    LsbFirst,
+}
+
+impl PixelOrder {
+    pub const ALL: &'static [Self] = &[Self::MsbFirst, Self::LsbFirst];
+
+    /// Value of the `pixel_order` uniform, matching the `PIXEL_ORDER_*`
+    /// constants in `shader.wgsl`.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            PixelOrder::MsbFirst => 0,
+            PixelOrder::LsbFirst => 1,
+        }
+    }
+
+    /// Bit, relative to the start of the data, where the pixel at
+    /// `pixel_index` begins. Pixel order only matters when whole pixels pack
+    /// into a byte, wider pixels are always laid out one after another.
+    pub fn pixel_bit_index(&self, pixel_index: u64, bits_per_pixel: u32) -> u64 {
+        let bits_per_pixel = u64::from(bits_per_pixel);
+        let packs_into_bytes = bits_per_pixel < 8 && 8u64.is_multiple_of(bits_per_pixel);
+
+        match self {
+            PixelOrder::LsbFirst if packs_into_bytes => {
+                let pixels_per_byte = 8 / bits_per_pixel;
+                let byte = pixel_index / pixels_per_byte;
+                let position = pixel_index % pixels_per_byte;
+                byte * 8 + 8 - (position + 1) * bits_per_pixel
+            }
+            _ => pixel_index * bits_per_pixel,
+        }
+    }
+}
+
+impl Display for PixelOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PixelOrder::MsbFirst => "msb-first",
+            PixelOrder::LsbFirst => "lsb-first",
+        })
+    }
+}
+
+impl FromStr for PixelOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PixelOrder::ALL
+            .iter()
+            .find(|order| order.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names = PixelOrder::ALL
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("unknown pixel order {s:?}, expected one of: {names}")
+            })
+    }
+}
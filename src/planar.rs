@@ -0,0 +1,379 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::byte_order::ByteOrder;
+use super::preview::DataWindow;
+use super::shader::DecodingScheme;
+
+/// How the planes of a planar image are interleaved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum PlaneInterleave {
+    /// Each line holds that line of every plane in turn, like Amiga ILBM
+    #[default]
+    Line,
+    /// Planes alternate every 16 bits, like the Atari ST's screen
+    Word,
+    /// Each plane is a whole image, one after the other
+    Image,
+}
+
+impl PlaneInterleave {
+    pub const ALL: &'static [Self] = &[Self::Line, Self::Word, Self::Image];
+}
+
+impl Display for PlaneInterleave {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PlaneInterleave::Line => "line",
+            PlaneInterleave::Word => "word",
+            PlaneInterleave::Image => "image",
+        })
+    }
+}
+
+impl FromStr for PlaneInterleave {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PlaneInterleave::ALL
+            .iter()
+            .find(|interleave| interleave.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                format!("unknown plane interleave {s:?}, expected one of: line, word, image")
+            })
+    }
+}
+
+/// Splits each pixel of a `DecodingScheme` across planes. The pixel's bits
+/// are cut into one field per plane, most significant first, and
+/// `plane_order` says which plane each field is read from. A single plane is
+/// the usual chunky layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanarLayout {
+    planes: u32,
+    plane_order: [u8; PlanarLayout::MAX_PLANES],
+    pub interleave: PlaneInterleave,
+    /// Bytes from the start of one plane to the next when interleaving whole
+    /// images, or 0 to split the file evenly.
+    pub plane_size: u64,
+}
+
+impl Default for PlanarLayout {
+    fn default() -> Self {
+        Self {
+            planes: 1,
+            plane_order: Self::IN_ORDER,
+            interleave: PlaneInterleave::default(),
+            plane_size: 0,
+        }
+    }
+}
+
+impl PlanarLayout {
+    pub const MAX_PLANES: usize = 8;
+    const IN_ORDER: [u8; Self::MAX_PLANES] = [0, 1, 2, 3, 4, 5, 6, 7];
+
+    /// Lays out `planes` planes, with the pixel's fields read from the planes
+    /// listed in `plane_order`, or from each plane in turn if it is empty.
+    pub fn new(
+        planes: u32,
+        plane_order: &[u8],
+        interleave: PlaneInterleave,
+        plane_size: u64,
+    ) -> Result<Self, PlanarError> {
+        if planes == 0 || planes as usize > Self::MAX_PLANES {
+            return Err(PlanarError::InvalidPlaneCount(planes));
+        }
+
+        let mut order = Self::IN_ORDER;
+        if !plane_order.is_empty() {
+            if plane_order.len() != planes as usize {
+                return Err(PlanarError::WrongOrderLength {
+                    planes,
+                    found: plane_order.len(),
+                });
+            }
+            for (field, &plane) in plane_order.iter().enumerate() {
+                if u32::from(plane) >= planes {
+                    return Err(PlanarError::PlaneOutOfRange { plane, planes });
+                }
+                if plane_order[..field].contains(&plane) {
+                    return Err(PlanarError::DuplicatePlane(plane));
+                }
+                order[field] = plane;
+            }
+        }
+
+        Ok(Self {
+            planes,
+            plane_order: order,
+            interleave,
+            plane_size,
+        })
+    }
+
+    pub fn planes(&self) -> u32 {
+        self.planes
+    }
+
+    pub fn is_planar(&self) -> bool {
+        self.planes > 1
+    }
+
+    /// The plane the `field`th field of each pixel is read from.
+    pub fn plane_for_field(&self, field: u32) -> u32 {
+        u32::from(self.plane_order[field as usize % Self::MAX_PLANES])
+    }
+
+    /// Value of the `plane_order` uniform: the plane of each field in 4 bits,
+    /// the first field lowest.
+    pub fn shader_plane_order(&self) -> u32 {
+        self.plane_order
+            .iter()
+            .rev()
+            .fold(0, |packed, &plane| (packed << 4) | u32::from(plane))
+    }
+
+    /// Checks that the scheme's pixels split evenly into the planes. YUV
+    /// schemes have their own plane layouts, so they can't be split further.
+    pub fn check(&self, decoding_scheme: &DecodingScheme) -> Result<(), PlanarError> {
+        let bits_per_pixel = decoding_scheme.bits_per_pixel;
+        if self.is_planar() && decoding_scheme.yuv.is_some() {
+            Err(PlanarError::Yuv)
+        } else if self.is_planar() && decoding_scheme.block.is_some() {
+            Err(PlanarError::Block)
+        } else if bits_per_pixel.is_multiple_of(self.planes) {
+            Ok(())
+        } else {
+            Err(PlanarError::UnevenPlanes {
+                bits_per_pixel,
+                planes: self.planes,
+            })
+        }
+    }
+
+    /// Bits each plane holds of a pixel.
+    pub fn bits_per_plane_pixel(&self, bits_per_pixel: u32) -> u32 {
+        (bits_per_pixel / self.planes).max(1)
+    }
+
+    /// Bits of one plane before the next plane's bits start, or 0 when each
+    /// plane is a whole image.
+    pub fn chunk_bits(&self, bits_per_pixel: u32, target_width: u32) -> u32 {
+        if !self.is_planar() {
+            return 0;
+        }
+
+        match self.interleave {
+            PlaneInterleave::Line => target_width * self.bits_per_plane_pixel(bits_per_pixel),
+            PlaneInterleave::Word => 16,
+            PlaneInterleave::Image => 0,
+        }
+    }
+
+    /// Bits a line of the image takes up, counting only the first plane when
+    /// planes are separate images.
+    pub fn bits_per_line(&self, bits_per_pixel: u32, target_width: u32) -> u64 {
+        let bits_per_pixel = match self.interleave {
+            PlaneInterleave::Image if self.is_planar() => self.bits_per_plane_pixel(bits_per_pixel),
+            _ => bits_per_pixel,
+        };
+        u64::from(bits_per_pixel) * u64::from(target_width)
+    }
+
+    /// Bits of a `file_len` byte file that lines are counted over, which is
+    /// just the first plane when planes are separate images.
+    pub fn line_data_bits(&self, file_len: usize) -> u64 {
+        match self.interleave {
+            PlaneInterleave::Image if self.is_planar() => self.plane_stride(file_len) * 8,
+            _ => file_len as u64 * 8,
+        }
+    }
+
+    /// Bytes from one plane to the next, for planes that are whole images.
+    pub fn plane_stride(&self, file_len: usize) -> u64 {
+        match self.plane_size {
+            0 => file_len as u64 / u64::from(self.planes),
+            plane_size => plane_size,
+        }
+    }
+
+    /// Reads the data for `plane_bits` bits of every plane from `start_bit`.
+    /// Planes that are whole images are read one after the other into the
+    /// window, each padded to a whole number of words.
+    pub fn read(
+        &self,
+        data: &[u8],
+        byte_order: ByteOrder,
+        start_bit: u64,
+        plane_bits: u64,
+    ) -> DataWindow {
+        let start_byte = start_bit / 8;
+        let bit_offset = (start_bit % 8) as u32;
+        // Schemes may read bits past the end of the last pixel, so keep a word spare
+        let plane_len = ((plane_bits + u64::from(bit_offset)).div_ceil(32) + 1) as usize * 4;
+
+        if !self.is_planar() || self.interleave != PlaneInterleave::Image {
+            let len = plane_len * self.planes as usize;
+            let (bytes, lead_bits) = byte_order.read(data, start_byte, len);
+            return DataWindow::new(&bytes, bit_offset + lead_bits);
+        }
+
+        let plane_stride = self.plane_stride(data.len());
+        let mut bytes = Vec::with_capacity(plane_len * self.planes as usize);
+        for plane in 0..u64::from(self.planes) {
+            let (plane_bytes, lead_bits) =
+                byte_order.read(data, start_byte + plane * plane_stride, plane_len);
+            let plane_bytes = plane_bytes
+                .get(lead_bits as usize / 8..)
+                .unwrap_or_default();
+            let plane_bytes = plane_bytes.get(..plane_len).unwrap_or(plane_bytes);
+
+            bytes.extend_from_slice(plane_bytes);
+            bytes.resize(bytes.len() + plane_len - plane_bytes.len(), 0);
+        }
+
+        DataWindow {
+            plane_stride: plane_len as u32 * 8,
+            ..DataWindow::new(&bytes, bit_offset)
+        }
+    }
+}
+
+/// Parses a list of plane numbers separated by spaces or commas.
+pub fn parse_plane_order(s: &str) -> Result<Vec<u8>, PlanarError> {
+    s.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|plane| !plane.is_empty())
+        .map(|plane| {
+            plane
+                .parse()
+                .map_err(|_| PlanarError::InvalidPlane(plane.to_owned()))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanarError {
+    InvalidPlaneCount(u32),
+    InvalidPlane(String),
+    PlaneOutOfRange { plane: u8, planes: u32 },
+    DuplicatePlane(u8),
+    WrongOrderLength { planes: u32, found: usize },
+    UnevenPlanes { bits_per_pixel: u32, planes: u32 },
+    Yuv,
+    Block,
+}
+
+impl Display for PlanarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanarError::InvalidPlaneCount(planes) => write!(
+                f,
+                "{planes} planes is not supported, the most is {}",
+                PlanarLayout::MAX_PLANES
+            ),
+            PlanarError::InvalidPlane(s) => write!(f, "Invalid plane {s:?}"),
+            PlanarError::PlaneOutOfRange { plane, planes } => {
+                write!(f, "Plane {plane} is out of range for {planes} planes")
+            }
+            PlanarError::DuplicatePlane(plane) => {
+                write!(f, "Plane {plane} is given more than once")
+            }
+            PlanarError::WrongOrderLength { planes, found } => {
+                write!(f, "Plane order lists {found} planes, expected {planes}")
+            }
+            PlanarError::UnevenPlanes {
+                bits_per_pixel,
+                planes,
+            } => write!(
+                f,
+                "{bits_per_pixel} bit pixels can't be split into {planes} planes"
+            ),
+            PlanarError::Yuv => write!(f, "YUV modes can't be split into planes"),
+            PlanarError::Block => write!(f, "Block-compressed modes can't be split into planes"),
+        }
+    }
+}
+
+impl std::error::Error for PlanarError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_mode::PixelMode;
+
+    #[test]
+    fn plane_order() {
+        let layout = PlanarLayout::new(4, &[3, 2, 1, 0], PlaneInterleave::Line, 0).unwrap();
+        assert_eq!(layout.plane_for_field(0), 3);
+        assert_eq!(layout.plane_for_field(3), 0);
+        assert_eq!(layout.shader_plane_order() & 0xFFFF, 0x0123);
+
+        let in_order = PlanarLayout::new(3, &[], PlaneInterleave::Line, 0).unwrap();
+        assert_eq!(in_order.plane_for_field(1), 1);
+
+        assert_eq!(parse_plane_order("3, 2 1,0"), Ok(vec![3, 2, 1, 0]));
+        assert_eq!(
+            parse_plane_order("1 x"),
+            Err(PlanarError::InvalidPlane("x".to_owned()))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let layout = |planes, order: &[u8]| PlanarLayout::new(planes, order, Default::default(), 0);
+
+        assert_eq!(layout(0, &[]), Err(PlanarError::InvalidPlaneCount(0)));
+        assert_eq!(layout(9, &[]), Err(PlanarError::InvalidPlaneCount(9)));
+        assert_eq!(
+            layout(2, &[0]),
+            Err(PlanarError::WrongOrderLength {
+                planes: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            layout(2, &[0, 2]),
+            Err(PlanarError::PlaneOutOfRange {
+                plane: 2,
+                planes: 2
+            })
+        );
+        assert_eq!(layout(2, &[1, 1]), Err(PlanarError::DuplicatePlane(1)));
+        assert_eq!(
+            layout(3, &[])
+                .unwrap()
+                .check(PixelMode::Bpp8.decoding_scheme()),
+            Err(PlanarError::UnevenPlanes {
+                bits_per_pixel: 8,
+                planes: 3
+            })
+        );
+        assert_eq!(
+            layout(2, &[])
+                .unwrap()
+                .check(PixelMode::Yuyv.decoding_scheme()),
+            Err(PlanarError::Yuv)
+        );
+        assert_eq!(
+            layout(2, &[])
+                .unwrap()
+                .check(PixelMode::Bc1.decoding_scheme()),
+            Err(PlanarError::Block)
+        );
+    }
+
+    #[test]
+    fn image_planes_are_read_separately() {
+        let data = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+        let layout = PlanarLayout::new(2, &[], PlaneInterleave::Image, 0).unwrap();
+
+        let window = layout.read(&data, ByteOrder::default(), 8, 16);
+        assert_eq!(window.plane_stride, 64);
+        assert_eq!(window.bit_offset, 0);
+        assert_eq!(
+            window.words,
+            [0x22334455, 0x66000000, 0x55660000, 0x00000000]
+        );
+    }
+}
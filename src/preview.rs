@@ -1,13 +1,44 @@
-use crate::shader::DecodingScheme;
+use iced::Point;
+
+use crate::alpha_mode::AlphaMode;
+use crate::block_format::BLOCK_SIZE;
+use crate::byte_order::ByteOrder;
+use crate::channel_scaling::ChannelScaling;
+use crate::decoder::{Decoder, PixelInspection};
+use crate::file_data::FileData;
+use crate::format_guess::{self, FormatGuess};
+use crate::palette::Palette;
+use crate::pixel_order::PixelOrder;
+use crate::planar::PlanarLayout;
+use crate::row_stride::{RowStride, RowStrideError};
+use crate::sample::ToneSettings;
+use crate::selection::{self, Selection, SelectionError};
+use crate::shader::{DecodingScheme, SelectionRect};
+use crate::tiling::Tiling;
+use crate::width_detection::{self, WidthCandidate};
+use crate::yuv::{Frame420, YuvSettings};
 
 use super::shader::FragmentShaderProgram;
-use std::sync::Arc;
+
+/// Image pixels across and lines down a screen pixel covers at most when
+/// zoomed out.
+pub const MAX_ZOOM_OUT: u32 = 8;
+
+/// Most bits handed to the shader at once: wgpu's default limit on the size
+/// of a storage buffer binding, 128 MiB, less some room for the words
+/// windows are padded with. Views that would need more only get the lines
+/// that fit, and show the rest as past the end of the data.
+const MAX_BUFFER_BITS: u64 = ((128 << 20) - (4 << 10)) * 8;
 
 pub struct Preview {
     start_bit: u64,
     frame_height: u32,
     frame_width: u32,
-    file_data: Arc<Vec<u8>>,
+    file_data: FileData,
+    byte_order: ByteOrder,
+    /// Row stride asked for, which the layout may not allow
+    row_stride: RowStride,
+    selection: Option<Selection>,
     pub program: FragmentShaderProgram,
 }
 
@@ -18,7 +49,10 @@ impl Default for Preview {
             frame_width: 0,
             start_bit: 0,
             program: FragmentShaderProgram::new(),
-            file_data: Arc::new(Vec::<u8>::new()),
+            file_data: FileData::default(),
+            byte_order: ByteOrder::default(),
+            row_stride: RowStride::UNPADDED,
+            selection: None,
         }
     }
 }
@@ -40,6 +74,138 @@ impl Preview {
         self.program.grid()
     }
 
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.program.set_alpha_mode(alpha_mode);
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.program.alpha_mode()
+    }
+
+    pub fn set_channel_scaling(&mut self, channel_scaling: ChannelScaling) {
+        self.program.set_channel_scaling(channel_scaling);
+    }
+
+    pub fn channel_scaling(&self) -> ChannelScaling {
+        self.program.channel_scaling()
+    }
+
+    pub fn set_byte_order(&mut self, byte_order: ByteOrder) {
+        self.byte_order = byte_order;
+        self.update_program_buffer();
+    }
+
+    pub fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    pub fn set_planar_layout(&mut self, planar_layout: PlanarLayout) {
+        self.program.set_planar_layout(planar_layout);
+        self.update_program_buffer();
+    }
+
+    pub fn planar_layout(&self) -> PlanarLayout {
+        self.program.planar_layout()
+    }
+
+    pub fn set_tiling(&mut self, tiling: Tiling) {
+        self.program.set_tiling(tiling);
+        self.go_to_line(self.current_line());
+    }
+
+    pub fn tiling(&self) -> Tiling {
+        self.program.tiling()
+    }
+
+    pub fn set_yuv(&mut self, yuv: YuvSettings) {
+        self.program.set_yuv(yuv);
+        self.update_program_buffer();
+    }
+
+    pub fn yuv(&self) -> YuvSettings {
+        self.program.yuv()
+    }
+
+    pub fn set_tone(&mut self, tone: ToneSettings) {
+        self.program.set_tone(tone);
+    }
+
+    pub fn tone(&self) -> ToneSettings {
+        self.program.tone()
+    }
+
+    /// Decodes on the CPU with the settings the shader decodes with.
+    fn decoder(&self) -> Decoder<'_> {
+        Decoder {
+            decoding_scheme: self.decoding_scheme(),
+            channel_scaling: self.channel_scaling(),
+            pixel_order: self.pixel_order(),
+            byte_order: self.byte_order,
+            planar_layout: self.planar_layout(),
+            tiling: self.tiling(),
+            row_stride: self.applied_row_stride(),
+            palette: self.palette(),
+            yuv: self.yuv(),
+            tone: self.tone(),
+        }
+    }
+
+    /// The smallest and largest color channel values in the lines on screen,
+    /// for normalizing them.
+    pub fn value_range(&self) -> Option<(f32, f32)> {
+        let decoder = self.decoder();
+        let remaining_lines = self.total_lines().saturating_sub(self.current_line());
+        let lines = (u64::from(self.frame_height) * u64::from(self.zoom_out())
+            / u64::from(self.scale().max(1)))
+        .min(remaining_lines)
+        .max(1);
+
+        decoder.file_value_range(
+            &self.file_data,
+            self.start_bit,
+            self.target_width(),
+            u32::try_from(lines).unwrap_or(u32::MAX),
+        )
+    }
+
+    /// Where the pixel at `x`, `y` of the view is read from and what it
+    /// decodes to.
+    pub fn inspect_pixel(&self, x: u32, y: u32) -> PixelInspection {
+        self.decoder()
+            .inspect_pixel(self.file_data(), self.start_bit, self.target_width(), x, y)
+    }
+
+    /// Where the 4:2:0 frame being shown is, for 4:2:0 schemes.
+    fn yuv_frame(&self) -> Option<Frame420> {
+        let format = self
+            .decoding_scheme()
+            .yuv
+            .filter(|format| format.is_420())?;
+        Some(Frame420::locate(
+            format,
+            self.file_data.len(),
+            self.start_bit,
+            self.target_width(),
+            self.yuv().frame_height,
+        ))
+    }
+
+    pub fn set_pixel_order(&mut self, pixel_order: PixelOrder) {
+        self.program.set_pixel_order(pixel_order);
+    }
+
+    pub fn pixel_order(&self) -> PixelOrder {
+        self.program.pixel_order()
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.program.set_palette(palette);
+    }
+
+    pub fn palette(&self) -> &Palette {
+        self.program.palette()
+    }
+
     pub fn set_start_bit(&mut self, offset: u64) {
         self.start_bit = offset;
         self.update_program_buffer();
@@ -54,25 +220,249 @@ impl Preview {
     }
 
     pub fn bits_per_line(&self) -> u64 {
-        u64::from(self.target_width()) * u64::from(self.decoding_scheme().bits_per_pixel)
+        if let Some(block) = self.decoding_scheme().block {
+            return block.bits_per_line(self.target_width());
+        }
+        let row_stride = self.applied_row_stride();
+        if row_stride.is_padded() {
+            return u64::from(row_stride.bits());
+        }
+        self.planar_layout()
+            .bits_per_line(self.decoding_scheme().bits_per_pixel, self.target_width())
     }
 
     pub fn scale(&self) -> u32 {
         self.program.scale()
     }
 
+    /// Sets how many screen pixels across and down show each image pixel,
+    /// leaving any zoom below 1x.
     pub fn set_scale(&mut self, scale: u32) {
-        self.program.set_scale(scale)
+        self.program.set_scale(scale);
+        self.set_zoom_out(1);
+    }
+
+    pub fn zoom_out(&self) -> u32 {
+        self.program.zoom_out()
+    }
+
+    /// Zooms out below 1x, each screen pixel covering `zoom_out` image
+    /// pixels across and lines down, up to `MAX_ZOOM_OUT`.
+    pub fn set_zoom_out(&mut self, zoom_out: u32) {
+        let zoom_out = zoom_out.clamp(1, MAX_ZOOM_OUT);
+        if zoom_out == self.zoom_out() {
+            return;
+        }
+        if zoom_out > 1 {
+            self.program.set_scale(1);
+        }
+        self.program.set_zoom_out(zoom_out);
+        // More lines fit on screen
+        self.update_program_buffer();
+    }
+
+    /// Zooms in by `steps` when positive, or out when negative, one scale at
+    /// a time above 1x and one image pixel per screen pixel at a time below.
+    pub fn zoom(&mut self, steps: i32) {
+        for _ in 0..steps.unsigned_abs() {
+            let scale = self.scale().max(1);
+            let zoom_out = self.zoom_out();
+            if steps > 0 && zoom_out > 1 {
+                self.set_zoom_out(zoom_out - 1);
+            } else if steps > 0 {
+                self.set_scale(scale.saturating_add(1));
+            } else if scale > 1 {
+                self.set_scale(scale - 1);
+            } else {
+                self.set_zoom_out(zoom_out + 1);
+            }
+        }
+    }
+
+    /// Zooms like `zoom`, keeping the part of the image at `position` in the
+    /// view where it is.
+    pub fn zoom_at(&mut self, position: Point, steps: i32) {
+        let (x_before, y_before) = self.program.image_offset(position);
+        self.zoom(steps);
+        let (x_after, y_after) = self.program.image_offset(position);
+
+        self.scroll(
+            i64::from(x_before) - i64::from(x_after),
+            i64::from(y_before) - i64::from(y_after),
+        );
+    }
+
+    /// Scrolls right by `columns` and down by `lines`, or left and up for
+    /// negative amounts, as far as the image goes.
+    pub fn scroll(&mut self, columns: i64, lines: i64) {
+        if columns != 0 {
+            let x_scroll =
+                (i64::from(self.x_scroll()) + columns).clamp(0, i64::from(self.target_width()));
+            self.set_x_scroll(x_scroll as u32);
+        }
+
+        if lines != 0 {
+            let current_line = self.current_line();
+            let line = if lines > 0 {
+                current_line.saturating_add(lines.unsigned_abs())
+            } else {
+                current_line.saturating_sub(lines.unsigned_abs())
+            };
+            self.go_to_line(line);
+        }
     }
 
     pub fn set_target_width(&mut self, width: u32) {
-        self.program.set_target_width(width)
+        self.program.set_target_width(width);
+        self.update_program_selection();
+        if self.yuv_frame().is_some()
+            || self.decoding_scheme().block.is_some()
+            || self.row_stride.is_padded()
+            || self.zoom_out() > 1
+        {
+            // Chroma planes, rows of blocks and padded rows move with the
+            // width, as does whether the stride holds a row, and so do the
+            // lines a zoomed out view fits
+            self.update_program_buffer();
+        }
     }
 
     pub fn target_width(&self) -> u32 {
         self.program.target_width()
     }
 
+    /// The widths up to `max_width` the data from the start bit most likely
+    /// has, best first. Block-compressed data has no rows of pixels to
+    /// compare.
+    pub fn detect_widths(&self, max_width: u32) -> Vec<WidthCandidate> {
+        if self.decoding_scheme().block.is_some() {
+            return vec![];
+        }
+
+        let bits_per_pixel = self
+            .planar_layout()
+            .bits_per_plane_pixel(self.decoding_scheme().bits_per_pixel);
+        width_detection::detect_widths(&self.file_data, self.start_bit, bits_per_pixel, max_width)
+    }
+
+    /// Guesses the pixel format and width of the data from the start bit,
+    /// keeping the current line length as one of the widths tried. The work
+    /// happens when the future is polled, so it can be moved off the UI
+    /// thread.
+    pub fn guess_formats(
+        &self,
+        max_width: u32,
+    ) -> impl std::future::Future<Output = Vec<FormatGuess>> + Send + 'static {
+        let file_data = self.file_data.clone();
+        let start_bit = self.start_bit;
+        let bits_per_line = self.bits_per_line();
+        async move { format_guess::guess_formats(&file_data, start_bit, bits_per_line, max_width) }
+    }
+
+    pub fn set_row_stride(&mut self, row_stride: RowStride) {
+        self.row_stride = row_stride;
+        self.update_program_buffer();
+    }
+
+    /// Why rows can't be padded with the current layout, when they can't.
+    pub fn row_stride_error(&self) -> Option<RowStrideError> {
+        self.row_stride
+            .validate(
+                self.decoding_scheme(),
+                &self.planar_layout(),
+                &self.tiling(),
+                self.target_width(),
+            )
+            .err()
+    }
+
+    /// The row stride rows are read with, which is unpadded when the layout
+    /// doesn't allow padding.
+    fn applied_row_stride(&self) -> RowStride {
+        match self.row_stride_error() {
+            Some(_) => RowStride::UNPADDED,
+            None => self.row_stride,
+        }
+    }
+
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+        self.update_program_selection();
+    }
+
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    /// The pixels of `selection` decoded, row by row, along with the width
+    /// and height of what's left of it within the image width.
+    pub fn selected_pixels(
+        &self,
+        selection: &Selection,
+    ) -> Result<(u32, u32, Vec<Pixel>), SelectionError> {
+        let target_width = self.target_width();
+        let x = selection.x.min(target_width);
+        let width = (selection.x + selection.width).min(target_width) - x;
+        if width == 0 || selection.height == 0 {
+            return Err(SelectionError::Empty);
+        }
+
+        let pixels = self.decoder().decode_file_lines(
+            &self.file_data,
+            selection.start_bit,
+            target_width,
+            selection.y + selection.height,
+        );
+        let pixels = pixels
+            .chunks(target_width.max(1) as usize)
+            .skip(selection.y as usize)
+            .flat_map(|row| &row[x as usize..(x + width) as usize])
+            .copied()
+            .collect();
+        Ok((width, selection.height, pixels))
+    }
+
+    /// The bytes each row of `selection` is read from, one row after the
+    /// other.
+    pub fn selected_bytes(&self, selection: &Selection) -> Result<Vec<u8>, SelectionError> {
+        selection::validate_rows(
+            self.decoding_scheme(),
+            &self.planar_layout(),
+            &self.tiling(),
+        )?;
+
+        let ranges = selection.byte_ranges(
+            self.bits_per_line(),
+            self.decoding_scheme().bits_per_pixel,
+            self.byte_order,
+        );
+        let file_len = self.file_data.len() as u64;
+        Ok(ranges
+            .into_iter()
+            .flat_map(|range| {
+                let start = range.start.min(file_len) as usize;
+                let end = range.end.min(file_len) as usize;
+                &self.file_data[start..end]
+            })
+            .copied()
+            .collect())
+    }
+
+    /// Highlights the selection in the view when the view starts a whole
+    /// number of lines away from it.
+    fn update_program_selection(&mut self) {
+        let rect = self.selection.and_then(|selection| {
+            let y = selection.view_y(self.start_bit, self.bits_per_line())?;
+            Some(SelectionRect {
+                x: selection.x,
+                y: i32::try_from(y).ok()?,
+                width: selection.width,
+                height: selection.height,
+            })
+        });
+        self.program.set_selection(rect);
+    }
+
     pub fn set_frame_height(&mut self, frame_height: u32) {
         self.frame_height = frame_height;
     }
@@ -82,7 +472,12 @@ impl Preview {
     }
 
     pub fn total_lines(&self) -> u64 {
-        let bits = (self.file_data.len() * 8) as u64;
+        // A single frame that fills the data ends where its chroma starts
+        if let Some(frame) = self.yuv_frame().filter(|_| self.yuv().frame_height == 0) {
+            return frame.lines;
+        }
+
+        let bits = self.planar_layout().line_data_bits(self.file_data.len());
         bits.checked_div(self.bits_per_line()).unwrap_or(0)
     }
 
@@ -103,60 +498,234 @@ impl Preview {
         } else {
             line
         };
+        // Tiles and blocks only line up when starting on a line of them
+        let line_step = match self.decoding_scheme().block {
+            Some(_) => u64::from(BLOCK_SIZE),
+            None => self
+                .tiling()
+                .line_step(self.decoding_scheme().bits_per_pixel),
+        };
+        let line = line - line % line_step;
 
         let new_offset = (line * self.bits_per_line()) + remainder;
         self.set_start_bit(new_offset);
     }
 
-    pub fn set_file_data(&mut self, data: Arc<Vec<u8>>) {
+    pub fn set_file_data(&mut self, data: FileData) {
         self.file_data = data;
         self.update_program_buffer();
     }
 
     pub fn clear(&mut self) {
-        self.set_file_data(Arc::new(vec![]));
+        self.set_file_data(FileData::default());
     }
 
     pub fn set_decoding_scheme(&mut self, decoding_scheme: &DecodingScheme) {
         self.program.set_decoding_scheme(decoding_scheme.clone());
+        self.update_program_buffer();
     }
 
     pub fn decoding_scheme(&self) -> &DecodingScheme {
         self.program.decoding_scheme()
     }
 
+    /// Image pixels the view can show: a frame's worth, or when zoomed out
+    /// every line that fits on screen, as far as `MAX_BUFFER_BITS` goes.
+    fn frame_pixels(&self) -> u64 {
+        let width = u64::from(self.target_width().max(1));
+        let pixels = match self.zoom_out() {
+            1 => u64::from(self.frame_height) * u64::from(self.frame_width),
+            zoom_out => u64::from(self.frame_height) * u64::from(zoom_out) * width,
+        };
+
+        // Blocks are read in whole rows of blocks, so keep a row spare
+        let max_lines =
+            (MAX_BUFFER_BITS / self.buffer_bits_per_line()).saturating_sub(u64::from(BLOCK_SIZE));
+        pixels.min(max_lines * width)
+    }
+
+    /// Bits of the buffer handed to the shader that each line of the image
+    /// takes up.
+    fn buffer_bits_per_line(&self) -> u64 {
+        let width = u64::from(self.target_width().max(1));
+        if self.yuv_frame().is_some() {
+            // A line's worth of each of the three planes
+            return width * 8 * 3;
+        }
+        if self.decoding_scheme().block.is_some() {
+            // Blocks are decoded to a word per pixel
+            return width.next_multiple_of(u64::from(BLOCK_SIZE)) * 32;
+        }
+
+        let planar_layout = self.planar_layout();
+        let plane_bits = match self.applied_row_stride().bits() {
+            0 => {
+                width
+                    * u64::from(
+                        planar_layout.bits_per_plane_pixel(self.decoding_scheme().bits_per_pixel),
+                    )
+            }
+            row_stride => u64::from(row_stride),
+        };
+        (plane_bits * u64::from(planar_layout.planes())).max(1)
+    }
+
     fn update_program_buffer(&mut self) {
+        self.program
+            .set_row_stride(self.applied_row_stride().bits());
+        self.update_program_selection();
+
+        if let Some(frame) = self.yuv_frame() {
+            let luma_bits = self.frame_pixels() * 8;
+            let window = frame.read(&self.file_data, self.byte_order, luma_bits);
+
+            self.program.set_bit_offset(window.bit_offset);
+            self.program.set_plane_stride(window.plane_stride);
+            self.program.set_line_parity(window.line_parity);
+            self.program.set_buffer(window.words);
+            return;
+        }
+
+        if let Some(block) = self.decoding_scheme().block {
+            let pixels = self.frame_pixels();
+            let lines = pixels.div_ceil(u64::from(self.target_width().max(1)));
+            let window = block.read(
+                &self.file_data,
+                self.byte_order,
+                self.start_bit,
+                self.target_width(),
+                u32::try_from(lines).unwrap_or(u32::MAX),
+            );
+
+            self.program.set_bit_offset(window.bit_offset);
+            self.program.set_plane_stride(window.plane_stride);
+            self.program.set_line_parity(window.line_parity);
+            self.program.set_buffer(window.words);
+            return;
+        }
+
         let bits_per_pixel = self.decoding_scheme().bits_per_pixel;
 
-        let start_byte = self.start_bit / 8;
-        let bit_offset = (self.start_bit % 8) as u32;
+        let planar_layout = self.planar_layout();
 
-        let start = start_byte as usize;
-        let max_size = (((self.frame_height * self.frame_width * bits_per_pixel) + 1) / 8) as usize;
+        let pixels = self.frame_pixels();
+        let plane_bits = match self.applied_row_stride().bits() {
+            0 => pixels * u64::from(planar_layout.bits_per_plane_pixel(bits_per_pixel)),
+            // Every line on screen, padding and all
+            row_stride => {
+                pixels.div_ceil(u64::from(self.target_width().max(1))) * u64::from(row_stride)
+            }
+        };
 
-        let buf_beginning = self.file_data.get(start..).unwrap_or_default();
-        let buf_limited = buf_beginning.get(..max_size).unwrap_or(buf_beginning);
+        let window =
+            planar_layout.read(&self.file_data, self.byte_order, self.start_bit, plane_bits);
 
-        let program_buffer = buf_limited
-            .chunks(4)
-            .map(|bytes| {
-                let a = bytes.first().unwrap_or(&0);
-                let b = bytes.get(1).unwrap_or(&0);
-                let c = bytes.get(2).unwrap_or(&0);
-                let d = bytes.get(3).unwrap_or(&0);
+        self.program.set_bit_offset(window.bit_offset);
+        self.program.set_plane_stride(window.plane_stride);
+        self.program.set_line_parity(window.line_parity);
+        self.program.set_buffer(window.words);
+    }
+}
 
-                (u32::from(*a) << 24) | (u32::from(*b) << 16) | (u32::from(*c) << 8) | u32::from(*d)
-            })
-            .collect::<Vec<u32>>();
+/// The part of the file handed to the shader.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataWindow {
+    pub words: Vec<u32>,
+    /// Bit of `words` the image starts at.
+    pub bit_offset: u32,
+    /// Bits from one plane to the next, when planes are whole images read
+    /// one after the other.
+    pub plane_stride: u32,
+    /// 1 when the first line is the second of a pair of 4:2:0 lines that
+    /// share chroma.
+    pub line_parity: u32,
+}
 
-        self.program.set_bit_offset(bit_offset);
-        self.program.set_buffer(program_buffer);
+impl DataWindow {
+    pub fn new(bytes: &[u8], bit_offset: u32) -> Self {
+        Self {
+            words: pack_words(bytes),
+            bit_offset,
+            plane_stride: 0,
+            line_parity: 0,
+        }
     }
 }
 
-#[derive(Copy, Clone)]
+/// Packs bytes big-endian into the `u32` words the shader reads from, padding
+/// the last word with zeroes.
+pub fn pack_words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks(4)
+        .map(|bytes| {
+            let a = bytes.first().unwrap_or(&0);
+            let b = bytes.get(1).unwrap_or(&0);
+            let c = bytes.get(2).unwrap_or(&0);
+            let d = bytes.get(3).unwrap_or(&0);
+
+            (u32::from(*a) << 24) | (u32::from(*b) << 16) | (u32::from(*c) << 8) | u32::from(*d)
+        })
+        .collect::<Vec<u32>>()
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Pixel {
     pub red: u8,
     pub green: u8,
     pub blue: u8,
+    pub alpha: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_mode::PixelMode;
+
+    /// A view of the `width` wide image the size of a 4K screen. The
+    /// screen size is set last, since it doesn't read the buffer again.
+    fn view(mode: &PixelMode, width: u32, zoom_out: u32) -> Preview {
+        let mut preview = Preview::default();
+        preview.set_decoding_scheme(mode.decoding_scheme());
+        preview.set_target_width(width);
+        preview.set_zoom_out(zoom_out);
+        preview.set_frame_width(3840);
+        preview.set_frame_height(2160);
+        preview
+    }
+
+    #[test]
+    fn buffers_fit_the_gpu_limit() {
+        // The widest image width detection goes up to, at 1x and zoomed all
+        // the way out
+        for mode in PixelMode::ALL {
+            for zoom_out in [1, MAX_ZOOM_OUT] {
+                let preview = view(mode, 2048, zoom_out);
+                let lines = preview.frame_pixels().div_ceil(2048);
+                assert!(lines > 0, "{mode} at 1/{zoom_out}");
+                assert!(
+                    lines * preview.buffer_bits_per_line() <= MAX_BUFFER_BITS,
+                    "{mode} at 1/{zoom_out}"
+                );
+            }
+        }
+
+        // 128 bit pixels zoomed out get only the lines that fit
+        let preview = view(&PixelMode::Rgba32f, 2048, MAX_ZOOM_OUT);
+        assert!(preview.frame_pixels() < 2160 * u64::from(MAX_ZOOM_OUT) * 2048);
+    }
+
+    #[test]
+    fn selections_are_clipped_to_the_image_width() {
+        let preview = view(&PixelMode::Rgb, 16, 1);
+
+        let partly_past = Selection::spanning(0, (14, 0), (17, 1));
+        let (width, height, _) = preview.selected_pixels(&partly_past).unwrap();
+        assert_eq!((width, height), (2, 2));
+
+        let past = Selection::spanning(0, (16, 0), (19, 1));
+        assert!(matches!(
+            preview.selected_pixels(&past),
+            Err(SelectionError::Empty)
+        ));
+    }
 }
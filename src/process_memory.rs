@@ -0,0 +1,199 @@
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+use super::file_data::FileData;
+
+/// A region of a process's address space, as listed in `/proc/<pid>/maps`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub end: u64,
+    /// Permissions like `r-xp`
+    pub permissions: String,
+    /// The mapped file or a pseudo name like `[heap]`, empty for anonymous
+    /// maps
+    pub name: String,
+}
+
+/// Most bytes of a region read at once. Regions are read again on every
+/// refresh, and can be far bigger than is worth copying that often, so big
+/// ones are read a window at a time.
+pub const MAX_READ_LEN: u64 = 64 << 20;
+
+impl MemoryRegion {
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// The offsets into the region of the window read from `offset` on, at
+    /// most `MAX_READ_LEN` bytes long.
+    pub fn window(&self, offset: u64) -> Range<u64> {
+        let start = offset.min(self.len());
+        start..self.len().min(start + MAX_READ_LEN)
+    }
+
+    /// Whether the region is too big to read in one go.
+    pub fn is_windowed(&self) -> bool {
+        self.len() > MAX_READ_LEN
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.permissions.starts_with('r')
+    }
+}
+
+impl Display for MemoryRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:x}-{:x} {} {}",
+            self.start, self.end, self.permissions, self.name
+        )
+    }
+}
+
+/// Parses one line of `/proc/<pid>/maps`, e.g.
+/// `7f12a000-7f12c000 rw-p 00000000 00:00 0    [heap]`.
+fn parse_maps_line(line: &str) -> Option<MemoryRegion> {
+    let mut fields = line.split_whitespace();
+    let (start, end) = fields.next()?.split_once('-')?;
+    let start = u64::from_str_radix(start, 16).ok()?;
+    let end = u64::from_str_radix(end, 16).ok()?;
+    let permissions = fields.next()?.to_owned();
+    // Skip the offset, device and inode
+    fields.nth(2)?;
+    let name = fields.collect::<Vec<_>>().join(" ");
+
+    (start < end).then_some(MemoryRegion {
+        start,
+        end,
+        permissions,
+        name,
+    })
+}
+
+/// The readable regions of process `pid`'s memory.
+pub fn regions(pid: u32) -> io::Result<Vec<MemoryRegion>> {
+    let maps = fs::read_to_string(format!("/proc/{pid}/maps"))?;
+    Ok(maps
+        .lines()
+        .filter_map(parse_maps_line)
+        .filter(MemoryRegion::is_readable)
+        .collect())
+}
+
+/// Bytes read between progress reports.
+const READ_CHUNK: u64 = 1 << 20;
+
+/// Reads the window of `region` of process `pid`'s memory from `offset` on,
+/// as `MemoryRegion::window` finds it, through `/proc/<pid>/mem`. Pages that
+/// can't be read, like guard pages, read as zeroes. `progress` works like it
+/// does for `FileData::load`.
+pub fn read_region(
+    pid: u32,
+    region: &MemoryRegion,
+    offset: u64,
+    mut progress: impl FnMut(u64, Option<u64>) -> bool,
+) -> io::Result<FileData> {
+    let mut mem = File::open(format!("/proc/{pid}/mem"))?;
+    let window = region.window(offset);
+    let len = window.end - window.start;
+    let mut bytes = vec![0; len as usize];
+
+    for (chunk_index, chunk) in bytes.chunks_mut(READ_CHUNK as usize).enumerate() {
+        let chunk_offset = chunk_index as u64 * READ_CHUNK;
+        let address = region.start + window.start + chunk_offset;
+        let result = mem
+            .seek(SeekFrom::Start(address))
+            .and_then(|_| mem.read_exact(chunk));
+        // A chunk that can't be read is all zeroes, even when some of it was
+        // read before the read failed
+        if result.is_err() {
+            chunk.fill(0);
+        }
+
+        let read = chunk_offset + chunk.len() as u64;
+        if !progress(read, Some(len)) {
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "loading was cancelled",
+            ));
+        }
+    }
+
+    Ok(FileData::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_lines() {
+        let heap = parse_maps_line("55d0c000-55d2d000 rw-p 00000000 00:00 0          [heap]");
+        assert_eq!(
+            heap,
+            Some(MemoryRegion {
+                start: 0x55d0c000,
+                end: 0x55d2d000,
+                permissions: "rw-p".to_owned(),
+                name: "[heap]".to_owned(),
+            })
+        );
+
+        let anonymous = parse_maps_line("7f00a000-7f00b000 ---p 00000000 00:00 0").unwrap();
+        assert_eq!(anonymous.name, "");
+        assert!(!anonymous.is_readable());
+
+        let spaced = parse_maps_line("1000-2000 r--p 00001000 08:01 42 /tmp/a file").unwrap();
+        assert_eq!(spaced.name, "/tmp/a file");
+        assert_eq!(spaced.len(), 0x1000);
+
+        assert_eq!(parse_maps_line("garbage"), None);
+    }
+
+    #[test]
+    fn reads_own_memory() {
+        let pid = std::process::id();
+        let Ok(regions) = regions(pid) else {
+            // Not a system with /proc
+            return;
+        };
+        let stack = regions.iter().find(|region| region.name == "[stack]");
+        if let Some(stack) = stack {
+            let data = read_region(pid, stack, 0, |_, _| true).unwrap();
+            assert_eq!(data.len() as u64, stack.len().min(MAX_READ_LEN));
+        }
+    }
+
+    #[test]
+    fn big_regions_are_read_in_windows() {
+        let region = MemoryRegion {
+            start: 0x1000,
+            end: 0x1000 + 2 * MAX_READ_LEN,
+            permissions: "rw-p".to_owned(),
+            name: String::new(),
+        };
+        assert_eq!(region.window(0), 0..MAX_READ_LEN);
+        assert_eq!(
+            region.window(MAX_READ_LEN + 5),
+            MAX_READ_LEN + 5..2 * MAX_READ_LEN
+        );
+        assert_eq!(
+            region.window(3 * MAX_READ_LEN),
+            2 * MAX_READ_LEN..2 * MAX_READ_LEN
+        );
+        assert!(region.is_windowed());
+
+        let small = MemoryRegion {
+            end: 0x3000,
+            ..region
+        };
+        assert_eq!(small.window(0), 0..0x2000);
+        assert!(!small.is_windowed());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::planar::PlanarLayout;
+use crate::shader::DecodingScheme;
+use crate::tiling::{TileOrder, Tiling};
+
+/// Unit a row stride is given in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum StrideUnit {
+    #[default]
+    Bytes,
+    Bits,
+}
+
+impl StrideUnit {
+    pub const ALL: &'static [Self] = &[Self::Bytes, Self::Bits];
+
+    /// `value` of this unit in bits, or `None` when that doesn't fit.
+    pub fn bits(&self, value: u32) -> Option<u32> {
+        match self {
+            StrideUnit::Bytes => value.checked_mul(8),
+            StrideUnit::Bits => Some(value),
+        }
+    }
+}
+
+impl Display for StrideUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StrideUnit::Bytes => "bytes",
+            StrideUnit::Bits => "bits",
+        })
+    }
+}
+
+impl FromStr for StrideUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StrideUnit::ALL
+            .iter()
+            .find(|unit| unit.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown stride unit {s:?}, expected one of: bytes, bits"))
+    }
+}
+
+/// Distance from the start of one row of pixels to the next. Framebuffers
+/// and GPU surfaces often pad their rows, to 64 or 256 bytes say, so rows
+/// start further apart than the width of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RowStride {
+    bits: u32,
+}
+
+impl RowStride {
+    /// Rows that follow straight on from each other, with no padding.
+    pub const UNPADDED: Self = Self { bits: 0 };
+
+    /// Rows starting `bits` apart, or following straight on for 0.
+    pub fn new(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Bits from one row to the next, or 0 when rows follow straight on.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn is_padded(&self) -> bool {
+        self.bits != 0
+    }
+
+    /// Checks rows of the scheme `target_width` pixels wide can be padded.
+    /// Planes, tiles, blocks and 4:2:0 frames have their own layouts that
+    /// rows aren't laid out by, and a stride shorter than a row would
+    /// overlap the rows.
+    pub fn validate(
+        &self,
+        decoding_scheme: &DecodingScheme,
+        planar_layout: &PlanarLayout,
+        tiling: &Tiling,
+        target_width: u32,
+    ) -> Result<(), RowStrideError> {
+        let row_bits = u64::from(target_width) * u64::from(decoding_scheme.bits_per_pixel);
+        if !self.is_padded() {
+            Ok(())
+        } else if planar_layout.is_planar() {
+            Err(RowStrideError::Planar)
+        } else if tiling.order() != TileOrder::Linear {
+            Err(RowStrideError::Tiled)
+        } else if decoding_scheme.block.is_some() {
+            Err(RowStrideError::Block)
+        } else if decoding_scheme.yuv.is_some_and(|format| format.is_420()) {
+            Err(RowStrideError::Yuv420)
+        } else if u64::from(self.bits) < row_bits {
+            Err(RowStrideError::ShorterThanRow { row_bits })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Bit where the pixel `pixel_index` pixels into an image `target_width`
+    /// wide begins, given where pixels begin in rows that follow straight on.
+    /// Rows that are padded start `bits` apart instead, the same way
+    /// `row_bit_index` in `shader.wgsl` finds them.
+    pub fn pixel_bit_index(
+        &self,
+        pixel_index: u64,
+        target_width: u32,
+        unpadded_bit_index: impl Fn(u64) -> u64,
+    ) -> u64 {
+        if !self.is_padded() {
+            return unpadded_bit_index(pixel_index);
+        }
+        let width = u64::from(target_width.max(1));
+        (pixel_index / width) * u64::from(self.bits) + unpadded_bit_index(pixel_index % width)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowStrideError {
+    Planar,
+    Tiled,
+    Block,
+    Yuv420,
+    /// The stride is shorter than the `row_bits` bits of a row of pixels
+    ShorterThanRow {
+        row_bits: u64,
+    },
+}
+
+impl Display for RowStrideError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let layout = match self {
+            RowStrideError::Planar => "planar pixels",
+            RowStrideError::Tiled => "tiled pixels",
+            RowStrideError::Block => "block-compressed modes",
+            RowStrideError::Yuv420 => "4:2:0 YUV modes",
+            RowStrideError::ShorterThanRow { row_bits } => {
+                return write!(
+                    f,
+                    "The row stride is shorter than a row of pixels, which takes {row_bits} bits"
+                );
+            }
+        };
+        write!(f, "Rows of {layout} can't be padded")
+    }
+}
+
+impl std::error::Error for RowStrideError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_mode::PixelMode;
+
+    #[test]
+    fn padded_rows() {
+        let linear = |pixel_index: u64| pixel_index * 16;
+        assert_eq!(RowStride::UNPADDED.pixel_bit_index(13, 10, linear), 13 * 16);
+
+        // 10 pixels of 16 bits padded to 32 bytes
+        let stride = RowStride::new(StrideUnit::Bytes.bits(32).unwrap());
+        assert_eq!(stride.pixel_bit_index(3, 10, linear), 3 * 16);
+        assert_eq!(stride.pixel_bit_index(13, 10, linear), 256 + 3 * 16);
+        assert_eq!(stride.pixel_bit_index(20, 10, linear), 512);
+    }
+
+    #[test]
+    fn padding_needs_plain_rows() {
+        let stride = RowStride::new(2048);
+        let rgb = PixelMode::Rgb.decoding_scheme();
+        let planar = PlanarLayout::default();
+        let linear = Tiling::default();
+
+        assert_eq!(stride.validate(rgb, &planar, &linear, 64), Ok(()));
+        assert_eq!(
+            stride.validate(PixelMode::Bc1.decoding_scheme(), &planar, &linear, 64),
+            Err(RowStrideError::Block)
+        );
+        assert_eq!(
+            stride.validate(PixelMode::Nv12.decoding_scheme(), &planar, &linear, 64),
+            Err(RowStrideError::Yuv420)
+        );
+        assert_eq!(
+            stride.validate(
+                rgb,
+                &planar,
+                &Tiling::new(TileOrder::Tiles, 8, 8).unwrap(),
+                64
+            ),
+            Err(RowStrideError::Tiled)
+        );
+        assert_eq!(
+            RowStride::UNPADDED.validate(PixelMode::Bc1.decoding_scheme(), &planar, &linear, 64),
+            Ok(())
+        );
+        assert_eq!("Bits".parse(), Ok(StrideUnit::Bits));
+    }
+
+    #[test]
+    fn strides_hold_a_row() {
+        let rgb = PixelMode::Rgb.decoding_scheme();
+        let planar = PlanarLayout::default();
+        let linear = Tiling::default();
+
+        // 100 pixels of 24 bits take 2400 bits
+        assert_eq!(
+            RowStride::new(2400).validate(rgb, &planar, &linear, 100),
+            Ok(())
+        );
+        assert_eq!(
+            RowStride::new(2399).validate(rgb, &planar, &linear, 100),
+            Err(RowStrideError::ShorterThanRow { row_bits: 2400 })
+        );
+        assert_eq!(
+            RowStride::new(1).validate(rgb, &planar, &linear, 100),
+            Err(RowStrideError::ShorterThanRow { row_bits: 2400 })
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use std::{fmt::Display, str::FromStr};
+
+/// How the bits of a channel are read as a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum SampleType {
+    /// Unsigned integers, 0 to the largest value being black to white
+    #[default]
+    Unorm,
+    /// Two's complement integers, negative values being black
+    Snorm,
+    /// IEEE floats of 32 or 16 bits, or the unsigned 11 and 10 bit floats
+    /// of packed HDR formats
+    Float,
+}
+
+impl SampleType {
+    pub const ALL: &'static [Self] = &[Self::Unorm, Self::Snorm, Self::Float];
+
+    /// Value of the `sample_type` uniform, matching the `SAMPLE_TYPE_*`
+    /// constants in `shader.wgsl`.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            SampleType::Unorm => 0,
+            SampleType::Snorm => 1,
+            SampleType::Float => 2,
+        }
+    }
+
+    /// Whether channels of `depth` bits can be read as this type.
+    pub fn supports_depth(&self, depth: u32) -> bool {
+        match self {
+            SampleType::Unorm | SampleType::Snorm => (1..=32).contains(&depth),
+            SampleType::Float => matches!(depth, 10 | 11 | 16 | 32),
+        }
+    }
+
+    /// The number `depth` bit `raw` stands for, the same way `channel_value`
+    /// in `shader.wgsl` reads it. Infinities and NaNs read as the largest
+    /// value of their sign.
+    pub fn value(&self, raw: u32, depth: u32) -> f32 {
+        if depth == 0 {
+            return 0.0;
+        }
+
+        match self {
+            SampleType::Unorm => raw as f32,
+            SampleType::Snorm => {
+                let shift = 32 - depth;
+                ((raw << shift) as i32 >> shift) as f32
+            }
+            SampleType::Float => match depth {
+                32 if (raw >> 23) & 0xFF == 0xFF => {
+                    if raw >> 31 == 0 {
+                        f32::MAX
+                    } else {
+                        f32::MIN
+                    }
+                }
+                32 => f32::from_bits(raw),
+                16 => small_float(raw, 10, true),
+                11 => small_float(raw, 6, false),
+                _ => small_float(raw, 5, false),
+            },
+        }
+    }
+
+    /// The value white stands for, before any normalization.
+    pub fn white(&self, depth: u32) -> f32 {
+        match self {
+            SampleType::Unorm => (2f32.powi(depth as i32) - 1.0).max(1.0),
+            SampleType::Snorm => (2f32.powi(depth as i32 - 1) - 1.0).max(1.0),
+            SampleType::Float => 1.0,
+        }
+    }
+}
+
+/// Decodes a float with a 5 bit exponent, like halves and the 11 and 10 bit
+/// floats of R11G11B10.
+fn small_float(raw: u32, mantissa_bits: u32, signed: bool) -> f32 {
+    let mantissa = raw & ((1 << mantissa_bits) - 1);
+    let exponent = (raw >> mantissa_bits) & 0x1F;
+    let negative = signed && (raw >> (mantissa_bits + 5)) & 1 == 1;
+
+    let fraction = mantissa as f32 / (1 << mantissa_bits) as f32;
+    let magnitude = match exponent {
+        0 => fraction * 2f32.powi(-14),
+        0x1F => 2f32.powi(16) * (1.0 - 1.0 / (2 << mantissa_bits) as f32),
+        _ => (1.0 + fraction) * 2f32.powi(exponent as i32 - 15),
+    };
+
+    if negative {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+impl Display for SampleType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SampleType::Unorm => "unorm",
+            SampleType::Snorm => "snorm",
+            SampleType::Float => "float",
+        })
+    }
+}
+
+impl FromStr for SampleType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SampleType::ALL
+            .iter()
+            .find(|sample_type| sample_type.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names = SampleType::ALL
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("unknown sample type {s:?}, expected one of: {names}")
+            })
+    }
+}
+
+/// How channel values are brought into the range that can be shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ToneMapping {
+    /// Values past white are clipped
+    #[default]
+    Clamp,
+    /// Values from the minimum to the maximum are stretched from black to
+    /// white
+    Normalize,
+    /// Reinhard's x / (1 + x), which rolls off highlights instead of
+    /// clipping them
+    Reinhard,
+}
+
+impl ToneMapping {
+    pub const ALL: &'static [Self] = &[Self::Clamp, Self::Normalize, Self::Reinhard];
+
+    /// Value of the `tone_mapping` uniform, matching the `TONE_MAPPING_*`
+    /// constants in `shader.wgsl`.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            ToneMapping::Clamp => 0,
+            ToneMapping::Normalize => 1,
+            ToneMapping::Reinhard => 2,
+        }
+    }
+}
+
+impl Display for ToneMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ToneMapping::Clamp => "clamp",
+            ToneMapping::Normalize => "normalize",
+            ToneMapping::Reinhard => "reinhard",
+        })
+    }
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ToneMapping::ALL
+            .iter()
+            .find(|tone_mapping| tone_mapping.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names = ToneMapping::ALL
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("unknown tone mapping {s:?}, expected one of: {names}")
+            })
+    }
+}
+
+/// Settings for showing channels that are wider than 8 bits, signed or
+/// floating point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneSettings {
+    pub tone_mapping: ToneMapping,
+    /// Stops color channels are brightened by before tone mapping
+    pub exposure: f32,
+    /// Value shown as black when normalizing
+    pub min: f32,
+    /// Value shown as white when normalizing
+    pub max: f32,
+}
+
+impl Default for ToneSettings {
+    fn default() -> Self {
+        Self {
+            tone_mapping: ToneMapping::Clamp,
+            exposure: 0.0,
+            min: 0.0,
+            max: 1.0,
+        }
+    }
+}
+
+impl ToneSettings {
+    /// Whether a channel is mapped through these settings. Unsigned channels
+    /// of up to 8 bits are otherwise scaled by the channel scaling.
+    pub fn maps(&self, sample_type: SampleType, depth: u32) -> bool {
+        sample_type != SampleType::Unorm
+            || depth > 8
+            || self.tone_mapping != ToneMapping::Clamp
+            || self.exposure != 0.0
+    }
+
+    /// Maps the `depth` bit `raw` channel value to 8 bits, the same way
+    /// `map_channel` in `shader.wgsl` does. Alpha is only brought into range.
+    pub fn map(&self, sample_type: SampleType, raw: u32, depth: u32, is_color: bool) -> u8 {
+        let value = sample_type.value(raw, depth);
+
+        let mut x = if is_color && self.tone_mapping == ToneMapping::Normalize {
+            (value - self.min) / (self.max - self.min)
+        } else {
+            value / sample_type.white(depth)
+        };
+
+        if is_color {
+            x *= self.exposure.exp2();
+            if self.tone_mapping == ToneMapping::Reinhard {
+                x = x.max(0.0) / (1.0 + x.max(0.0));
+            }
+        }
+
+        (x.clamp(0.0, 1.0) * 255.0 + 0.5).floor() as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_values() {
+        assert_eq!(SampleType::Unorm.value(1023, 10), 1023.0);
+        assert_eq!(SampleType::Snorm.value(0xFFFF, 16), -1.0);
+        assert_eq!(SampleType::Snorm.value(0x7FFF, 16), 32767.0);
+
+        assert_eq!(SampleType::Float.value(1.5f32.to_bits(), 32), 1.5);
+        assert_eq!(
+            SampleType::Float.value(f32::INFINITY.to_bits(), 32),
+            f32::MAX
+        );
+        // Halves: 1.0, -2.0, the largest and the smallest subnormal
+        assert_eq!(SampleType::Float.value(0x3C00, 16), 1.0);
+        assert_eq!(SampleType::Float.value(0xC000, 16), -2.0);
+        assert_eq!(SampleType::Float.value(0x7BFF, 16), 65504.0);
+        assert_eq!(SampleType::Float.value(0x0001, 16), 2f32.powi(-24));
+        // 11 and 10 bit floats have no sign, and 1.0 is the bias alone
+        assert_eq!(SampleType::Float.value(15 << 6, 11), 1.0);
+        assert_eq!(SampleType::Float.value(15 << 5 | 16, 10), 1.5);
+
+        assert!(SampleType::Float.supports_depth(11));
+        assert!(!SampleType::Float.supports_depth(8));
+    }
+
+    #[test]
+    fn tone_mapping() {
+        let clamp = ToneSettings::default();
+        assert!(!clamp.maps(SampleType::Unorm, 8));
+        assert!(clamp.maps(SampleType::Unorm, 16));
+        assert_eq!(clamp.map(SampleType::Unorm, 0xFFFF, 16, true), 255);
+        assert_eq!(clamp.map(SampleType::Unorm, 0x8000, 16, true), 128);
+        assert_eq!(
+            clamp.map(SampleType::Float, 2.0f32.to_bits(), 32, true),
+            255
+        );
+        assert_eq!(clamp.map(SampleType::Snorm, 0x8000, 16, true), 0);
+
+        let normalize = ToneSettings {
+            tone_mapping: ToneMapping::Normalize,
+            min: 1000.0,
+            max: 3000.0,
+            ..ToneSettings::default()
+        };
+        assert_eq!(normalize.map(SampleType::Unorm, 1000, 16, true), 0);
+        assert_eq!(normalize.map(SampleType::Unorm, 2000, 16, true), 128);
+        assert_eq!(normalize.map(SampleType::Unorm, 4000, 16, true), 255);
+        // Alpha isn't normalized
+        assert_eq!(normalize.map(SampleType::Unorm, 0xFFFF, 16, false), 255);
+
+        let reinhard = ToneSettings {
+            tone_mapping: ToneMapping::Reinhard,
+            exposure: 1.0,
+            ..ToneSettings::default()
+        };
+        // 0.5, doubled by the exposure, then halved by x / (1 + x)
+        assert_eq!(
+            reinhard.map(SampleType::Float, 0.5f32.to_bits(), 32, true),
+            128
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::{fmt::Display, ops::Range};
+
+use crate::byte_order::ByteOrder;
+use crate::planar::PlanarLayout;
+use crate::shader::DecodingScheme;
+use crate::tiling::{TileOrder, Tiling};
+
+/// A rectangle of pixels dragged out on the image. It keeps the start bit of
+/// the image it was dragged out on, so it stays on the same data as the view
+/// scrolls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub start_bit: u64,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Selection {
+    /// The rectangle with pixels `from` and `to` of the image starting at
+    /// `start_bit` in opposite corners.
+    pub fn spanning(start_bit: u64, from: (u32, u32), to: (u32, u32)) -> Self {
+        Self {
+            start_bit,
+            x: from.0.min(to.0),
+            y: from.1.min(to.1),
+            width: from.0.abs_diff(to.0) + 1,
+            height: from.1.abs_diff(to.1) + 1,
+        }
+    }
+
+    /// The line of an image starting at `start_bit` the rectangle's top is
+    /// on, if the image starts a whole number of `bits_per_line` long lines
+    /// away from where the rectangle was dragged out.
+    pub fn view_y(&self, start_bit: u64, bits_per_line: u64) -> Option<i64> {
+        let bits_per_line = i128::from(bits_per_line);
+        let offset = i128::from(self.start_bit) - i128::from(start_bit);
+        if bits_per_line == 0 || offset % bits_per_line != 0 {
+            return None;
+        }
+        i64::try_from(offset / bits_per_line + i128::from(self.y)).ok()
+    }
+
+    /// The bytes each row of the rectangle is read from, for rows
+    /// `bits_per_line` apart. Ranges are widened to whole units of reordered
+    /// bytes, so that the bytes keep their order when saved.
+    pub fn byte_ranges(
+        &self,
+        bits_per_line: u64,
+        bits_per_pixel: u32,
+        byte_order: ByteOrder,
+    ) -> Vec<Range<u64>> {
+        let unit = if byte_order.is_swapped() {
+            byte_order.unit_size.bytes() as u64
+        } else {
+            1
+        };
+        let bits_per_pixel = u64::from(bits_per_pixel);
+
+        (self.y..self.y + self.height)
+            .map(|y| {
+                let first = self.start_bit
+                    + u64::from(y) * bits_per_line
+                    + u64::from(self.x) * bits_per_pixel;
+                let end = first + u64::from(self.width) * bits_per_pixel;
+                let start_byte = first / 8;
+                let end_byte = end.div_ceil(8);
+                start_byte - start_byte % unit..end_byte.div_ceil(unit) * unit
+            })
+            .collect()
+    }
+}
+
+/// Checks each row of pixels of the scheme is read from a single run of
+/// bytes, so rows can be cut out of the file. Planes, tiles, blocks and
+/// 4:2:0 frames spread rows out.
+pub fn validate_rows(
+    decoding_scheme: &DecodingScheme,
+    planar_layout: &PlanarLayout,
+    tiling: &Tiling,
+) -> Result<(), SelectionError> {
+    if planar_layout.is_planar() {
+        Err(SelectionError::Planar)
+    } else if tiling.order() != TileOrder::Linear {
+        Err(SelectionError::Tiled)
+    } else if decoding_scheme.block.is_some() {
+        Err(SelectionError::Block)
+    } else if decoding_scheme.yuv.is_some_and(|format| format.is_420()) {
+        Err(SelectionError::Yuv420)
+    } else {
+        Ok(())
+    }
+}
+
+/// What a selection can be saved as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionExport {
+    /// The decoded pixels as a PNG
+    Png,
+    /// The bytes of each row, one after the other
+    Bytes,
+}
+
+impl SelectionExport {
+    pub fn title(&self) -> &'static str {
+        match self {
+            SelectionExport::Png => "Save selection as PNG",
+            SelectionExport::Bytes => "Save selected bytes",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SelectionExport::Png => "png",
+            SelectionExport::Bytes => "bin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionError {
+    /// None of the selection is within the image width
+    Empty,
+    Planar,
+    Tiled,
+    Block,
+    Yuv420,
+}
+
+impl Display for SelectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let layout = match self {
+            SelectionError::Empty => return write!(f, "The selection is past the image width"),
+            SelectionError::Planar => "planar pixels",
+            SelectionError::Tiled => "tiled pixels",
+            SelectionError::Block => "block-compressed modes",
+            SelectionError::Yuv420 => "4:2:0 YUV modes",
+        };
+        write!(f, "Rows of {layout} aren't single runs of bytes")
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_order::{Endianness, UnitSize};
+
+    #[test]
+    fn spans_either_way() {
+        let selection = Selection::spanning(64, (5, 2), (1, 7));
+        assert_eq!(
+            selection,
+            Selection {
+                start_bit: 64,
+                x: 1,
+                y: 2,
+                width: 5,
+                height: 6
+            }
+        );
+        assert_eq!(Selection::spanning(0, (3, 3), (3, 3)).width, 1);
+    }
+
+    #[test]
+    fn stays_on_its_lines() {
+        let selection = Selection::spanning(800, (0, 2), (4, 4));
+        assert_eq!(selection.view_y(800, 80), Some(2));
+        assert_eq!(selection.view_y(0, 80), Some(12));
+        assert_eq!(selection.view_y(1600, 80), Some(-8));
+        assert_eq!(selection.view_y(808, 80), None);
+    }
+
+    #[test]
+    fn byte_ranges_per_row() {
+        // 4 bit pixels, 10 to a 48 bit padded row
+        let selection = Selection::spanning(8, (3, 1), (6, 2));
+        let ranges = selection.byte_ranges(48, 4, ByteOrder::default());
+        assert_eq!(ranges, [8..11, 14..17]);
+
+        let little_endian_32 = ByteOrder {
+            unit_size: UnitSize::Bits32,
+            endianness: Endianness::Little,
+        };
+        let ranges = selection.byte_ranges(48, 4, little_endian_32);
+        assert_eq!(ranges, [8..12, 12..20]);
+    }
+}
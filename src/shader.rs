@@ -1,93 +1,150 @@
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use glam::Vec2;
 
-use iced::mouse;
+use iced::keyboard;
+use iced::mouse::{self, ScrollDelta};
 
 use iced::widget::shader::wgpu::util::DeviceExt;
 use iced::widget::shader::wgpu::{self};
 
 use iced::widget::shader;
-use iced::{Rectangle, Size};
+use iced::{event, Point, Rectangle, Size};
+
+use super::alpha_mode::AlphaMode;
+use super::block_format::BlockFormat;
+use super::channel_scaling::ChannelScaling;
+use super::palette::{BuiltinPalette, Palette};
+use super::pixel_order::PixelOrder;
+use super::planar::PlanarLayout;
+use super::sample::{SampleType, ToneSettings};
+use super::tiling::Tiling;
+use super::yuv::{YuvFormat, YuvSettings};
 
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 #[repr(C)]
 pub struct Uniforms {
     viewport_position: Vec2,
     viewport_resolution: Vec2,
+    /// The bits of each channel, most significant first and -1 past its
+    /// depth, `MAX_CHANNEL_BITS` slots per channel in `CHANNELS` order
+    channel_bits: [[i32; 4]; CHANNEL_BIT_VECTORS],
     target_width: u32,
     scale: u32,
     bit_offset: u32,
-    decoding_red0bit: i32,
-    decoding_red1bit: i32,
-    decoding_red2bit: i32,
-    decoding_red3bit: i32,
-    decoding_red4bit: i32,
-    decoding_red5bit: i32,
-    decoding_red6bit: i32,
-    decoding_red7bit: i32,
-    decoding_green0bit: i32,
-    decoding_green1bit: i32,
-    decoding_green2bit: i32,
-    decoding_green3bit: i32,
-    decoding_green4bit: i32,
-    decoding_green5bit: i32,
-    decoding_green6bit: i32,
-    decoding_green7bit: i32,
-    decoding_blue0bit: i32,
-    decoding_blue1bit: i32,
-    decoding_blue2bit: i32,
-    decoding_blue3bit: i32,
-    decoding_blue4bit: i32,
-    decoding_blue5bit: i32,
-    decoding_blue6bit: i32,
-    decoding_blue7bit: i32,
     decoding_bits_per_pixel: u32,
     grid: u32,
     x_pixel_scroll: u32,
+    alpha_mode: u32,
+    channel_scaling: u32,
+    pixel_order: u32,
+    inverted: u32,
+    plane_count: u32,
+    plane_order: u32,
+    plane_chunk_bits: u32,
+    plane_stride: u32,
+    yuv_format: u32,
+    yuv_matrix: u32,
+    yuv_range: u32,
+    line_parity: u32,
+    tile_order: u32,
+    tile_width: u32,
+    tile_height: u32,
+    predecoded: u32,
+    sample_type: u32,
+    tone_mapping: u32,
+    exposure: f32,
+    range_min: f32,
+    range_max: f32,
+    row_stride: u32,
+    selection_x: u32,
+    selection_y: i32,
+    selection_width: u32,
+    selection_height: u32,
+    zoom_out: u32,
+    _padding: [u32; 3],
 }
 
-#[derive(Clone, Debug)]
+/// The bits of a channel, as bit numbers within the pixel, most significant
+/// first. Bit 0 is the first bit of the pixel.
+pub type ChannelBits = Cow<'static, [u32]>;
+
+/// Channels can be up to this many bits wide.
+pub const MAX_CHANNEL_BITS: usize = 32;
+
+/// Palette indices can be up to this many bits wide.
+pub const MAX_INDEX_BITS: usize = 8;
+
+const CHANNELS: usize = 5;
+const CHANNEL_BIT_VECTORS: usize = CHANNELS * MAX_CHANNEL_BITS / 4;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DecodingScheme {
-    pub red: [Option<u32>; 8],
-    pub green: [Option<u32>; 8],
-    pub blue: [Option<u32>; 8],
+    pub red: ChannelBits,
+    pub green: ChannelBits,
+    pub blue: ChannelBits,
+    pub alpha: ChannelBits,
+    /// Palette index bits. When any are assigned, the palette gives every
+    /// channel and the others are unused.
+    pub index: ChannelBits,
+    /// How the channel bits are read as numbers.
+    pub sample_type: SampleType,
+    /// Color channels read as their complement, for formats where a set bit
+    /// is dark.
+    pub inverted: bool,
+    /// YUV samples laid out as this format. The channel bits are unused and
+    /// `bits_per_pixel` counts only luma for 4:2:0 formats.
+    pub yuv: Option<YuvFormat>,
+    /// Pixels compressed in blocks of this format, decoded before reaching
+    /// the shader. The channel bits are unused.
+    pub block: Option<BlockFormat>,
     pub bits_per_pixel: u32,
 }
 
+impl DecodingScheme {
+    /// Schemes without any alpha bits are fully opaque.
+    pub fn has_alpha(&self) -> bool {
+        if let Some(block) = self.block {
+            return block.has_alpha();
+        }
+        !self.is_indexed() && self.yuv.is_none() && !self.alpha.is_empty()
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.index_depth() > 0
+    }
+
+    /// Number of bits in a palette index.
+    pub fn index_depth(&self) -> u32 {
+        self.index.len() as u32
+    }
+
+    /// The bits of every channel laid out for the `channel_bits` uniform.
+    fn shader_channel_bits(&self) -> [[i32; 4]; CHANNEL_BIT_VECTORS] {
+        let mut slots = [-1; CHANNELS * MAX_CHANNEL_BITS];
+        let channels = [&self.red, &self.green, &self.blue, &self.alpha, &self.index];
+        for (channel_slots, bits) in slots.chunks_mut(MAX_CHANNEL_BITS).zip(channels) {
+            for (slot, bit) in channel_slots.iter_mut().zip(bits.iter()) {
+                *slot = i32::try_from(*bit).unwrap_or(-1);
+            }
+        }
+        std::array::from_fn(|i| std::array::from_fn(|j| slots[i * 4 + j]))
+    }
+}
+
 impl Default for DecodingScheme {
     fn default() -> Self {
         Self {
-            red: [
-                Some(31),
-                Some(30),
-                Some(29),
-                Some(28),
-                Some(27),
-                Some(26),
-                Some(25),
-                Some(24),
-            ],
-            green: [
-                Some(23),
-                Some(22),
-                Some(21),
-                Some(20),
-                Some(19),
-                Some(18),
-                Some(17),
-                Some(16),
-            ],
-            blue: [
-                Some(15),
-                Some(14),
-                Some(13),
-                Some(12),
-                Some(11),
-                Some(10),
-                Some(9),
-                Some(8),
-            ],
+            red: Cow::Borrowed(&[24, 25, 26, 27, 28, 29, 30, 31]),
+            green: Cow::Borrowed(&[16, 17, 18, 19, 20, 21, 22, 23]),
+            blue: Cow::Borrowed(&[8, 9, 10, 11, 12, 13, 14, 15]),
+            alpha: Cow::Borrowed(&[]),
+            index: Cow::Borrowed(&[]),
+            sample_type: SampleType::Unorm,
+            inverted: false,
+            yuv: None,
+            block: None,
             bits_per_pixel: 24,
         }
     }
@@ -99,6 +156,7 @@ struct FragmentShaderPipeline {
     uniform_bind_group: wgpu::BindGroup,
     uniform_bind_group_layout: wgpu::BindGroupLayout,
     data_buffer: wgpu::Buffer,
+    palette_buffer: wgpu::Buffer,
 }
 
 impl FragmentShaderPipeline {
@@ -146,6 +204,13 @@ impl FragmentShaderPipeline {
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
 
+        let palette_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Palette Buffer"),
+            size: (Palette::MAX_COLORS * std::mem::size_of::<u32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         let uniform_bind_group_layout = pipeline.get_bind_group_layout(0);
         let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("shader_quad uniform bind group"),
@@ -159,6 +224,10 @@ impl FragmentShaderPipeline {
                     binding: 1,
                     resource: pixel_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: palette_buffer.as_entire_binding(),
+                },
             ],
         });
 
@@ -168,6 +237,7 @@ impl FragmentShaderPipeline {
             uniform_bind_group,
             uniform_bind_group_layout,
             data_buffer: pixel_buffer,
+            palette_buffer,
         }
     }
 
@@ -177,8 +247,10 @@ impl FragmentShaderPipeline {
         queue: &wgpu::Queue,
         uniforms: &Uniforms,
         buffer: &[u32],
+        palette: &[u32],
     ) {
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(uniforms));
+        queue.write_buffer(&self.palette_buffer, 0, bytemuck::cast_slice(palette));
         let pixel_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Storage Buffer"),
             contents: bytemuck::cast_slice(buffer),
@@ -196,6 +268,10 @@ impl FragmentShaderPipeline {
                     binding: 1,
                     resource: pixel_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.palette_buffer.as_entire_binding(),
+                },
             ],
         });
         self.data_buffer = pixel_buffer;
@@ -237,37 +313,37 @@ impl FragmentShaderPipeline {
     }
 }
 
+/// A rectangle of image pixels in the view. Its top can be above the view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SelectionRect {
+    pub x: u32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
 #[derive(Debug)]
 pub struct FragmentShaderPrimitive {
     target_width: u32,
     scale: u32,
+    zoom_out: u32,
     buffer: Arc<Vec<u32>>,
     decoding_scheme: DecodingScheme,
     bit_offset: u32,
     grid: bool,
     x_pixel_scroll: u32,
-}
-
-impl FragmentShaderPrimitive {
-    fn new(
-        target_width: u32,
-        scale: u32,
-        buffer: Arc<Vec<u32>>,
-        bit_offset: u32,
-        decoding_scheme: DecodingScheme,
-        grid: bool,
-        x_pixel_scroll: u32,
-    ) -> Self {
-        Self {
-            target_width,
-            scale,
-            buffer,
-            bit_offset,
-            decoding_scheme,
-            grid,
-            x_pixel_scroll,
-        }
-    }
+    alpha_mode: AlphaMode,
+    channel_scaling: ChannelScaling,
+    pixel_order: PixelOrder,
+    planar_layout: PlanarLayout,
+    plane_stride: u32,
+    row_stride: u32,
+    selection: Option<SelectionRect>,
+    yuv: YuvSettings,
+    line_parity: u32,
+    tiling: Tiling,
+    tone: ToneSettings,
+    palette: Arc<Palette>,
 }
 
 impl shader::Primitive for FragmentShaderPrimitive {
@@ -287,9 +363,9 @@ impl shader::Primitive for FragmentShaderPrimitive {
 
         let pipeline = storage.get_mut::<FragmentShaderPipeline>().unwrap();
 
-        fn d(num: Option<u32>) -> i32 {
-            num.and_then(|x| i32::try_from(x).ok()).unwrap_or(-1)
-        }
+        let alpha_mode = self.alpha_mode.for_scheme(&self.decoding_scheme);
+        let selection = self.selection.unwrap_or_default();
+        let (tile_width, tile_height) = self.tiling.tile_size(self.decoding_scheme.bits_per_pixel);
 
         pipeline.update_uniforms(
             device,
@@ -297,38 +373,46 @@ impl shader::Primitive for FragmentShaderPrimitive {
             &Uniforms {
                 viewport_position: Vec2::new(bounds.x, bounds.y),
                 viewport_resolution: Vec2::new(bounds.width, bounds.height),
+                channel_bits: self.decoding_scheme.shader_channel_bits(),
                 scale: self.scale,
                 target_width: self.target_width,
                 bit_offset: self.bit_offset,
-                decoding_red0bit: d(self.decoding_scheme.red[0]),
-                decoding_red1bit: d(self.decoding_scheme.red[1]),
-                decoding_red2bit: d(self.decoding_scheme.red[2]),
-                decoding_red3bit: d(self.decoding_scheme.red[3]),
-                decoding_red4bit: d(self.decoding_scheme.red[4]),
-                decoding_red5bit: d(self.decoding_scheme.red[5]),
-                decoding_red6bit: d(self.decoding_scheme.red[6]),
-                decoding_red7bit: d(self.decoding_scheme.red[7]),
-                decoding_green0bit: d(self.decoding_scheme.green[0]),
-                decoding_green1bit: d(self.decoding_scheme.green[1]),
-                decoding_green2bit: d(self.decoding_scheme.green[2]),
-                decoding_green3bit: d(self.decoding_scheme.green[3]),
-                decoding_green4bit: d(self.decoding_scheme.green[4]),
-                decoding_green5bit: d(self.decoding_scheme.green[5]),
-                decoding_green6bit: d(self.decoding_scheme.green[6]),
-                decoding_green7bit: d(self.decoding_scheme.green[7]),
-                decoding_blue0bit: d(self.decoding_scheme.blue[0]),
-                decoding_blue1bit: d(self.decoding_scheme.blue[1]),
-                decoding_blue2bit: d(self.decoding_scheme.blue[2]),
-                decoding_blue3bit: d(self.decoding_scheme.blue[3]),
-                decoding_blue4bit: d(self.decoding_scheme.blue[4]),
-                decoding_blue5bit: d(self.decoding_scheme.blue[5]),
-                decoding_blue6bit: d(self.decoding_scheme.blue[6]),
-                decoding_blue7bit: d(self.decoding_scheme.blue[7]),
                 decoding_bits_per_pixel: self.decoding_scheme.bits_per_pixel,
                 grid: if self.grid { 1 } else { 0 },
                 x_pixel_scroll: self.x_pixel_scroll,
+                alpha_mode: alpha_mode.shader_value(),
+                channel_scaling: self.channel_scaling.shader_value(),
+                pixel_order: self.pixel_order.shader_value(),
+                inverted: u32::from(self.decoding_scheme.inverted),
+                plane_count: self.planar_layout.planes(),
+                plane_order: self.planar_layout.shader_plane_order(),
+                plane_chunk_bits: self
+                    .planar_layout
+                    .chunk_bits(self.decoding_scheme.bits_per_pixel, self.target_width),
+                plane_stride: self.plane_stride,
+                yuv_format: self.decoding_scheme.yuv.map_or(0, |yuv| yuv.shader_value()),
+                yuv_matrix: self.yuv.matrix.shader_value(),
+                yuv_range: self.yuv.range.shader_value(),
+                line_parity: self.line_parity,
+                tile_order: self.tiling.order().shader_value(),
+                tile_width,
+                tile_height,
+                predecoded: u32::from(self.decoding_scheme.block.is_some()),
+                sample_type: self.decoding_scheme.sample_type.shader_value(),
+                tone_mapping: self.tone.tone_mapping.shader_value(),
+                exposure: self.tone.exposure,
+                range_min: self.tone.min,
+                range_max: self.tone.max,
+                row_stride: self.row_stride,
+                selection_x: selection.x,
+                selection_y: selection.y,
+                selection_width: selection.width,
+                selection_height: selection.height,
+                zoom_out: self.zoom_out.max(1),
+                _padding: [0; 3],
             },
             self.buffer.as_slice(),
+            &self.palette.shader_buffer(),
         );
     }
 
@@ -348,11 +432,25 @@ impl shader::Primitive for FragmentShaderPrimitive {
 pub struct FragmentShaderProgram {
     target_width: u32,
     scale: u32,
+    zoom_out: u32,
     buffer: Arc<Vec<u32>>,
     bit_offset: u32,
     decoding_scheme: DecodingScheme,
     grid: bool,
     x_pixel_scroll: u32,
+    alpha_mode: AlphaMode,
+    channel_scaling: ChannelScaling,
+    pixel_order: PixelOrder,
+    planar_layout: PlanarLayout,
+    plane_stride: u32,
+    row_stride: u32,
+    /// Selected pixels, highlighted
+    selection: Option<SelectionRect>,
+    yuv: YuvSettings,
+    line_parity: u32,
+    tiling: Tiling,
+    tone: ToneSettings,
+    palette: Arc<Palette>,
 }
 
 impl FragmentShaderProgram {
@@ -360,14 +458,109 @@ impl FragmentShaderProgram {
         Self {
             target_width: 300,
             scale: 1,
+            zoom_out: 1,
             buffer: Arc::new(vec![0u32; 1]),
             bit_offset: 0,
             decoding_scheme: Default::default(),
             grid: false,
             x_pixel_scroll: 0,
+            alpha_mode: AlphaMode::default(),
+            channel_scaling: ChannelScaling::default(),
+            pixel_order: PixelOrder::default(),
+            planar_layout: PlanarLayout::default(),
+            plane_stride: 0,
+            row_stride: 0,
+            selection: None,
+            yuv: YuvSettings::default(),
+            line_parity: 0,
+            tiling: Tiling::default(),
+            tone: ToneSettings::default(),
+            palette: Arc::new(BuiltinPalette::default().palette(8)),
         }
     }
 
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = Arc::new(palette);
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn set_planar_layout(&mut self, planar_layout: PlanarLayout) {
+        self.planar_layout = planar_layout;
+    }
+
+    pub fn planar_layout(&self) -> PlanarLayout {
+        self.planar_layout
+    }
+
+    pub fn set_plane_stride(&mut self, plane_stride: u32) {
+        self.plane_stride = plane_stride;
+    }
+
+    /// Sets the bits from one row to the next, or 0 for rows that follow
+    /// straight on.
+    pub fn set_row_stride(&mut self, row_stride: u32) {
+        self.row_stride = row_stride;
+    }
+
+    pub fn set_selection(&mut self, selection: Option<SelectionRect>) {
+        self.selection = selection;
+    }
+
+    pub fn set_yuv(&mut self, yuv: YuvSettings) {
+        self.yuv = yuv;
+    }
+
+    pub fn yuv(&self) -> YuvSettings {
+        self.yuv
+    }
+
+    pub fn set_tiling(&mut self, tiling: Tiling) {
+        self.tiling = tiling;
+    }
+
+    pub fn tiling(&self) -> Tiling {
+        self.tiling
+    }
+
+    pub fn set_tone(&mut self, tone: ToneSettings) {
+        self.tone = tone;
+    }
+
+    pub fn tone(&self) -> ToneSettings {
+        self.tone
+    }
+
+    pub fn set_line_parity(&mut self, line_parity: u32) {
+        self.line_parity = line_parity;
+    }
+
+    pub fn set_pixel_order(&mut self, pixel_order: PixelOrder) {
+        self.pixel_order = pixel_order;
+    }
+
+    pub fn pixel_order(&self) -> PixelOrder {
+        self.pixel_order
+    }
+
+    pub fn set_channel_scaling(&mut self, channel_scaling: ChannelScaling) {
+        self.channel_scaling = channel_scaling;
+    }
+
+    pub fn channel_scaling(&self) -> ChannelScaling {
+        self.channel_scaling
+    }
+
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.alpha_mode = alpha_mode;
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
     pub fn set_x_scroll(&mut self, x: u32) {
         self.x_pixel_scroll = x;
     }
@@ -400,6 +593,16 @@ impl FragmentShaderProgram {
         self.scale
     }
 
+    /// Sets how many image pixels across and lines down each screen pixel
+    /// covers, for zooming out below 1x.
+    pub fn set_zoom_out(&mut self, zoom_out: u32) {
+        self.zoom_out = zoom_out;
+    }
+
+    pub fn zoom_out(&self) -> u32 {
+        self.zoom_out
+    }
+
     pub fn set_buffer(&mut self, mut buffer: Vec<u32>) {
         if buffer.is_empty() {
             buffer.push(0u32);
@@ -418,26 +621,362 @@ impl FragmentShaderProgram {
     pub fn decoding_scheme(&self) -> &DecodingScheme {
         &self.decoding_scheme
     }
+
+    /// How far into the view `position` is, in image pixels across and lines
+    /// down, the same way `fs_main` in `shader.wgsl` finds it.
+    pub fn image_offset(&self, position: Point) -> (u32, u32) {
+        let to_image = |screen: f32| {
+            (screen.max(0.0) as u32).saturating_mul(self.zoom_out.max(1)) / self.scale.max(1)
+        };
+        (to_image(position.x), to_image(position.y))
+    }
+
+    /// The pixel of the image at `position` in the view, if there is one
+    /// there.
+    pub fn pixel_at(&self, position: Point) -> Option<(u32, u32)> {
+        if position.x < 0.0 || position.y < 0.0 {
+            return None;
+        }
+
+        let (x, y) = self.image_offset(position);
+        let x = x + self.x_pixel_scroll;
+        (x < self.target_width).then_some((x, y))
+    }
+
+    /// The pixel of the image nearest to `position` in a view of `size`, for
+    /// dragging past the edges of the view or the image.
+    fn nearest_pixel(&self, position: Point, size: Size) -> Option<(u32, u32)> {
+        let last_x = self.target_width.checked_sub(1)?;
+        let position = Point::new(
+            position.x.clamp(0.0, (size.width - 1.0).max(0.0)),
+            position.y.clamp(0.0, (size.height - 1.0).max(0.0)),
+        );
+
+        let (x, y) = self.image_offset(position);
+        Some(((x + self.x_pixel_scroll).min(last_x), y))
+    }
+
+    /// Image pixels across and lines down `screen` pixels of the view cover.
+    fn screen_to_image(&self, screen: f32) -> f32 {
+        screen * self.zoom_out.max(1) as f32 / self.scale.max(1) as f32
+    }
+
+    /// Image pixels across and lines down the wheel scrolls the view by, with
+    /// each notch scrolling `WHEEL_STEP`.
+    fn wheel_scroll(&self, delta: ScrollDelta) -> (f32, f32) {
+        match delta {
+            ScrollDelta::Lines { x, y } => (-x * WHEEL_STEP, -y * WHEEL_STEP),
+            ScrollDelta::Pixels { x, y } => (-self.screen_to_image(x), -self.screen_to_image(y)),
+        }
+    }
+}
+
+/// Image pixels or lines scrolled by each notch of the mouse wheel.
+pub const WHEEL_STEP: f32 = 5.0;
+
+/// What the cursor is doing over the view.
+#[derive(Debug, Default)]
+pub struct CursorState {
+    /// The pixel under the cursor, so hovering only sends a message when a
+    /// different pixel is hovered
+    hovered_pixel: Option<(u32, u32)>,
+    /// The pixels a selection is being dragged from and to
+    dragging: Option<((u32, u32), (u32, u32))>,
+    /// Where in the view the image is held while panning. It moves along as
+    /// the view scrolls, keeping what's left of a pixel not scrolled yet.
+    panning_from: Option<Point>,
+    /// Held for zooming and for scrolling across with the wheel
+    modifiers: keyboard::Modifiers,
 }
 
 impl shader::Program<super::AppMessage> for FragmentShaderProgram {
-    type State = ();
+    type State = CursorState;
     type Primitive = FragmentShaderPrimitive;
 
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: shader::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+        _shell: &mut iced::advanced::Shell<'_, super::AppMessage>,
+    ) -> (event::Status, Option<super::AppMessage>) {
+        let pixel = cursor
+            .position_in(bounds)
+            .and_then(|position| self.pixel_at(position));
+
+        match event {
+            shader::Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = modifiers;
+            }
+            shader::Event::Mouse(mouse::Event::ButtonPressed(
+                mouse::Button::Right | mouse::Button::Middle,
+            )) => {
+                if let Some(position) = cursor.position_in(bounds) {
+                    state.panning_from = Some(position);
+                    return (event::Status::Captured, None);
+                }
+            }
+            shader::Event::Mouse(mouse::Event::ButtonReleased(
+                mouse::Button::Right | mouse::Button::Middle,
+            )) if state.panning_from.is_some() => {
+                state.panning_from = None;
+                return (event::Status::Captured, None);
+            }
+            shader::Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let Some(position) = cursor.position_in(bounds) else {
+                    return (event::Status::Ignored, None);
+                };
+
+                if state.modifiers.command() {
+                    let y = match delta {
+                        ScrollDelta::Lines { y, .. } | ScrollDelta::Pixels { y, .. } => y,
+                    };
+                    let message = (y != 0.0).then(|| super::AppMessage::ZoomAt {
+                        position,
+                        steps: y.signum() as i32,
+                    });
+                    return (event::Status::Captured, message);
+                }
+
+                let (mut columns, mut lines) = self.wheel_scroll(delta);
+                if state.modifiers.shift() {
+                    (columns, lines) = (lines, columns);
+                }
+                return (
+                    event::Status::Captured,
+                    Some(super::AppMessage::ScrollView {
+                        columns: columns.round() as i64,
+                        lines: lines.round() as i64,
+                    }),
+                );
+            }
+            shader::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                if let Some(pixel) = pixel {
+                    state.dragging = Some((pixel, pixel));
+                    return (
+                        event::Status::Captured,
+                        Some(super::AppMessage::PixelSelected(pixel)),
+                    );
+                }
+            }
+            shader::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if state.dragging.is_some() =>
+            {
+                state.dragging = None;
+                return (event::Status::Captured, None);
+            }
+            shader::Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                if let Some(from) = state.panning_from {
+                    let position = Point::ORIGIN + (position - bounds.position());
+                    // Whole pixels and lines only, the rest is kept for later
+                    let columns = self.screen_to_image(from.x - position.x).trunc();
+                    let lines = self.screen_to_image(from.y - position.y).trunc();
+                    if columns == 0.0 && lines == 0.0 {
+                        return (event::Status::Captured, None);
+                    }
+
+                    let screen_per_image = self.scale.max(1) as f32 / self.zoom_out.max(1) as f32;
+                    state.panning_from = Some(Point::new(
+                        from.x - columns * screen_per_image,
+                        from.y - lines * screen_per_image,
+                    ));
+                    return (
+                        event::Status::Captured,
+                        Some(super::AppMessage::ScrollView {
+                            columns: columns as i64,
+                            lines: lines as i64,
+                        }),
+                    );
+                }
+                if let Some((from, to)) = state.dragging {
+                    let position = position - bounds.position();
+                    let dragged_to = self.nearest_pixel(Point::ORIGIN + position, bounds.size());
+                    return match dragged_to.filter(|&pixel| pixel != to) {
+                        Some(pixel) => {
+                            state.dragging = Some((from, pixel));
+                            (
+                                event::Status::Captured,
+                                Some(super::AppMessage::SelectionDragged(from, pixel)),
+                            )
+                        }
+                        None => (event::Status::Captured, None),
+                    };
+                }
+                if pixel != state.hovered_pixel {
+                    state.hovered_pixel = pixel;
+                    return (
+                        event::Status::Ignored,
+                        Some(super::AppMessage::PixelHovered(pixel)),
+                    );
+                }
+            }
+            shader::Event::Mouse(mouse::Event::CursorLeft) if state.hovered_pixel.is_some() => {
+                state.hovered_pixel = None;
+                return (
+                    event::Status::Ignored,
+                    Some(super::AppMessage::PixelHovered(None)),
+                );
+            }
+            _ => {}
+        }
+        (event::Status::Ignored, None)
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &Self::State,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        if state.panning_from.is_some() {
+            return mouse::Interaction::Grabbing;
+        }
+        match cursor
+            .position_in(bounds)
+            .and_then(|position| self.pixel_at(position))
+        {
+            Some(_) => mouse::Interaction::Crosshair,
+            None => mouse::Interaction::default(),
+        }
+    }
+
     fn draw(
         &self,
         _state: &Self::State,
         _cursor: mouse::Cursor,
         _bounds: Rectangle,
     ) -> Self::Primitive {
-        FragmentShaderPrimitive::new(
-            self.target_width,
-            self.scale,
-            self.buffer.clone(),
-            self.bit_offset,
-            self.decoding_scheme.clone(),
-            self.grid,
-            self.x_pixel_scroll,
+        FragmentShaderPrimitive {
+            target_width: self.target_width,
+            scale: self.scale,
+            zoom_out: self.zoom_out,
+            buffer: self.buffer.clone(),
+            bit_offset: self.bit_offset,
+            decoding_scheme: self.decoding_scheme.clone(),
+            grid: self.grid,
+            x_pixel_scroll: self.x_pixel_scroll,
+            alpha_mode: self.alpha_mode,
+            channel_scaling: self.channel_scaling,
+            pixel_order: self.pixel_order,
+            planar_layout: self.planar_layout,
+            plane_stride: self.plane_stride,
+            row_stride: self.row_stride,
+            selection: self.selection,
+            yuv: self.yuv,
+            line_parity: self.line_parity,
+            tiling: self.tiling,
+            tone: self.tone,
+            palette: self.palette.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FragmentShaderProgram, Uniforms};
+    use iced::widget::shader::wgpu::naga;
+    use iced::Point;
+
+    fn parse_shader() -> naga::Module {
+        naga::front::wgsl::parse_str(include_str!("shader.wgsl")).unwrap()
+    }
+
+    #[test]
+    fn shader_validates() {
+        let module = parse_shader();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
         )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    fn uniforms_match_shader_layout() {
+        let module = parse_shader();
+        let mut layouter = naga::proc::Layouter::default();
+        layouter.update(module.to_ctx()).unwrap();
+
+        let (handle, ty) = module
+            .types
+            .iter()
+            .find(|(_, ty)| ty.name.as_deref() == Some("Uniforms"))
+            .unwrap();
+        assert_eq!(
+            layouter[handle].size as usize,
+            std::mem::size_of::<Uniforms>()
+        );
+
+        let naga::TypeInner::Struct { members, .. } = &ty.inner else {
+            panic!("Uniforms isn't a struct in the shader");
+        };
+        let shader_offsets: Vec<(&str, usize)> = members
+            .iter()
+            .map(|member| (member.name.as_deref().unwrap(), member.offset as usize))
+            .collect();
+
+        macro_rules! offsets {
+            ($($field:ident),*) => {
+                vec![$((stringify!($field), std::mem::offset_of!(Uniforms, $field))),*]
+            };
+        }
+        // Every field but the padding, which the shader leaves implicit
+        let offsets = offsets!(
+            viewport_position,
+            viewport_resolution,
+            channel_bits,
+            target_width,
+            scale,
+            bit_offset,
+            decoding_bits_per_pixel,
+            grid,
+            x_pixel_scroll,
+            alpha_mode,
+            channel_scaling,
+            pixel_order,
+            inverted,
+            plane_count,
+            plane_order,
+            plane_chunk_bits,
+            plane_stride,
+            yuv_format,
+            yuv_matrix,
+            yuv_range,
+            line_parity,
+            tile_order,
+            tile_width,
+            tile_height,
+            predecoded,
+            sample_type,
+            tone_mapping,
+            exposure,
+            range_min,
+            range_max,
+            row_stride,
+            selection_x,
+            selection_y,
+            selection_width,
+            selection_height,
+            zoom_out
+        );
+        assert_eq!(offsets, shader_offsets);
+    }
+
+    #[test]
+    fn pixels_follow_the_zoom() {
+        let mut program = FragmentShaderProgram::new();
+        program.set_target_width(100);
+        program.set_x_scroll(10);
+        assert_eq!(program.pixel_at(Point::new(5.5, 7.0)), Some((15, 7)));
+
+        program.set_scale(4);
+        assert_eq!(program.pixel_at(Point::new(5.5, 7.0)), Some((11, 1)));
+
+        program.set_scale(1);
+        program.set_zoom_out(3);
+        assert_eq!(program.pixel_at(Point::new(5.5, 7.0)), Some((25, 21)));
+        assert_eq!(program.pixel_at(Point::new(30.0, 0.0)), None);
     }
 }
@@ -1,40 +1,109 @@
 struct Uniforms {
 	viewport_position: vec2f,
 	viewport_resolution: vec2f,
+	// MAX_CHANNEL_BITS bits per channel, most significant first and -1 past
+	// the channel's depth
+	channel_bits: array<vec4i, 40>,
 	target_width: u32,
 	scale: u32,
 	bit_offset: u32,
-	decoding_red0bit: i32,
-	decoding_red1bit: i32,
-	decoding_red2bit: i32,
-	decoding_red3bit: i32,
-	decoding_red4bit: i32,
-	decoding_red5bit: i32,
-	decoding_red6bit: i32,
-	decoding_red7bit: i32,
-	decoding_green0bit: i32,
-	decoding_green1bit: i32,
-	decoding_green2bit: i32,
-	decoding_green3bit: i32,
-	decoding_green4bit: i32,
-	decoding_green5bit: i32,
-	decoding_green6bit: i32,
-	decoding_green7bit: i32,
-	decoding_blue0bit: i32,
-	decoding_blue1bit: i32,
-	decoding_blue2bit: i32,
-	decoding_blue3bit: i32,
-	decoding_blue4bit: i32,
-	decoding_blue5bit: i32,
-	decoding_blue6bit: i32,
-	decoding_blue7bit: i32,
 	decoding_bits_per_pixel: u32,
 	grid: u32,
 	x_pixel_scroll: u32,
+	alpha_mode: u32,
+	channel_scaling: u32,
+	pixel_order: u32,
+	inverted: u32,
+	plane_count: u32,
+	plane_order: u32,
+	plane_chunk_bits: u32,
+	plane_stride: u32,
+	yuv_format: u32,
+	yuv_matrix: u32,
+	yuv_range: u32,
+	line_parity: u32,
+	tile_order: u32,
+	tile_width: u32,
+	tile_height: u32,
+	predecoded: u32,
+	sample_type: u32,
+	tone_mapping: u32,
+	exposure: f32,
+	range_min: f32,
+	range_max: f32,
+	// Bits from one row to the next, or 0 when rows follow straight on
+	row_stride: u32,
+	// Pixels highlighted as selected, none when selection_width is 0. The
+	// top can be above the view.
+	selection_x: u32,
+	selection_y: i32,
+	selection_width: u32,
+	selection_height: u32,
+	// Image pixels across and lines down each screen pixel covers, above 1
+	// when zoomed out
+	zoom_out: u32,
 }
 
+// Must match AlphaMode::shader_value
+const ALPHA_MODE_IGNORE: u32 = 0u;
+const ALPHA_MODE_CHECKERBOARD: u32 = 1u;
+const ALPHA_MODE_PREMULTIPLIED_CHECKERBOARD: u32 = 2u;
+const ALPHA_MODE_ALPHA_ONLY: u32 = 3u;
+
+// Must match ChannelScaling::shader_value
+const CHANNEL_SCALING_REPLICATE: u32 = 0u;
+const CHANNEL_SCALING_LINEAR: u32 = 1u;
+const CHANNEL_SCALING_ZERO_FILL: u32 = 2u;
+const CHANNEL_SCALING_RAW: u32 = 3u;
+
+// Must match the channel order of DecodingScheme::shader_channel_bits
+const CHANNEL_RED: u32 = 0u;
+const CHANNEL_GREEN: u32 = 1u;
+const CHANNEL_BLUE: u32 = 2u;
+const CHANNEL_ALPHA: u32 = 3u;
+const CHANNEL_INDEX: u32 = 4u;
+const MAX_CHANNEL_BITS: u32 = 32u;
+
+// Must match SampleType::shader_value
+const SAMPLE_TYPE_UNORM: u32 = 0u;
+const SAMPLE_TYPE_SNORM: u32 = 1u;
+const SAMPLE_TYPE_FLOAT: u32 = 2u;
+
+// Must match ToneMapping::shader_value
+const TONE_MAPPING_CLAMP: u32 = 0u;
+const TONE_MAPPING_NORMALIZE: u32 = 1u;
+const TONE_MAPPING_REINHARD: u32 = 2u;
+
+// Must match PixelOrder::shader_value
+const PIXEL_ORDER_MSB_FIRST: u32 = 0u;
+const PIXEL_ORDER_LSB_FIRST: u32 = 1u;
+
+// Must match TileOrder::shader_value
+const TILE_ORDER_LINEAR: u32 = 0u;
+const TILE_ORDER_TILES: u32 = 1u;
+const TILE_ORDER_MORTON: u32 = 2u;
+const TILE_ORDER_PSP: u32 = 3u;
+
+// Must match YuvFormat::shader_value
+const YUV_FORMAT_NONE: u32 = 0u;
+const YUV_FORMAT_YUYV: u32 = 1u;
+const YUV_FORMAT_UYVY: u32 = 2u;
+const YUV_FORMAT_NV12: u32 = 3u;
+const YUV_FORMAT_NV21: u32 = 4u;
+const YUV_FORMAT_I420: u32 = 5u;
+const YUV_FORMAT_YV12: u32 = 6u;
+
+// Must match YuvMatrix::shader_value
+const YUV_MATRIX_BT601: u32 = 0u;
+const YUV_MATRIX_BT709: u32 = 1u;
+
+// Must match YuvRange::shader_value
+const YUV_RANGE_LIMITED: u32 = 0u;
+const YUV_RANGE_FULL: u32 = 1u;
+
 @group(0) @binding(0) var<uniform> uniforms: Uniforms;
 @group(0) @binding(1) var<storage, read> data: array<u32>;
+@group(0) @binding(2) var<storage, read> palette: array<u32>;
 
 struct VertexIn {
 	@builtin(vertex_index) vertex_index: u32,
@@ -52,489 +121,505 @@ fn srgbToLinear(srgb: f32) -> f32 {
     }
 }
 
-@vertex
-fn vs_main(in: VertexIn) -> VertexOut {
-	let uv = vec2f(vec2u((in.vertex_index << 1) & 2, in.vertex_index & 2));
-	let position = vec4f(uv * 2. - 1., 0., 1.);
-	return VertexOut(position);
+// Where the pixel at x, y is stored, counted in pixels. Tiles are stored left
+// to right, then top to bottom, and the pixels in each tile in tile_order.
+fn tiled_pixel_index(x: u32, y: u32) -> u32 {
+	if (uniforms.tile_order == TILE_ORDER_LINEAR) {
+		return y * uniforms.target_width + x;
+	}
+
+	let tile_width = uniforms.tile_width;
+	let tile_height = uniforms.tile_height;
+	let tiles_across = (uniforms.target_width + tile_width - 1u) / tile_width;
+	let tile = (y / tile_height) * tiles_across + x / tile_width;
+
+	let tile_x = x % tile_width;
+	let tile_y = y % tile_height;
+	var in_tile = tile_y * tile_width + tile_x;
+	if (uniforms.tile_order == TILE_ORDER_MORTON) {
+		in_tile = morton_index(tile_x, tile_y);
+	}
+	return tile * tile_width * tile_height + in_tile;
 }
 
-@fragment
-fn fs_main(in: VertexOut) -> @location(0) vec4f {
-	// Without this, the shader starts in the 10px of padding we've provided
-	let real_pos_x = in.position.x - uniforms.viewport_position.x;
-	let real_pos_y = in.position.y - uniforms.viewport_position.y;
+// Interleaves the bits of x and y, x lowest, for as many bits as both sides
+// of the tile have. The rest of the longer side's bits go on top.
+fn morton_index(x: u32, y: u32) -> u32 {
+	let shared_bits = countTrailingZeros(min(uniforms.tile_width, uniforms.tile_height));
 
-	if (real_pos_x < 0.0 || real_pos_y < 0.0) {
-		return vec4f(0.0, 0.0, 0.0, 1.0);
+	var index: u32 = 0u;
+	for (var bit: u32 = 0u; bit < shared_bits; bit++) {
+		index |= ((x >> bit) & 1u) << (bit * 2u);
+		index |= ((y >> bit) & 1u) << (bit * 2u + 1u);
 	}
 
-	if (uniforms.grid != 0 && uniforms.scale > 1) {
-		if ((u32(real_pos_x) % uniforms.scale == 0) || (u32(real_pos_y) % uniforms.scale == 0)) {
-			return vec4f(0.0, 0.0, 0.0, 1.0);
-		}
+	var rest = y;
+	if (uniforms.tile_width > uniforms.tile_height) {
+		rest = x;
 	}
+	return index | ((rest >> shared_bits) << (shared_bits * 2u));
+}
 
-	// Get the x and y position of the binary image to display
-	// This is different from real_pos_x and y because of the ability to scale
-	let data_x = (u32(real_pos_x) / uniforms.scale) + uniforms.x_pixel_scroll;
-	let data_y = u32(real_pos_y) / uniforms.scale;
+// Bit where the pixel at pixel_index begins. With LSB first order, pixels
+// that pack into a byte are read from its least significant end.
+fn pixel_bit_index(pixel_index: u32, bits_per_pixel: u32) -> u32 {
+	let packs_into_bytes = bits_per_pixel > 0u && bits_per_pixel < 8u && 8u % bits_per_pixel == 0u;
 
-	// Do not draw anything past the target_width
-	if (data_x >= uniforms.target_width) {
-		return vec4f(0.0, 0.0, 0.0, 1.0);
-	} 
+	if (uniforms.pixel_order == PIXEL_ORDER_LSB_FIRST && packs_into_bytes) {
+		let pixels_per_byte = 8u / bits_per_pixel;
+		let byte = pixel_index / pixels_per_byte;
+		let position = pixel_index % pixels_per_byte;
+		return byte * 8u + 8u - (position + 1u) * bits_per_pixel;
+	}
+	return pixel_index * bits_per_pixel;
+}
 
-	// Figure out which pixel, linearly, we need to draw
-	let pixel_index = (data_y * u32(uniforms.target_width) + data_x);
+// Bit where the pixel at pixel_index begins, with rows row_stride bits apart
+// when they are padded.
+fn row_bit_index(pixel_index: u32, bits_per_pixel: u32) -> u32 {
+	if (uniforms.row_stride == 0u) {
+		return pixel_bit_index(pixel_index, bits_per_pixel);
+	}
+	let width = max(uniforms.target_width, 1u);
+	return (pixel_index / width) * uniforms.row_stride
+		+ pixel_bit_index(pixel_index % width, bits_per_pixel);
+}
 
-	var red: u32 = 0;
-	var green: u32 = 0;
-	var blue: u32 = 0;
+// Where bit color_bit of the pixel at pixel_index is in the data. Planar
+// pixels are cut into one field per plane, and each field is read from the
+// plane plane_order gives it at the same position.
+fn bit_address(pixel_index: u32, color_bit: u32) -> u32 {
+	let bits_per_pixel = uniforms.decoding_bits_per_pixel;
+	if (uniforms.plane_count <= 1u) {
+		return row_bit_index(pixel_index, bits_per_pixel) + color_bit + uniforms.bit_offset;
+	}
+
+	let plane_bits = max(bits_per_pixel / uniforms.plane_count, 1u);
+	let field = color_bit / plane_bits;
+	let plane = (uniforms.plane_order >> ((field % 8u) * 4u)) & 0xFu;
+	let plane_bit = pixel_bit_index(pixel_index, plane_bits) + color_bit % plane_bits;
+
+	let chunk_bits = uniforms.plane_chunk_bits;
+	if (chunk_bits == 0u) {
+		// Each plane is a whole image
+		return plane * uniforms.plane_stride + plane_bit + uniforms.bit_offset;
+	}
+	let chunk = plane_bit / chunk_bits;
+	return chunk * chunk_bits * uniforms.plane_count
+		+ plane * chunk_bits
+		+ plane_bit % chunk_bits
+		+ uniforms.bit_offset;
+}
 
-	var bit: u32 = 0;
-	var bit_shift: u32 = 0;
-	var array_index: u32 = 0;
-	
-	var color_bit_index: u32 = 0;
-	var bit_index: u32 = pixel_index * uniforms.decoding_bits_per_pixel + uniforms.bit_offset;
+// Reads the bit at index of the data, or 0 past its end
+fn read_data_bit(index: u32) -> u32 {
+	let array_index = index / 32u;
+	if (array_index >= arrayLength(&data)) {
+		return 0u;
+	}
 
+	let bit_shift = 31u - (index % 32u);
+	return (data[array_index] >> bit_shift) & 1u;
+}
 
-	// Red bit 7
-	if (uniforms.decoding_red7bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_red7bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
-	}
-	red = red << 1;
-	red = red | bit;
+struct Channel {
+	value: u32,
+	depth: u32,
+}
 
-	// Red bit 6
-	if (uniforms.decoding_red6bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_red6bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
+// Scales a channel of any depth up to 8 bits, according to channel_scaling
+fn scale_channel(channel: Channel) -> u32 {
+	if (channel.depth == 0u) {
+		return 0u;
 	}
-	red = red << 1;
-	red = red | bit;
 
-	// Red bit 5
-	if (uniforms.decoding_red5bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_red5bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
+	var value: u32 = 0u;
+	switch uniforms.channel_scaling {
+		case CHANNEL_SCALING_LINEAR: {
+			let max_value = (1u << channel.depth) - 1u;
+			value = (channel.value * 255u + max_value / 2u) / max_value;
 		}
-		else {
-			bit = 0u;
+		case CHANNEL_SCALING_ZERO_FILL: {
+			value = channel.value << (8u - channel.depth);
 		}
-	}
-	else {
-		bit = 0u;
-	}
-	red = red << 1;
-	red = red | bit;
-
-	// Red bit 4
-	if (uniforms.decoding_red4bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_red4bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
+		case CHANNEL_SCALING_RAW: {
+			value = channel.value;
 		}
-		else {
-			bit = 0u;
+		case CHANNEL_SCALING_REPLICATE, default: {
+			// Repeat the bits until all 8 are filled, then drop the excess
+			var filled: u32 = 0u;
+			loop {
+				if (filled >= 8u) {
+					break;
+				}
+				value = (value << channel.depth) | channel.value;
+				filled += channel.depth;
+			}
+			value = value >> (filled - 8u);
 		}
 	}
-	else {
-		bit = 0u;
-	}
-	red = red << 1;
-	red = red | bit;
+	return value;
+}
 
-	// Red bit 3
-	if (uniforms.decoding_red3bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_red3bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
-	}
-	red = red << 1;
-	red = red | bit;
+// The pixel bit that bit number `bit` of channel is read from, most
+// significant first, or -1 past the channel's depth
+fn channel_bit(channel: u32, bit: u32) -> i32 {
+	let slot = channel * MAX_CHANNEL_BITS + bit;
+	return uniforms.channel_bits[slot / 4u][slot % 4u];
+}
 
-	// Red bit 2
-	if (uniforms.decoding_red2bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_red2bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
+// Assembles a channel from its assigned bits, most significant bit first
+fn assemble_channel(channel: u32, pixel_index: u32) -> Channel {
+	var assembled = Channel(0u, 0u);
+	for (var bit: u32 = 0u; bit < MAX_CHANNEL_BITS; bit++) {
+		let color_bit = channel_bit(channel, bit);
+		if (color_bit < 0) {
+			break;
 		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
+		let value = read_data_bit(bit_address(pixel_index, u32(color_bit)));
+		assembled = Channel((assembled.value << 1u) | value, assembled.depth + 1u);
 	}
-	red = red << 1;
-	red = red | bit;
+	return assembled;
+}
 
-	// Red bit 1
-	if (uniforms.decoding_red1bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_red1bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
-	}
-	red = red << 1;
-	red = red | bit;
+// Decodes a float with a 5 bit exponent, like halves and the 11 and 10 bit
+// floats of R11G11B10. Must match small_float in sample.rs.
+fn small_float(raw: u32, mantissa_bits: u32, has_sign: bool) -> f32 {
+	let mantissa = raw & ((1u << mantissa_bits) - 1u);
+	let exponent = (raw >> mantissa_bits) & 0x1Fu;
+	let negative = has_sign && ((raw >> (mantissa_bits + 5u)) & 1u) == 1u;
 
-	// Red bit 0
-	if (uniforms.decoding_red0bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_red0bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
+	let fraction = f32(mantissa) / f32(1u << mantissa_bits);
+	var magnitude: f32;
+	if (exponent == 0u) {
+		magnitude = fraction * exp2(-14.0);
+	} else if (exponent == 0x1Fu) {
+		magnitude = exp2(16.0) * (1.0 - 1.0 / f32(2u << mantissa_bits));
+	} else {
+		magnitude = (1.0 + fraction) * exp2(f32(exponent) - 15.0);
 	}
-	else {
-		bit = 0u;
-	}
-	red = red << 1;
-	red = red | bit;
 
-	// Green bit 7
-	if (uniforms.decoding_green7bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_green7bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
+	if (negative) {
+		return -magnitude;
 	}
-	green = green << 1;
-	green = green | bit;
+	return magnitude;
+}
 
-	// Green bit 6
-	if (uniforms.decoding_green6bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_green6bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
+// The number a channel stands for, according to sample_type. Must match
+// SampleType::value.
+fn channel_value(channel: Channel) -> f32 {
+	if (channel.depth == 0u) {
+		return 0.0;
+	}
+
+	switch uniforms.sample_type {
+		case SAMPLE_TYPE_SNORM: {
+			let shift = 32u - channel.depth;
+			return f32(bitcast<i32>(channel.value << shift) >> shift);
+		}
+		case SAMPLE_TYPE_FLOAT: {
+			switch channel.depth {
+				case 32u: {
+					if (((channel.value >> 23u) & 0xFFu) == 0xFFu) {
+						if ((channel.value >> 31u) == 0u) {
+							return 3.40282347e38;
+						}
+						return -3.40282347e38;
+					}
+					return bitcast<f32>(channel.value);
+				}
+				case 16u: {
+					return small_float(channel.value, 10u, true);
+				}
+				case 11u: {
+					return small_float(channel.value, 6u, false);
+				}
+				default: {
+					return small_float(channel.value, 5u, false);
+				}
+			}
+		}
+		case SAMPLE_TYPE_UNORM, default: {
+			return f32(channel.value);
 		}
 	}
-	else {
-		bit = 0u;
-	}
-	green = green << 1;
-	green = green | bit;
+}
 
-	// Green bit 5
-	if (uniforms.decoding_green5bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_green5bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
+// The value white stands for. Must match SampleType::white.
+fn channel_white(depth: u32) -> f32 {
+	switch uniforms.sample_type {
+		case SAMPLE_TYPE_SNORM: {
+			return max(exp2(f32(depth) - 1.0) - 1.0, 1.0);
 		}
-		else {
-			bit = 0u;
+		case SAMPLE_TYPE_FLOAT: {
+			return 1.0;
 		}
-	}
-	else {
-		bit = 0u;
-	}
-	green = green << 1;
-	green = green | bit;
-
-	// Green bit 4
-	if (uniforms.decoding_green4bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_green4bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
+		case SAMPLE_TYPE_UNORM, default: {
+			return max(exp2(f32(depth)) - 1.0, 1.0);
 		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
 	}
-	green = green << 1;
-	green = green | bit;
+}
 
-	// Green bit 3
-	if (uniforms.decoding_green3bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_green3bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
-	}
-	green = green << 1;
-	green = green | bit;
+// Whether a channel goes through map_channel rather than scale_channel. Must
+// match ToneSettings::maps.
+fn tone_maps(depth: u32) -> bool {
+	return uniforms.sample_type != SAMPLE_TYPE_UNORM
+		|| depth > 8u
+		|| uniforms.tone_mapping != TONE_MAPPING_CLAMP
+		|| uniforms.exposure != 0.0;
+}
 
-	// Green bit 2
-	if (uniforms.decoding_green2bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_green2bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
-	}
-	green = green << 1;
-	green = green | bit;
+// Brings a channel to 8 bits through the tone settings. Alpha is only
+// brought into range. Must match ToneSettings::map.
+fn map_channel(channel: Channel, is_color: bool) -> u32 {
+	let value = channel_value(channel);
 
-	// Green bit 1
-	if (uniforms.decoding_green1bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_green1bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
+	var x: f32;
+	if (is_color && uniforms.tone_mapping == TONE_MAPPING_NORMALIZE) {
+		x = (value - uniforms.range_min) / (uniforms.range_max - uniforms.range_min);
+	} else {
+		x = value / channel_white(channel.depth);
 	}
-	green = green << 1;
-	green = green | bit;
 
-	// Green bit 0
-	if (uniforms.decoding_green0bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_green0bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
+	if (is_color) {
+		x *= exp2(uniforms.exposure);
+		if (uniforms.tone_mapping == TONE_MAPPING_REINHARD) {
+			x = max(x, 0.0) / (1.0 + max(x, 0.0));
 		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
 	}
-	green = green << 1;
-	green = green | bit;
 
-		// Blue bit 7
-	if (uniforms.decoding_blue7bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_blue7bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
-	}
-	blue = blue << 1;
-	blue = blue | bit;
+	return u32(floor(clamp(x, 0.0, 1.0) * 255.0 + 0.5));
+}
 
-	// Blue bit 6
-	if (uniforms.decoding_blue6bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_blue6bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
+// Assembles a channel, complementing it if inverted, and brings it to 8 bits
+fn read_channel(channel: u32, pixel_index: u32, inverted: bool) -> u32 {
+	var assembled = assemble_channel(channel, pixel_index);
+	if (inverted && assembled.depth > 0u) {
+		assembled.value = assembled.value ^ (0xFFFFFFFFu >> (32u - assembled.depth));
 	}
-	else {
-		bit = 0u;
+	if (tone_maps(assembled.depth)) {
+		return map_channel(assembled, channel != CHANNEL_ALPHA);
 	}
-	blue = blue << 1;
-	blue = blue | bit;
+	return scale_channel(assembled);
+}
 
-	// Blue bit 5
-	if (uniforms.decoding_blue5bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_blue5bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
+// Reads the 8 bit sample starting at bit index of the data
+fn read_sample(index: u32) -> u32 {
+	var value: u32 = 0u;
+	for (var bit: u32 = 0u; bit < 8u; bit++) {
+		value = (value << 1u) | read_data_bit(index + bit);
 	}
-	blue = blue << 1;
-	blue = blue | bit;
+	return value;
+}
 
-	// Blue bit 4
-	if (uniforms.decoding_blue4bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_blue4bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
+// Where the Y, U and V samples of the pixel at x, y are. Packed formats share
+// chroma between pairs of pixels, 4:2:0 formats between 2x2 blocks, with the
+// chroma planes plane_stride bits apart.
+fn yuv_sample_bits(x: u32, y: u32) -> vec3u {
+	let bit_offset = uniforms.bit_offset;
+	let width = uniforms.target_width;
+
+	switch uniforms.yuv_format {
+		case YUV_FORMAT_YUYV, YUV_FORMAT_UYVY: {
+			var pixel_index = y * width + x;
+			var row_start = 0u;
+			if (uniforms.row_stride != 0u) {
+				// Padded rows start on a pair of their own
+				pixel_index = x;
+				row_start = y * uniforms.row_stride;
+			}
+			let pair = row_start + (pixel_index / 2u) * 32u + bit_offset;
+			let luma = (pixel_index % 2u) * 16u;
+			if (uniforms.yuv_format == YUV_FORMAT_UYVY) {
+				return vec3u(pair + 8u + luma, pair, pair + 16u);
+			}
+			return vec3u(pair + luma, pair + 8u, pair + 24u);
+		}
+		default: {}
+	}
+
+	let chroma_width = (width + 1u) / 2u;
+	let chroma_row = (y + uniforms.line_parity) / 2u;
+	let luma = (y * width + x) * 8u + bit_offset;
+
+	switch uniforms.yuv_format {
+		case YUV_FORMAT_NV12: {
+			let uv = uniforms.plane_stride + (chroma_row * chroma_width + x / 2u) * 16u + bit_offset;
+			return vec3u(luma, uv, uv + 8u);
+		}
+		case YUV_FORMAT_NV21: {
+			let uv = uniforms.plane_stride + (chroma_row * chroma_width + x / 2u) * 16u + bit_offset;
+			return vec3u(luma, uv + 8u, uv);
+		}
+		default: {
+			let chroma = (chroma_row * chroma_width + x / 2u) * 8u + bit_offset;
+			let first = uniforms.plane_stride + chroma;
+			let second = uniforms.plane_stride * 2u + chroma;
+			if (uniforms.yuv_format == YUV_FORMAT_YV12) {
+				return vec3u(luma, second, first);
+			}
+			return vec3u(luma, first, second);
 		}
 	}
-	else {
-		bit = 0u;
-	}
-	blue = blue << 1;
-	blue = blue | bit;
+}
 
-	// Blue bit 3
-	if (uniforms.decoding_blue3bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_blue3bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
-	}
-	else {
-		bit = 0u;
-	}
-	blue = blue << 1;
-	blue = blue | bit;
+// Converts 8 bit YUV samples to 8 bit RGB using yuv_matrix and yuv_range
+fn yuv_to_rgb(samples: vec3u) -> vec3u {
+	var y = f32(samples.x);
+	var u = f32(samples.y) - 128.0;
+	var v = f32(samples.z) - 128.0;
+	if (uniforms.yuv_range == YUV_RANGE_LIMITED) {
+		y = (y - 16.0) * 255.0 / 219.0;
+		u = u * 255.0 / 224.0;
+		v = v * 255.0 / 224.0;
+	}
+
+	var kr = 0.299;
+	var kb = 0.114;
+	if (uniforms.yuv_matrix == YUV_MATRIX_BT709) {
+		kr = 0.2126;
+		kb = 0.0722;
+	}
+	let kg = 1.0 - kr - kb;
+
+	let red = y + 2.0 * (1.0 - kr) * v;
+	let blue = y + 2.0 * (1.0 - kb) * u;
+	let green = (y - kr * red - kb * blue) / kg;
+	return vec3u(clamp(round(vec3f(red, green, blue)), vec3f(0.0), vec3f(255.0)));
+}
 
-	// Blue bit 2
-	if (uniforms.decoding_blue2bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_blue2bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
+// Looks up a palette entry, packed as 0xRRGGBBAA
+fn palette_color(index: u32) -> vec4u {
+	var color: u32 = 0x000000FFu;
+	if (index < arrayLength(&palette)) {
+		color = palette[index];
 	}
-	else {
-		bit = 0u;
-	}
-	blue = blue << 1;
-	blue = blue | bit;
+	return vec4u((color >> 24u) & 0xFFu, (color >> 16u) & 0xFFu, (color >> 8u) & 0xFFu, color & 0xFFu);
+}
 
-	// Blue bit 1
-	if (uniforms.decoding_blue1bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_blue1bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
-		}
+// Color selected pixels are tinted towards
+const SELECTION_TINT: vec3f = vec3f(0.2, 0.5, 1.0);
+
+// Whether the pixel at x, y is in the selection
+fn is_selected(x: u32, y: u32) -> bool {
+	let top = uniforms.selection_y;
+	return x >= uniforms.selection_x
+		&& x - uniforms.selection_x < uniforms.selection_width
+		&& i32(y) >= top
+		&& u32(i32(y) - top) < uniforms.selection_height;
+}
+
+// Background shown through transparent pixels, in 8x8 image pixel squares
+fn checkerboard(x: u32, y: u32) -> f32 {
+	if (((x / 8u) + (y / 8u)) % 2u == 0u) {
+		return 0.8;
 	}
-	else {
-		bit = 0u;
+	return 1.0;
+}
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+	let uv = vec2f(vec2u((in.vertex_index << 1) & 2, in.vertex_index & 2));
+	let position = vec4f(uv * 2. - 1., 0., 1.);
+	return VertexOut(position);
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4f {
+	// Without this, the shader starts in the 10px of padding we've provided
+	let real_pos_x = in.position.x - uniforms.viewport_position.x;
+	let real_pos_y = in.position.y - uniforms.viewport_position.y;
+
+	if (real_pos_x < 0.0 || real_pos_y < 0.0) {
+		return vec4f(0.0, 0.0, 0.0, 1.0);
 	}
-	blue = blue << 1;
-	blue = blue | bit;
 
-	// Blue bit 0
-	if (uniforms.decoding_blue0bit >= 0) { // If bit is assigned to something
-		color_bit_index = u32(uniforms.decoding_blue0bit);
-		array_index = (color_bit_index + bit_index) / 32u;
-		bit_shift = 31u - ((color_bit_index + bit_index) % 32u);
-		if (array_index < arrayLength(&data)) {
-			bit = (data[array_index] >> bit_shift) & 1u;
-		}
-		else {
-			bit = 0u;
+	if (uniforms.grid != 0 && uniforms.scale > 1 && uniforms.zoom_out == 1) {
+		if ((u32(real_pos_x) % uniforms.scale == 0) || (u32(real_pos_y) % uniforms.scale == 0)) {
+			return vec4f(0.0, 0.0, 0.0, 1.0);
 		}
 	}
-	else {
-		bit = 0u;
-	}
-	blue = blue << 1;
-	blue = blue | bit;
 
-	let r = f32(red) / 255.0;
-	let g = f32(green) / 255.0;
-	let b = f32(blue) / 255.0;
+	// Get the x and y position of the binary image to display
+	// This is different from real_pos_x and y because of the ability to scale
+	let data_x = (u32(real_pos_x) * uniforms.zoom_out / uniforms.scale) + uniforms.x_pixel_scroll;
+	let data_y = u32(real_pos_y) * uniforms.zoom_out / uniforms.scale;
+
+	// Do not draw anything past the target_width
+	if (data_x >= uniforms.target_width) {
+		return vec4f(0.0, 0.0, 0.0, 1.0);
+	} 
+
+	// Figure out which pixel, in the order they are stored, we need to draw
+	let pixel_index = tiled_pixel_index(data_x, data_y);
+
+	var red: u32;
+	var green: u32;
+	var blue: u32;
+	var alpha: u32;
+
+	// Indexed schemes take every channel from the palette
+	let index = assemble_channel(CHANNEL_INDEX, pixel_index);
+	if (uniforms.predecoded != 0u) {
+		// Block-compressed data is decoded beforehand, one 0xRRGGBBAA word
+		// per pixel
+		let color = data[data_y * uniforms.target_width + data_x];
+		red = (color >> 24u) & 0xFFu;
+		green = (color >> 16u) & 0xFFu;
+		blue = (color >> 8u) & 0xFFu;
+		alpha = color & 0xFFu;
+	} else if (uniforms.yuv_format != YUV_FORMAT_NONE) {
+		let samples = yuv_sample_bits(data_x, data_y);
+		let rgb = yuv_to_rgb(vec3u(read_sample(samples.x), read_sample(samples.y), read_sample(samples.z)));
+		red = rgb.r;
+		green = rgb.g;
+		blue = rgb.b;
+		alpha = 255u;
+	} else if (index.depth > 0u) {
+		let color = palette_color(index.value);
+		red = color.r;
+		green = color.g;
+		blue = color.b;
+		alpha = color.a;
+	} else {
+		red = read_channel(CHANNEL_RED, pixel_index, uniforms.inverted != 0u);
+		green = read_channel(CHANNEL_GREEN, pixel_index, uniforms.inverted != 0u);
+		blue = read_channel(CHANNEL_BLUE, pixel_index, uniforms.inverted != 0u);
+		alpha = read_channel(CHANNEL_ALPHA, pixel_index, false);
+	}
+
+	var r = f32(red) / 255.0;
+	var g = f32(green) / 255.0;
+	var b = f32(blue) / 255.0;
+	let a = f32(alpha) / 255.0;
+
+	switch uniforms.alpha_mode {
+		case ALPHA_MODE_CHECKERBOARD: {
+			let background = checkerboard(data_x, data_y);
+			r = r * a + background * (1.0 - a);
+			g = g * a + background * (1.0 - a);
+			b = b * a + background * (1.0 - a);
+		}
+		case ALPHA_MODE_PREMULTIPLIED_CHECKERBOARD: {
+			let background = checkerboard(data_x, data_y);
+			r = min(r + background * (1.0 - a), 1.0);
+			g = min(g + background * (1.0 - a), 1.0);
+			b = min(b + background * (1.0 - a), 1.0);
+		}
+		case ALPHA_MODE_ALPHA_ONLY: {
+			r = a;
+			g = a;
+			b = a;
+		}
+		case ALPHA_MODE_IGNORE, default: {}
+	}
+
+	if (is_selected(data_x, data_y)) {
+		let tinted = mix(vec3f(r, g, b), SELECTION_TINT, 0.4);
+		r = tinted.r;
+		g = tinted.g;
+		b = tinted.b;
+	}
 
 	return vec4f(srgbToLinear(r), srgbToLinear(g), srgbToLinear(b), 1.0);
 }
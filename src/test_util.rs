@@ -0,0 +1,14 @@
+//! Helpers shared by the tests of several modules.
+
+/// Deterministic noise, so images have detail along their rows.
+pub fn noise(seed: u32) -> u8 {
+    let mut x = seed.wrapping_mul(0x9E37_79B9) ^ 0x85EB_CA6B;
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x2C1B_3C6D);
+    (x >> 24) as u8
+}
+
+/// The first, and so most likely, of a list ranked best first.
+pub fn best<T>(ranked: &[T]) -> &T {
+    ranked.first().expect("nothing was ranked")
+}
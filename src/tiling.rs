@@ -0,0 +1,285 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Order the pixels of an image are stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum TileOrder {
+    /// One line after another
+    #[default]
+    Linear,
+    /// Tiles of lines, like GBA, NES and SNES tiles
+    Tiles,
+    /// Tiles in Morton, or Z, order, like twiddled textures
+    Morton,
+    /// PSP swizzling, 16 byte by 8 line blocks
+    Psp,
+}
+
+impl TileOrder {
+    pub const ALL: &'static [Self] = &[Self::Linear, Self::Tiles, Self::Morton, Self::Psp];
+
+    /// Value of the `tile_order` uniform, matching the `TILE_ORDER_*`
+    /// constants in `shader.wgsl`.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            TileOrder::Linear => 0,
+            TileOrder::Tiles => 1,
+            TileOrder::Morton => 2,
+            TileOrder::Psp => 3,
+        }
+    }
+
+    /// Whether the order uses the tile size.
+    pub fn has_tile_size(&self) -> bool {
+        matches!(self, TileOrder::Tiles | TileOrder::Morton)
+    }
+}
+
+impl Display for TileOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TileOrder::Linear => "linear",
+            TileOrder::Tiles => "tiles",
+            TileOrder::Morton => "morton",
+            TileOrder::Psp => "psp",
+        })
+    }
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TileOrder::ALL
+            .iter()
+            .find(|order| order.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names = TileOrder::ALL
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("unknown tile order {s:?}, expected one of: {names}")
+            })
+    }
+}
+
+/// Maps pixel positions in the image to the order pixels are stored in.
+/// Tiles are stored left to right, then top to bottom, and the pixels in
+/// each tile in `order`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tiling {
+    order: TileOrder,
+    tile_width: u32,
+    tile_height: u32,
+}
+
+impl Default for Tiling {
+    fn default() -> Self {
+        Self {
+            order: TileOrder::Linear,
+            tile_width: 8,
+            tile_height: 8,
+        }
+    }
+}
+
+impl Tiling {
+    /// Lays pixels out in `order`, using the tile size for tiles and Morton
+    /// order. Morton tiles must be a power of two each way.
+    pub fn new(order: TileOrder, tile_width: u32, tile_height: u32) -> Result<Self, TilingError> {
+        if order.has_tile_size() {
+            if tile_width == 0 || tile_height == 0 {
+                return Err(TilingError::EmptyTile);
+            }
+            if order == TileOrder::Morton
+                && !(tile_width.is_power_of_two() && tile_height.is_power_of_two())
+            {
+                return Err(TilingError::MortonNotPowerOfTwo {
+                    tile_width,
+                    tile_height,
+                });
+            }
+        }
+
+        Ok(Self {
+            order,
+            tile_width,
+            tile_height,
+        })
+    }
+
+    pub fn order(&self) -> TileOrder {
+        self.order
+    }
+
+    /// Width and height in pixels of the tiles, for pixels of
+    /// `bits_per_pixel` bits.
+    pub fn tile_size(&self, bits_per_pixel: u32) -> (u32, u32) {
+        match self.order {
+            TileOrder::Linear => (1, 1),
+            TileOrder::Tiles | TileOrder::Morton => (self.tile_width, self.tile_height),
+            TileOrder::Psp => ((128 / bits_per_pixel.max(1)).max(1), 8),
+        }
+    }
+
+    /// Lines of tiles start this many lines apart, so the start of the data
+    /// should only move by as many.
+    pub fn line_step(&self, bits_per_pixel: u32) -> u64 {
+        u64::from(self.tile_size(bits_per_pixel).1)
+    }
+
+    /// Where the pixel at `x`, `y` is stored, counted in pixels, the same
+    /// way `tiled_pixel_index` in `shader.wgsl` finds it.
+    pub fn pixel_index(&self, x: u32, y: u32, target_width: u32, bits_per_pixel: u32) -> u64 {
+        let (x, y, width) = (u64::from(x), u64::from(y), u64::from(target_width));
+        if self.order == TileOrder::Linear {
+            return y * width + x;
+        }
+
+        let (tile_width, tile_height) = self.tile_size(bits_per_pixel);
+        let (tile_width, tile_height) = (u64::from(tile_width), u64::from(tile_height));
+        let tiles_across = width.div_ceil(tile_width);
+        let tile = (y / tile_height) * tiles_across + x / tile_width;
+
+        let (x, y) = (x % tile_width, y % tile_height);
+        let in_tile = match self.order {
+            TileOrder::Morton => morton_index(x, y, tile_width, tile_height),
+            _ => y * tile_width + x,
+        };
+        tile * tile_width * tile_height + in_tile
+    }
+}
+
+/// Interleaves the bits of `x` and `y`, `x` lowest, for as many bits as both
+/// sides of the tile have. The rest of the longer side's bits go on top.
+fn morton_index(x: u64, y: u64, tile_width: u64, tile_height: u64) -> u64 {
+    let shared_bits = tile_width.min(tile_height).trailing_zeros();
+
+    let mut index = 0;
+    for bit in 0..shared_bits {
+        index |= ((x >> bit) & 1) << (bit * 2);
+        index |= ((y >> bit) & 1) << (bit * 2 + 1);
+    }
+
+    let rest = if tile_width > tile_height { x } else { y };
+    index | (rest >> shared_bits) << (shared_bits * 2)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TilingError {
+    EmptyTile,
+    MortonNotPowerOfTwo { tile_width: u32, tile_height: u32 },
+}
+
+impl Display for TilingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TilingError::EmptyTile => write!(f, "Tiles must be at least 1x1"),
+            TilingError::MortonNotPowerOfTwo {
+                tile_width,
+                tile_height,
+            } => write!(
+                f,
+                "Morton tiles must be a power of two each way, not {tile_width}x{tile_height}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TilingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The pixel stored at each index of a `width` by `height` image.
+    fn storage_order(
+        tiling: Tiling,
+        width: u32,
+        height: u32,
+        bits_per_pixel: u32,
+    ) -> Vec<(u32, u32)> {
+        let mut pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect::<Vec<_>>();
+        pixels.sort_by_key(|&(x, y)| tiling.pixel_index(x, y, width, bits_per_pixel));
+        pixels
+    }
+
+    #[test]
+    fn tiles() {
+        let tiling = Tiling::new(TileOrder::Tiles, 2, 2).unwrap();
+        assert_eq!(
+            storage_order(tiling, 4, 2, 8),
+            [
+                (0, 0),
+                (1, 0),
+                (0, 1),
+                (1, 1),
+                (2, 0),
+                (3, 0),
+                (2, 1),
+                (3, 1)
+            ]
+        );
+        // A partial tile at the right edge still takes a whole tile
+        assert_eq!(tiling.pixel_index(0, 2, 3, 8), 8);
+    }
+
+    #[test]
+    fn morton() {
+        let tiling = Tiling::new(TileOrder::Morton, 4, 4).unwrap();
+        let order = storage_order(tiling, 4, 4, 8);
+        assert_eq!(
+            order[..8],
+            [
+                (0, 0),
+                (1, 0),
+                (0, 1),
+                (1, 1),
+                (2, 0),
+                (3, 0),
+                (2, 1),
+                (3, 1)
+            ]
+        );
+        assert_eq!(order[15], (3, 3));
+
+        // Wide tiles store one square after another
+        let wide = Tiling::new(TileOrder::Morton, 4, 2).unwrap();
+        assert_eq!(
+            storage_order(wide, 4, 2, 8),
+            [
+                (0, 0),
+                (1, 0),
+                (0, 1),
+                (1, 1),
+                (2, 0),
+                (3, 0),
+                (2, 1),
+                (3, 1)
+            ]
+        );
+
+        assert_eq!(
+            Tiling::new(TileOrder::Morton, 6, 4),
+            Err(TilingError::MortonNotPowerOfTwo {
+                tile_width: 6,
+                tile_height: 4
+            })
+        );
+    }
+
+    #[test]
+    fn psp_blocks_are_16_bytes_wide() {
+        let tiling = Tiling::new(TileOrder::Psp, 0, 0).unwrap();
+        assert_eq!(tiling.tile_size(32), (4, 8));
+        assert_eq!(tiling.tile_size(4), (32, 8));
+
+        // The second block of a 32 bit image starts after 8 lines of 4 pixels
+        assert_eq!(tiling.pixel_index(4, 0, 64, 32), 32);
+        assert_eq!(tiling.pixel_index(0, 1, 64, 32), 4);
+        assert_eq!(tiling.line_step(32), 8);
+    }
+}
@@ -0,0 +1,203 @@
+/// A width that rows of the data seem to repeat at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WidthCandidate {
+    pub width: u32,
+    /// How much more alike pixels a row apart are than pixels a row and a
+    /// pixel apart, from 0 for no more alike to 1 for identical
+    pub confidence: f32,
+}
+
+/// Bytes from the start bit that are analyzed.
+const SAMPLE_BYTES: usize = 256 << 10;
+
+/// Pixel pairs compared for each width, spread over the sample.
+const MAX_PAIRS: usize = 8 << 10;
+
+/// Neighboring pairs compared in a row before skipping ahead.
+const RUN_PAIRS: usize = 64;
+
+/// Candidates returned at most.
+const CANDIDATES: usize = 8;
+
+/// Widths whose rows are less alike than this aren't offered.
+const MIN_CONFIDENCE: f32 = 0.1;
+
+/// Finds the widths, up to `max_width`, the rows of the data starting at
+/// `start_bit` most likely have, best first.
+///
+/// Pixels a row apart are usually much more alike than pixels a row and a
+/// pixel apart, so for every width the mean difference between samples a row
+/// apart is compared with the widths either side of it. Multiples of a width
+/// that did at least as well are left out.
+pub fn detect_widths(
+    data: &[u8],
+    start_bit: u64,
+    bits_per_pixel: u32,
+    max_width: u32,
+) -> Vec<WidthCandidate> {
+    let start_byte = usize::try_from(start_bit / 8).unwrap_or(usize::MAX);
+    let sample = data.get(start_byte..).unwrap_or_default();
+    let sample = &sample[..sample.len().min(SAMPLE_BYTES)];
+
+    let (units, units_per_pixel) = split_units(sample, bits_per_pixel);
+    if units_per_pixel == 0 {
+        return vec![];
+    }
+
+    // Mean difference between units `width` pixels apart, for widths up to
+    // one past the largest candidate. Widths that don't fit two rows in the
+    // sample are left out.
+    let max_width = max_width.min((units.len() / units_per_pixel / 2) as u32);
+    let differences: Vec<Option<f32>> = (0..=max_width + 1)
+        .map(|width| mean_difference(&units, width as usize * units_per_pixel))
+        .collect();
+
+    let mut candidates: Vec<WidthCandidate> = (2..=max_width)
+        .filter_map(|width| {
+            let difference = differences[width as usize]?;
+            let before = differences[width as usize - 1]?;
+            let after = differences[width as usize + 1]?;
+            let neighbors = (before + after) / 2.0;
+            if neighbors <= 0.0 {
+                return None;
+            }
+
+            let confidence = 1.0 - difference / neighbors;
+            (confidence >= MIN_CONFIDENCE).then_some(WidthCandidate { width, confidence })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let mut ranked: Vec<WidthCandidate> = vec![];
+    for candidate in candidates {
+        let is_multiple = ranked
+            .iter()
+            .any(|better| candidate.width.is_multiple_of(better.width));
+        if !is_multiple {
+            ranked.push(candidate);
+        }
+        if ranked.len() == CANDIDATES {
+            break;
+        }
+    }
+    ranked
+}
+
+/// Splits the sample into the units compared: bytes for pixels of whole
+/// bytes, pixels for pixels that pack into bytes, and single bits otherwise.
+/// Returns the units and how many make up a pixel.
+fn split_units(sample: &[u8], bits_per_pixel: u32) -> (Vec<u8>, usize) {
+    match bits_per_pixel {
+        0 => (vec![], 0),
+        bits if bits % 8 == 0 => (sample.to_vec(), bits as usize / 8),
+        bits if bits < 8 && 8 % bits == 0 => {
+            let mask = (1u8 << bits) - 1;
+            let units = sample
+                .iter()
+                .flat_map(|byte| {
+                    (0..8 / bits)
+                        .rev()
+                        .map(move |i| (byte >> (i * bits)) & mask)
+                })
+                .collect();
+            (units, 1)
+        }
+        bits => {
+            let units = sample
+                .iter()
+                .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+                .collect();
+            (units, bits as usize)
+        }
+    }
+}
+
+/// Mean absolute difference between units `lag` apart, or `None` when the
+/// units don't reach that far. Pairs are taken in short runs of neighbors
+/// spread over the sample, so every channel of a pixel is compared rather
+/// than whichever one a fixed step keeps landing on.
+fn mean_difference(units: &[u8], lag: usize) -> Option<f32> {
+    let pairs = units.len().checked_sub(lag).filter(|&pairs| pairs > 0)?;
+    let runs = MAX_PAIRS.div_ceil(RUN_PAIRS);
+    let spacing = pairs.div_ceil(runs).max(RUN_PAIRS);
+
+    let (total, count) = (0..pairs)
+        .step_by(spacing)
+        .flat_map(|run| run..(run + RUN_PAIRS).min(pairs))
+        .fold((0u64, 0u64), |(total, count), i| {
+            (
+                total + u64::from(units[i].abs_diff(units[i + lag])),
+                count + 1,
+            )
+        });
+    Some(total as f32 / count as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{best, noise};
+
+    /// An image whose columns vary a lot and whose rows change slowly.
+    fn image(width: u32, height: u32, bytes_per_pixel: u32) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| {
+                (0..width * bytes_per_pixel).map(move |x| noise(x).wrapping_add((y / 4) as u8))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_byte_pixel_widths() {
+        let rgb = image(123, 100, 3);
+        let candidates = detect_widths(&rgb, 0, 24, 2048);
+        assert_eq!(best(&candidates).width, 123);
+        // Multiples of the width are left out
+        assert!(candidates
+            .iter()
+            .all(|c| c.width % 123 != 0 || c.width == 123));
+
+        let gray = image(640, 40, 1);
+        assert_eq!(best(&detect_widths(&gray, 0, 8, 2048)).width, 640);
+    }
+
+    #[test]
+    fn starts_at_the_start_bit() {
+        let mut data = vec![0xAA; 1000];
+        data.extend(image(200, 50, 4));
+        assert_eq!(best(&detect_widths(&data, 1000 * 8, 32, 2048)).width, 200);
+    }
+
+    #[test]
+    fn compares_every_channel() {
+        // A constant alpha byte must not make every lag look like a row
+        let rgba: Vec<u8> = image(100, 64, 3)
+            .chunks(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect();
+        assert_eq!(best(&detect_widths(&rgba, 0, 32, 2048)).width, 100);
+    }
+
+    #[test]
+    fn finds_packed_pixel_widths() {
+        // 1 bit pixels, 8 to a byte: 77 pixel rows that repeat
+        let bits: Vec<u8> = (0..77 * 60).map(|i| noise(i % 77) & 1).collect();
+        let bytes: Vec<u8> = bits
+            .chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |byte, (i, bit)| byte | bit << (7 - i))
+            })
+            .collect();
+        assert_eq!(best(&detect_widths(&bytes, 0, 1, 2048)).width, 77);
+    }
+
+    #[test]
+    fn noise_has_no_width() {
+        let data: Vec<u8> = (0..100_000).map(noise).collect();
+        assert!(detect_widths(&data, 0, 8, 2048).is_empty());
+        assert!(detect_widths(&[], 0, 8, 2048).is_empty());
+    }
+}
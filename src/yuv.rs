@@ -0,0 +1,378 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::byte_order::ByteOrder;
+use super::preview::{DataWindow, Pixel};
+use super::row_stride::RowStride;
+
+/// Luma and chroma layouts of the YUV pixel modes. All samples are 8 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum YuvFormat {
+    /// Packed 4:2:2, Y0 U Y1 V
+    Yuyv,
+    /// Packed 4:2:2, U Y0 V Y1
+    Uyvy,
+    /// 4:2:0, a luma plane then interleaved U V
+    Nv12,
+    /// 4:2:0, a luma plane then interleaved V U
+    Nv21,
+    /// 4:2:0, luma, U and V planes
+    I420,
+    /// 4:2:0, luma, V and U planes
+    Yv12,
+}
+
+impl YuvFormat {
+    /// Value of the `yuv_format` uniform, matching the `YUV_FORMAT_*`
+    /// constants in `shader.wgsl`. 0 means the scheme isn't YUV.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            YuvFormat::Yuyv => 1,
+            YuvFormat::Uyvy => 2,
+            YuvFormat::Nv12 => 3,
+            YuvFormat::Nv21 => 4,
+            YuvFormat::I420 => 5,
+            YuvFormat::Yv12 => 6,
+        }
+    }
+
+    /// Whether luma and chroma are in separate planes, with chroma shared by
+    /// 2x2 blocks of pixels.
+    pub fn is_420(&self) -> bool {
+        !matches!(self, YuvFormat::Yuyv | YuvFormat::Uyvy)
+    }
+}
+
+/// Where the planes of a 4:2:0 frame are, relative to the first line shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame420 {
+    /// Bits of the first shown luma line, then of the chroma row it uses in
+    /// each chroma plane.
+    pub plane_starts: [u64; 3],
+    /// Lines in the frame's luma plane.
+    pub lines: u64,
+    /// Line of the frame shown first.
+    pub first_line: u64,
+    /// Whether the first shown line is the second of a pair sharing chroma.
+    pub line_parity: u32,
+}
+
+impl Frame420 {
+    /// Frames follow each other from the start of the data, shifted by
+    /// however far `start_bit` is into a line, and have `frame_height` lines.
+    /// If it is 0 there is a single frame as tall as fits in the data.
+    pub fn locate(
+        format: YuvFormat,
+        data_len: usize,
+        start_bit: u64,
+        target_width: u32,
+        frame_height: u32,
+    ) -> Self {
+        let width = u64::from(target_width.max(1));
+        let line_bits = width * 8;
+        let origin = start_bit % line_bits;
+
+        let frame_height = match frame_height {
+            0 => (data_len as u64 * 8).saturating_sub(origin) / (line_bits * 3 / 2),
+            frame_height => u64::from(frame_height),
+        };
+
+        let chroma_width = width.div_ceil(2);
+        let chroma_height = frame_height.div_ceil(2);
+        let chroma_plane_bits = chroma_width * chroma_height * 8;
+
+        let frame_bits = frame_height * line_bits + chroma_plane_bits * 2;
+        let frame = (start_bit - origin).checked_div(frame_bits).unwrap_or(0);
+        let frame_start = origin + frame * frame_bits;
+        let first_line = (start_bit - frame_start) / line_bits;
+        let chroma_row = first_line / 2;
+
+        let chroma_start = frame_start + frame_height * line_bits;
+        let plane_starts = match format {
+            YuvFormat::Nv12 | YuvFormat::Nv21 => {
+                let uv = chroma_start + chroma_row * chroma_width * 16;
+                [start_bit, uv, uv]
+            }
+            _ => {
+                let row = chroma_row * chroma_width * 8;
+                [
+                    start_bit,
+                    chroma_start + row,
+                    chroma_start + chroma_plane_bits + row,
+                ]
+            }
+        };
+
+        Self {
+            plane_starts,
+            lines: frame_height,
+            first_line,
+            line_parity: (first_line % 2) as u32,
+        }
+    }
+
+    /// Reads `luma_bits` bits of luma and the chroma for them. The planes
+    /// are read one after the other into the window, each padded to the same
+    /// whole number of words.
+    pub fn read(&self, data: &[u8], byte_order: ByteOrder, luma_bits: u64) -> DataWindow {
+        let bit_offset = (self.plane_starts[0] % 8) as u32;
+        // Keep a word spare, like the chunky layouts do
+        let plane_len = ((luma_bits + u64::from(bit_offset)).div_ceil(32) + 1) as usize * 4;
+
+        let mut bytes = Vec::with_capacity(plane_len * 3);
+        for plane_start in self.plane_starts {
+            let (plane_bytes, lead_bits) = byte_order.read(data, plane_start / 8, plane_len);
+            let plane_bytes = plane_bytes
+                .get(lead_bits as usize / 8..)
+                .unwrap_or_default();
+            let plane_bytes = plane_bytes.get(..plane_len).unwrap_or(plane_bytes);
+
+            bytes.extend_from_slice(plane_bytes);
+            bytes.resize(bytes.len() + plane_len - plane_bytes.len(), 0);
+        }
+
+        DataWindow {
+            plane_stride: plane_len as u32 * 8,
+            line_parity: self.line_parity,
+            ..DataWindow::new(&bytes, bit_offset)
+        }
+    }
+}
+
+/// Where the Y, U and V samples of the pixel at `x`, `y` are in `window`, the
+/// same way `yuv_sample_bits` in `shader.wgsl` finds them.
+pub fn sample_bits(
+    format: YuvFormat,
+    window: &DataWindow,
+    target_width: u32,
+    row_stride: RowStride,
+    x: u32,
+    y: u32,
+) -> [u64; 3] {
+    let bit_offset = u64::from(window.bit_offset);
+    let width = u64::from(target_width);
+    let (x, y) = (u64::from(x), u64::from(y));
+
+    if !format.is_420() {
+        // Padded rows start on a pair of their own
+        let (row_start, pixel_index) = match row_stride.bits() {
+            0 => (0, y * width + x),
+            bits => (y * u64::from(bits), x),
+        };
+        let pair = row_start + (pixel_index / 2) * 32 + bit_offset;
+        let luma = (pixel_index % 2) * 16;
+        return match format {
+            YuvFormat::Uyvy => [pair + 8 + luma, pair, pair + 16],
+            _ => [pair + luma, pair + 8, pair + 24],
+        };
+    }
+
+    let plane_stride = u64::from(window.plane_stride);
+    let chroma_width = width.div_ceil(2);
+    let chroma_row = (y + u64::from(window.line_parity)) / 2;
+
+    let luma = (y * width + x) * 8 + bit_offset;
+    match format {
+        YuvFormat::Nv12 | YuvFormat::Nv21 => {
+            let uv = plane_stride + (chroma_row * chroma_width + x / 2) * 16 + bit_offset;
+            match format {
+                YuvFormat::Nv21 => [luma, uv + 8, uv],
+                _ => [luma, uv, uv + 8],
+            }
+        }
+        _ => {
+            let chroma = (chroma_row * chroma_width + x / 2) * 8 + bit_offset;
+            let first = plane_stride + chroma;
+            let second = plane_stride * 2 + chroma;
+            match format {
+                YuvFormat::Yv12 => [luma, second, first],
+                _ => [luma, first, second],
+            }
+        }
+    }
+}
+
+/// Color matrix used to turn YUV into RGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum YuvMatrix {
+    #[default]
+    Bt601,
+    Bt709,
+}
+
+impl YuvMatrix {
+    pub const ALL: &'static [Self] = &[Self::Bt601, Self::Bt709];
+
+    /// Value of the `yuv_matrix` uniform, matching the `YUV_MATRIX_*`
+    /// constants in `shader.wgsl`.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            YuvMatrix::Bt601 => 0,
+            YuvMatrix::Bt709 => 1,
+        }
+    }
+
+    /// The red and blue luma weights.
+    fn kr_kb(&self) -> (f32, f32) {
+        match self {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+        }
+    }
+}
+
+impl Display for YuvMatrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            YuvMatrix::Bt601 => "bt601",
+            YuvMatrix::Bt709 => "bt709",
+        })
+    }
+}
+
+impl FromStr for YuvMatrix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        YuvMatrix::ALL
+            .iter()
+            .find(|matrix| matrix.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown YUV matrix {s:?}, expected one of: bt601, bt709"))
+    }
+}
+
+/// Range of the YUV samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum YuvRange {
+    /// Luma from 16 to 235 and chroma from 16 to 240, as video uses
+    #[default]
+    Limited,
+    /// Every sample uses 0 to 255, as JPEG does
+    Full,
+}
+
+impl YuvRange {
+    pub const ALL: &'static [Self] = &[Self::Limited, Self::Full];
+
+    /// Value of the `yuv_range` uniform, matching the `YUV_RANGE_*`
+    /// constants in `shader.wgsl`.
+    pub fn shader_value(&self) -> u32 {
+        match self {
+            YuvRange::Limited => 0,
+            YuvRange::Full => 1,
+        }
+    }
+}
+
+impl Display for YuvRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            YuvRange::Limited => "limited",
+            YuvRange::Full => "full",
+        })
+    }
+}
+
+impl FromStr for YuvRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        YuvRange::ALL
+            .iter()
+            .find(|range| range.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown YUV range {s:?}, expected one of: limited, full"))
+    }
+}
+
+/// How YUV samples are turned into RGB, and how tall 4:2:0 frames are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct YuvSettings {
+    pub matrix: YuvMatrix,
+    pub range: YuvRange,
+    /// Lines in the luma plane of a 4:2:0 frame, or 0 to fit the data.
+    pub frame_height: u32,
+}
+
+impl YuvSettings {
+    /// Converts 8 bit samples to RGB, the same way `yuv_to_rgb` in
+    /// `shader.wgsl` does.
+    pub fn convert(&self, y: u8, u: u8, v: u8) -> Pixel {
+        let (mut y, mut u, mut v) = (f32::from(y), f32::from(u) - 128.0, f32::from(v) - 128.0);
+        if self.range == YuvRange::Limited {
+            y = (y - 16.0) * 255.0 / 219.0;
+            u *= 255.0 / 224.0;
+            v *= 255.0 / 224.0;
+        }
+
+        let (kr, kb) = self.matrix.kr_kb();
+        let kg = 1.0 - kr - kb;
+        let red = y + 2.0 * (1.0 - kr) * v;
+        let blue = y + 2.0 * (1.0 - kb) * u;
+        let green = (y - kr * red - kb * blue) / kg;
+
+        let channel = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+        Pixel {
+            red: channel(red),
+            green: channel(green),
+            blue: channel(blue),
+            alpha: u8::MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(settings: YuvSettings, y: u8, u: u8, v: u8) -> (u8, u8, u8) {
+        let pixel = settings.convert(y, u, v);
+        (pixel.red, pixel.green, pixel.blue)
+    }
+
+    #[test]
+    fn conversion() {
+        let limited_601 = YuvSettings::default();
+        assert_eq!(rgb(limited_601, 16, 128, 128), (0, 0, 0));
+        assert_eq!(rgb(limited_601, 235, 128, 128), (255, 255, 255));
+        assert_eq!(rgb(limited_601, 126, 128, 128), (128, 128, 128));
+
+        // 8 bit samples of the primaries come back within a step
+        let full_601 = YuvSettings {
+            range: YuvRange::Full,
+            ..Default::default()
+        };
+        assert_eq!(rgb(full_601, 128, 128, 128), (128, 128, 128));
+        assert_eq!(rgb(full_601, 76, 85, 255), (254, 0, 0));
+        assert_eq!(rgb(full_601, 150, 44, 21), (0, 255, 1));
+
+        let limited_709 = YuvSettings {
+            matrix: YuvMatrix::Bt709,
+            ..Default::default()
+        };
+        assert_eq!(rgb(limited_709, 63, 102, 240), (255, 1, 0));
+        assert_eq!(rgb(limited_709, 32, 240, 118), (1, 0, 255));
+    }
+
+    #[test]
+    fn locate_420_planes() {
+        // A 4x4 frame: 16 bytes of luma, then 4 bytes of each chroma plane
+        let frame = Frame420::locate(YuvFormat::I420, 24, 0, 4, 0);
+        assert_eq!(frame.plane_starts, [0, 128, 160]);
+        assert_eq!(frame.lines, 4);
+        assert_eq!(frame.line_parity, 0);
+
+        // Starting on the third line uses the second chroma row
+        let frame = Frame420::locate(YuvFormat::I420, 24, 64, 4, 0);
+        assert_eq!(frame.plane_starts, [64, 144, 176]);
+
+        let frame = Frame420::locate(YuvFormat::Nv12, 24, 32, 4, 0);
+        assert_eq!(frame.plane_starts, [32, 128, 128]);
+        assert_eq!(frame.line_parity, 1);
+
+        // Frames of a given height follow each other
+        let frame = Frame420::locate(YuvFormat::Nv21, 48, 192 + 8 * 4 * 3, 4, 4);
+        assert_eq!(frame.plane_starts, [288, 352, 352]);
+        assert_eq!(frame.first_line, 3);
+    }
+}